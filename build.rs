@@ -1,11 +1,32 @@
+extern crate naga_build as naga;
+
 use anyhow::{bail, Context, Result};
 use glob::glob;
 use naga::back::wgsl;
 use naga::front::glsl::Options;
 use naga::front::glsl::Frontend;
-use std::{fs::read_to_string, path::PathBuf};
+use std::path::{Path, PathBuf};
+
+#[path = "build/diagnostics.rs"]
+mod diagnostics;
+#[path = "build/reflect.rs"]
+mod reflect;
+#[path = "src/preprocess.rs"]
+mod preprocess;
+
+// Directory that `#include` paths are resolved against
+const INCLUDE_DIRECTORY: &str = "res/shaders";
+
+/// A shader compiled for one set of defines.
+pub struct CompiledShader {
+    /// Source path with the variant name spliced in, e.g. `text_effect.outline.frag`
+    pub name: PathBuf,
+    pub stage: naga::ShaderStage,
+    pub module: naga::Module,
+}
 
-pub fn load_shader(src_path: PathBuf) -> anyhow::Result<()> {
+/// Compiles a GLSL shader and each of its variants to WGSL next to its source.
+pub fn load_shader(src_path: PathBuf) -> Result<Vec<CompiledShader>> {
     let extension = src_path
         .extension()
         .context("File has no extension")?
@@ -18,38 +39,57 @@ pub fn load_shader(src_path: PathBuf) -> anyhow::Result<()> {
         _ => bail!("Unsupported shader: {}", src_path.display()),
     };
 
-    let src = read_to_string(src_path.clone())?;
-    let wgsl_path = src_path.with_extension(format!("{}.wgsl", extension));
-
-    let mut frontend = Frontend::default();
-    let options = Options::from(kind);
-    let module = match frontend.parse(&options, &src) {
+    let source = match preprocess::expand(Path::new(INCLUDE_DIRECTORY), &src_path) {
         Ok(it) => it,
-        Err(errors) => {
-            bail!(
-                "Failed to compile shader: {}\nErrors:\n{:#?}",
-                src_path.display(),
-                errors
-            );
-        }
+        Err(error) => bail!("error: {}", error),
     };
 
-    let flags = naga::valid::ValidationFlags::all();
-    let info =
-        naga::valid::Validator::new(flags, naga::valid::Capabilities::empty()).validate(&module)?;
-    std::fs::write(
-        wgsl_path,
-        wgsl::write_string(&module, &info, wgsl::WriterFlags::all())?,
-    )?;
+    let mut permutations = vec![(src_path.clone(), Vec::new())];
+    for variant in &source.variants {
+        let name = src_path.with_extension(format!("{}.{}", variant.name, extension));
+        permutations.push((name, variant.defines.clone()));
+    }
+
+    let mut compiled = Vec::new();
+    for (name, defines) in permutations {
+        diagnostics::set_current_shader(&name);
+        let mut frontend = Frontend::default();
+        let mut options = Options::from(kind);
+        options.defines.extend(defines);
+        let module = match frontend.parse(&options, &source.code) {
+            Ok(it) => it,
+            Err(errors) => bail!(diagnostics::parse_errors(&source, &errors)),
+        };
 
-    Ok(())
+        let flags = naga::valid::ValidationFlags::all();
+        let info = match naga::valid::Validator::new(flags, naga::valid::Capabilities::empty())
+            .validate(&module)
+        {
+            Ok(it) => it,
+            Err(error) => bail!(diagnostics::validation_error(&source, &error)),
+        };
+        std::fs::write(
+            name.with_extension(format!("{}.wgsl", extension)),
+            wgsl::write_string(&module, &info, wgsl::WriterFlags::all())?,
+        )?;
+
+        compiled.push(CompiledShader {
+            name,
+            stage: kind,
+            module,
+        });
+    }
+
+    Ok(compiled)
 }
 
 fn main() -> Result<()> {
     // This tells cargo to rerun this script if something in /res/ changes.
     println!("cargo:rerun-if-changed=res/");
+    diagnostics::forward_warnings();
 
     // Collect all shaders recursively within /res/
+    // `.glsl` files are only ever included, never compiled on their own
     let shader_paths = {
         let mut data = Vec::new();
         data.extend(glob("./res/**/*.vert")?);
@@ -59,10 +99,30 @@ fn main() -> Result<()> {
     };
 
     // Process shaders
+    // Every shader is compiled so that all of their errors are reported at once
+    let mut reflection = reflect::Reflection::default();
+    let mut failed = 0;
     for glob_result in shader_paths {
         let shader_path = glob_result?;
-        load_shader(shader_path)?;
+        match load_shader(shader_path) {
+            Ok(compiled) => {
+                for shader in compiled {
+                    reflection.add(&shader.name, shader.stage, &shader.module)?;
+                }
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                failed += 1;
+            }
+        }
     }
+    if failed > 0 {
+        bail!("{} shader(s) failed to compile", failed);
+    }
+
+    // Rust types mirroring the shaders' uniform blocks and vertex inputs
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
+    std::fs::write(out_dir.join("shader_types.rs"), reflection.finish())?;
 
     Ok(())
 }
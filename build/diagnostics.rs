@@ -0,0 +1,158 @@
+//! Readable reports for shader compile errors and warnings.
+//!
+//! naga errors carry byte spans into the preprocessed GLSL, which are mapped back to the file
+//! and line they came from. They are printed the way rustc prints its own errors: the message,
+//! a `--> file:line:column` pointer and the offending source line with the span underlined.
+//! naga's own warnings go through `log`, which is forwarded to cargo as `cargo:warning` lines.
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::Write;
+use std::ops::Range;
+use std::path::Path;
+
+use naga::front::glsl::ParseError;
+use naga::valid::ValidationError;
+use naga::WithSpan;
+
+use crate::preprocess::Source;
+
+thread_local! {
+    // Shader being compiled, used to attribute naga's warnings to a file
+    static CURRENT_SHADER: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+struct CargoWarnings;
+
+impl log::Log for CargoWarnings {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        CURRENT_SHADER.with_borrow(|shader| {
+            for line in message.lines() {
+                if shader.is_empty() {
+                    println!("cargo:warning={}", line);
+                } else {
+                    println!("cargo:warning={}: {}", shader, line);
+                }
+            }
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Forwards warnings logged by naga to cargo.
+pub fn forward_warnings() {
+    if log::set_logger(&CargoWarnings).is_ok() {
+        log::set_max_level(log::LevelFilter::Warn);
+    }
+}
+
+/// Attributes warnings logged from now on to `path`.
+pub fn set_current_shader(path: &Path) {
+    CURRENT_SHADER.with_borrow_mut(|shader| *shader = path.display().to_string());
+}
+
+/// Formats every error of a failed GLSL parse.
+pub fn parse_errors(source: &Source, errors: &ParseError) -> String {
+    errors
+        .errors
+        .iter()
+        .map(|error| {
+            let span = error.meta.to_range().unwrap_or(0..0);
+            snippet(source, &error.kind.to_string(), &[(span, String::new())], &[])
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats a validation error with the spans naga attached to it and its chain of causes.
+pub fn validation_error(source: &Source, error: &WithSpan<ValidationError>) -> String {
+    let labels: Vec<_> = error
+        .spans()
+        .filter_map(|(span, label)| Some((span.to_range()?, label.clone())))
+        .collect();
+
+    let mut causes = Vec::new();
+    let mut cause = error.as_inner().source();
+    while let Some(error) = cause {
+        causes.push(error.to_string());
+        cause = error.source();
+    }
+
+    snippet(source, &error.as_inner().to_string(), &labels, &causes)
+}
+
+/// Renders `message` followed by each labelled span of `source` and the notes.
+fn snippet(
+    source: &Source,
+    message: &str,
+    labels: &[(Range<usize>, String)],
+    notes: &[String],
+) -> String {
+    let mut out = format!("error: {}\n", message);
+
+    let locations: Vec<_> = labels
+        .iter()
+        .map(|(span, label)| {
+            let (line, column, text) = location(&source.code, span.start);
+            let (path, line) = source.origin(line);
+            (path, line, column, text, span, label)
+        })
+        .collect();
+    let gutter = locations
+        .iter()
+        .map(|(_, line, ..)| line.to_string().len())
+        .max()
+        .unwrap_or(1);
+
+    if locations.is_empty() {
+        let (path, _) = source.origin(1);
+        let _ = writeln!(out, "{:gutter$}--> {}", "", path.display());
+    }
+
+    let mut previous_path: Option<&Path> = None;
+    for (i, (path, line, column, text, span, label)) in locations.iter().enumerate() {
+        // Spans in an included file get their own pointer, the way rustc marks other files
+        if previous_path != Some(*path) {
+            let arrow = if i == 0 { "-->" } else { ":::" };
+            let _ = writeln!(out, "{:gutter$}{} {}:{}:{}", "", arrow, path.display(), line, column);
+            previous_path = Some(*path);
+        }
+
+        // Underline up to the end of the first line of the span
+        let width = span
+            .len()
+            .min(text.len().saturating_sub(column - 1))
+            .max(1);
+        let _ = writeln!(out, "{:gutter$} |", "");
+        let _ = writeln!(out, "{:>gutter$} | {}", line, text);
+        let underline = format!("{}{} {}", " ".repeat(column - 1), "^".repeat(width), label);
+        let _ = writeln!(out, "{:gutter$} | {}", "", underline.trim_end());
+    }
+
+    for note in notes {
+        let _ = writeln!(out, "{:gutter$} = {}", "", note);
+    }
+
+    out
+}
+
+/// Returns the 1-based line and column of a byte offset, along with the text of its line.
+fn location(source: &str, offset: usize) -> (usize, usize, &str) {
+    let offset = offset.min(source.len());
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i);
+    let line = source[..line_start].matches('\n').count() + 1;
+    let column = source[line_start..offset].chars().count() + 1;
+    (line, column, source[line_start..line_end].trim_end_matches('\r'))
+}
@@ -0,0 +1,355 @@
+//! Generates Rust mirrors of shader uniform blocks and vertex inputs.
+//!
+//! Uniform blocks become `#[repr(C)]` structs laid out with the offsets naga computed for the
+//! block, with explicit padding fields, plus an `<FIELD>_OFFSET` constant per member. Blocks are
+//! padded to a multiple of 16 bytes, which downlevel backends require of uniform bindings. The
+//! inputs of each vertex shader become a vertex struct with `SIZE` and `DESC` constants. Inputs
+//! named `iName` are per-instance and go into a separate instance struct stepped per instance.
+//! Changing a shader therefore changes the Rust types, and any CPU code that no longer matches
+//! fails to compile.
+
+use anyhow::{bail, Result};
+use naga::{Binding, Handle, Module, ScalarKind, ShaderStage, Type, TypeInner, VectorSize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+// Uniform bindings whose size is not a multiple of this fail validation without
+// `DownlevelFlags::BUFFER_BINDINGS_NOT_16_BYTE_ALIGNED`
+const BLOCK_ALIGNMENT: u32 = 16;
+
+#[derive(Default)]
+pub struct Reflection {
+    // Generated uniform structs by name, with the shaders that declare them
+    uniforms: BTreeMap<String, (String, Vec<String>)>,
+    vertices: BTreeMap<String, String>,
+}
+
+impl Reflection {
+    pub fn add(&mut self, path: &Path, stage: ShaderStage, module: &Module) -> Result<()> {
+        let file = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        for (_, global) in module.global_variables.iter() {
+            if global.space != naga::AddressSpace::Uniform {
+                continue;
+            }
+            let mut definitions = BTreeMap::new();
+            uniform_struct(module, global.ty, BLOCK_ALIGNMENT, &mut definitions)?;
+
+            for (name, code) in definitions {
+                match self.uniforms.get_mut(&name) {
+                    Some((existing, files)) if *existing == code => files.push(file.clone()),
+                    Some((_, files)) => bail!(
+                        "Uniform block `{}` in {} does not match its declaration in {}",
+                        name,
+                        file,
+                        files.join(", ")
+                    ),
+                    None => {
+                        self.uniforms.insert(name, (code, vec![file.clone()]));
+                    }
+                }
+            }
+        }
+
+        if stage == ShaderStage::Vertex {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            for step in [Step::Vertex, Step::Instance] {
+                let name = format!("{}{:?}", pascal_case(&stem), step);
+                if let Some(code) = vertex_struct(module, &name, &file, step)? {
+                    self.vertices.insert(name, code);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> String {
+        let mut out = String::from(
+            "// Generated by build.rs from the shaders in res/shaders. Do not edit.\n",
+        );
+        for (name, (code, files)) in self.uniforms {
+            let _ = write!(
+                out,
+                "\n/// Uniform block `{}`, declared in {}.\n{}",
+                name,
+                files.join(", "),
+                code
+            );
+        }
+        for code in self.vertices.into_values() {
+            out.push('\n');
+            out.push_str(&code);
+        }
+        out
+    }
+}
+
+/// Generates the struct for a uniform block type and any structs nested in it. The struct is
+/// padded at the end to a multiple of `alignment`.
+fn uniform_struct(
+    module: &Module,
+    ty: Handle<Type>,
+    alignment: u32,
+    definitions: &mut BTreeMap<String, String>,
+) -> Result<()> {
+    let ty = &module.types[ty];
+    let Some(name) = &ty.name else {
+        bail!("Uniform struct has no name");
+    };
+    let TypeInner::Struct { members, span } = &ty.inner else {
+        bail!("Uniform `{}` is not a block", name);
+    };
+
+    let span = span.next_multiple_of(alignment);
+    let mut fields = String::new();
+    let mut offsets = String::new();
+    let mut cursor = 0;
+    for member in members {
+        let Some(member_name) = &member.name else {
+            bail!("Uniform block `{}` has an unnamed member", name);
+        };
+        let field = snake_case(member_name);
+        if member.offset > cursor {
+            let _ = writeln!(
+                fields,
+                "    pub _pad{}: [u8; {}],",
+                cursor,
+                member.offset - cursor
+            );
+        }
+
+        let (rust_type, size) = uniform_type(module, member.ty, definitions)?;
+        let _ = writeln!(fields, "    pub {}: {},", field, rust_type);
+        let _ = writeln!(
+            offsets,
+            "    pub const {}_OFFSET: wgpu::BufferAddress = {};",
+            field.to_uppercase(),
+            member.offset
+        );
+        cursor = member.offset + size;
+    }
+    if span > cursor {
+        let _ = writeln!(fields, "    pub _pad{}: [u8; {}],", cursor, span - cursor);
+    }
+
+    let code = format!(
+        "#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct {name} {{
+{fields}}}
+
+unsafe impl bytemuck::Pod for {name} {{}}
+unsafe impl bytemuck::Zeroable for {name} {{}}
+
+// Offsets are generated for every member, whether or not Rust code writes it on its own
+#[allow(dead_code)]
+impl {name} {{
+{offsets}}}
+
+const _: () = assert!(std::mem::size_of::<{name}>() == {span});
+"
+    );
+    definitions.insert(name.clone(), code);
+
+    Ok(())
+}
+
+/// Returns the Rust type of a uniform member and its size in bytes.
+fn uniform_type(
+    module: &Module,
+    ty: Handle<Type>,
+    definitions: &mut BTreeMap<String, String>,
+) -> Result<(String, u32)> {
+    let inner = &module.types[ty].inner;
+    Ok(match *inner {
+        TypeInner::Scalar(scalar) => (scalar_type(scalar.kind, scalar.width)?.to_string(), 4),
+        TypeInner::Vector { size, scalar } => (
+            format!("[{}; {}]", scalar_type(scalar.kind, scalar.width)?, size as u32),
+            4 * size as u32,
+        ),
+        // Columns of three or four rows are padded to 16 bytes in uniform blocks
+        TypeInner::Matrix {
+            columns,
+            rows: VectorSize::Tri | VectorSize::Quad,
+            scalar,
+        } => (
+            format!(
+                "[[{}; 4]; {}]",
+                scalar_type(scalar.kind, scalar.width)?,
+                columns as u32
+            ),
+            16 * columns as u32,
+        ),
+        TypeInner::Array {
+            base,
+            size: naga::ArraySize::Constant(count),
+            stride,
+        } => {
+            let (element, element_size) = uniform_type(module, base, definitions)?;
+            if element_size != stride {
+                bail!(
+                    "Uniform arrays with padded elements are not supported, use vec4 elements"
+                );
+            }
+            (format!("[{}; {}]", element, count), stride * count.get())
+        }
+        TypeInner::Struct { span, .. } => {
+            uniform_struct(module, ty, 1, definitions)?;
+            (module.types[ty].name.clone().unwrap_or_default(), span)
+        }
+        _ => bail!("Unsupported uniform member type {:?}", inner),
+    })
+}
+
+/// Which vertex buffer a vertex shader input is read from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    Vertex,
+    Instance,
+}
+
+impl Step {
+    fn of(input_name: &str) -> Self {
+        let mut chars = input_name.chars();
+        match (chars.next(), chars.next()) {
+            (Some('i'), Some(c)) if c.is_uppercase() => Step::Instance,
+            _ => Step::Vertex,
+        }
+    }
+}
+
+/// Generates the vertex or instance struct for the location inputs of a vertex shader's entry
+/// point, if it has any with that step.
+fn vertex_struct(module: &Module, name: &str, file: &str, step: Step) -> Result<Option<String>> {
+    let Some(entry_point) = module.entry_points.first() else {
+        bail!("{} has no entry point", file);
+    };
+
+    let mut inputs = Vec::new();
+    for argument in &entry_point.function.arguments {
+        let Some(Binding::Location { location, .. }) = argument.binding else {
+            continue;
+        };
+        let (rust_type, format, size) = match module.types[argument.ty].inner {
+            TypeInner::Scalar(scalar) => {
+                let rust_type = scalar_type(scalar.kind, scalar.width)?;
+                (rust_type.to_string(), vertex_format(rust_type, 1), 4)
+            }
+            TypeInner::Vector { size, scalar } => {
+                let rust_type = scalar_type(scalar.kind, scalar.width)?;
+                (
+                    format!("[{}; {}]", rust_type, size as u32),
+                    vertex_format(rust_type, size as u32),
+                    4 * size as u32,
+                )
+            }
+            ref inner => bail!("Unsupported vertex input type {:?} in {}", inner, file),
+        };
+        let input_name = argument.name.clone().unwrap_or_default();
+        if Step::of(&input_name) != step {
+            continue;
+        }
+        inputs.push((location, input_name, rust_type, format, size));
+    }
+    if inputs.is_empty() {
+        return Ok(None);
+    }
+    inputs.sort_by_key(|(location, ..)| *location);
+
+    let mut fields = String::new();
+    let mut attributes = String::new();
+    let mut offset = 0;
+    for (location, input_name, rust_type, format, size) in inputs {
+        // `aPosition` and `iPosition` become `position`
+        let field_name = match input_name.strip_prefix(['a', 'i']) {
+            Some(rest) if rest.starts_with(char::is_uppercase) => snake_case(rest),
+            _ => snake_case(&input_name),
+        };
+        let _ = writeln!(fields, "    pub {}: {},", field_name, rust_type);
+        let _ = writeln!(
+            attributes,
+            "            wgpu::VertexAttribute {{ format: wgpu::VertexFormat::{}, offset: {}, shader_location: {} }},",
+            format, offset, location
+        );
+        offset += size;
+    }
+
+    let description = match step {
+        Step::Vertex => "Vertex input",
+        Step::Instance => "Per-instance input",
+    };
+    Ok(Some(format!(
+        "/// {description} of {file}.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct {name} {{
+{fields}}}
+
+unsafe impl bytemuck::Pod for {name} {{}}
+unsafe impl bytemuck::Zeroable for {name} {{}}
+
+impl {name} {{
+    pub const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as wgpu::BufferAddress;
+    pub const DESC: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {{
+        array_stride: Self::SIZE,
+        step_mode: wgpu::VertexStepMode::{step:?},
+        attributes: &[
+{attributes}        ],
+    }};
+}}
+
+const _: () = assert!(std::mem::size_of::<{name}>() == {offset});
+"
+    )))
+}
+
+fn scalar_type(kind: ScalarKind, width: u8) -> Result<&'static str> {
+    Ok(match (kind, width) {
+        (ScalarKind::Float, 4) => "f32",
+        (ScalarKind::Sint, 4) => "i32",
+        (ScalarKind::Uint, 4) => "u32",
+        _ => bail!("Unsupported scalar {:?} of width {}", kind, width),
+    })
+}
+
+fn vertex_format(rust_type: &str, components: u32) -> String {
+    let base = match rust_type {
+        "f32" => "Float32",
+        "i32" => "Sint32",
+        _ => "Uint32",
+    };
+    match components {
+        1 => base.to_string(),
+        n => format!("{}x{}", base, n),
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+// Variants are named `stem.variant`, which becomes `StemVariant`
+fn pascal_case(name: &str) -> String {
+    name.split(['_', '.'])
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
@@ -1,6 +1,12 @@
 use std::sync::Arc;
+use std::time::Instant;
 
 use wgpu_renderer::renderer::Renderer;
+use wgpu_renderer::{
+    ComputeBinding, ComputePipelineId, DrawList, FontId, MaterialId, MeshId, MsdfText, PostEffect,
+    Readback, Rect, RenderError, RichText, Shadows, ShapeInstance, StorageBufferId, Text,
+    TextInput, TextStyle, UniformLayout, Vertex,
+};
 use winit::{
     application::ApplicationHandler,
     event::WindowEvent,
@@ -8,6 +14,98 @@ use winit::{
     window::WindowAttributes,
 };
 
+// Fades shapes in and out over time
+const PULSE_SHADER: &str = r#"
+struct Globals {
+    screen_size: vec2<f32>,
+    view: mat4x4<f32>,
+}
+
+struct Pulse {
+    time: f32,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(1) @binding(0) var<uniform> pulse: Pulse;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>, @location(1) color: vec4<f32>) -> VertexOutput {
+    let ndc = position / globals.screen_size * vec2(2.0, -2.0) + vec2(-1.0, 1.0);
+    return VertexOutput(vec4(ndc, 0.0, 1.0), color);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let alpha = 0.5 + 0.5 * sin(pulse.time * 3.0);
+    return vec4(in.color.rgb, in.color.a * alpha);
+}
+"#;
+
+const PARTICLE_COUNT: u32 = 64;
+
+/// Particles moved on the GPU by particles.comp and read back for drawing.
+struct Particles {
+    pipeline: ComputePipelineId,
+    particles: StorageBufferId,
+    params: StorageBufferId,
+    readback: Option<Readback>,
+    // Position and velocity of each particle
+    state: Vec<[f32; 4]>,
+}
+
+impl Particles {
+    fn new(renderer: &mut Renderer) -> Result<Self, RenderError> {
+        let pipeline =
+            renderer.create_compute_pipeline(include_str!("../res/shaders/particles.comp.wgsl"))?;
+        let state: Vec<[f32; 4]> = (0..PARTICLE_COUNT)
+            .map(|i| {
+                let angle = i as f32 * 0.7;
+                [400.0, 300.0, angle.cos() * 120.0, angle.sin() * 120.0]
+            })
+            .collect();
+        let particles = renderer.create_storage_buffer(16 * PARTICLE_COUNT as u64);
+        renderer.write_storage_buffer(particles, 0, &state)?;
+        let params = renderer.create_storage_buffer(16);
+
+        Ok(Self {
+            pipeline,
+            particles,
+            params,
+            readback: None,
+            state,
+        })
+    }
+
+    fn update(&mut self, renderer: &mut Renderer, dt: f32) -> Result<(), RenderError> {
+        if let Some(readback) = &self.readback {
+            match readback.try_take_as::<[f32; 4]>() {
+                Some(state) => self.state = state?,
+                None => return Ok(()),
+            }
+        }
+
+        // SimParams: bounds, dt and count
+        let bounds = [renderer.width(), renderer.height()];
+        renderer.write_storage_buffer(self.params, 0, &[bounds[0], bounds[1], dt])?;
+        renderer.write_storage_buffer(self.params, 12, &[PARTICLE_COUNT])?;
+        renderer.dispatch_compute(
+            self.pipeline,
+            &[
+                ComputeBinding::Buffer(self.particles),
+                ComputeBinding::Buffer(self.params),
+            ],
+            [PARTICLE_COUNT.div_ceil(64), 1, 1],
+        )?;
+        self.readback = Some(renderer.read_storage_buffer(self.particles)?);
+        Ok(())
+    }
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
 
@@ -23,6 +121,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 struct RenderApp {
     renderer: Option<Renderer>,
     window: Option<Arc<winit::window::Window>>,
+    input: TextInput,
+    pulse: Option<MaterialId>,
+    particles: Option<Particles>,
+    background: Option<MeshId>,
+    start: Instant,
+    last_frame: Instant,
 }
 
 impl RenderApp {
@@ -30,6 +134,12 @@ impl RenderApp {
         Self {
             renderer: None,
             window: None,
+            input: TextInput::new(Rect::new(100.0, 200.0, 300.0, 40.0), 16.0),
+            pulse: None,
+            particles: None,
+            background: None,
+            start: Instant::now(),
+            last_frame: Instant::now(),
         }
     }
 }
@@ -51,8 +161,65 @@ impl ApplicationHandler for RenderApp {
             );
 
             let size = window.inner_size();
-            let renderer = pollster::block_on(Renderer::new(window.clone(), size));
+            let mut renderer = pollster::block_on(Renderer::with_pipeline_cache(
+                window.clone(),
+                size,
+                "target/pipeline_cache",
+            ));
+            if let Some(path) = renderer.pipeline_cache_path() {
+                log::info!("Pipeline cache: {}", path.display());
+            }
+            self.pulse = renderer
+                .create_material(PULSE_SHADER, UniformLayout::of::<f32>())
+                .inspect_err(|e| log::error!("{e}"))
+                .ok();
+            self.particles = Particles::new(&mut renderer)
+                .inspect_err(|e| log::error!("{e}"))
+                .ok();
+            // Static gradient floor, uploaded once
+            let (top, bottom) = ([0.1, 0.1, 0.2, 1.0], [0.25, 0.2, 0.3, 1.0]);
+            self.background = Some(renderer.create_mesh(
+                &[
+                    Vertex::with_color(0.0, 480.0, top),
+                    Vertex::with_color(800.0, 480.0, top),
+                    Vertex::with_color(800.0, 600.0, bottom),
+                    Vertex::with_color(0.0, 600.0, bottom),
+                ],
+                &[0, 1, 2, 0, 2, 3],
+            ));
+            renderer.preload_msdf_glyphs(FontId::default(), "Scalable");
+            renderer.set_ambient_light([0.35, 0.35, 0.45]);
+            renderer.add_point_light(
+                glam::vec2(220.0, 250.0),
+                [1.0, 0.8, 0.6],
+                350.0,
+                1.5,
+                Shadows::Soft { softness: 12.0 },
+            );
+            renderer.add_point_light(
+                glam::vec2(520.0, 260.0),
+                [0.4, 0.6, 1.0],
+                300.0,
+                1.2,
+                Shadows::Hard,
+            );
+            renderer.set_post_effects(&[
+                PostEffect::Bloom {
+                    threshold: 0.7,
+                    intensity: 0.8,
+                    radius: 2.0,
+                },
+                PostEffect::Vignette {
+                    intensity: 0.4,
+                    radius: 0.6,
+                    softness: 0.5,
+                },
+            ]);
+            renderer.set_profiling(true);
+            #[cfg(feature = "hot-reload")]
+            renderer.watch_shaders();
 
+            window.set_ime_allowed(true);
             window.request_redraw();
             self.window = Some(window);
             self.renderer = Some(renderer);
@@ -65,6 +232,12 @@ impl ApplicationHandler for RenderApp {
         _window_id: winit::window::WindowId,
         event: WindowEvent,
     ) {
+        if let Some(renderer) = &mut self.renderer
+            && self.input.handle_event(renderer, &event)
+        {
+            return;
+        }
+
         match event {
             WindowEvent::CloseRequested => {
                 event_loop.exit();
@@ -76,17 +249,137 @@ impl ApplicationHandler for RenderApp {
             }
             WindowEvent::RedrawRequested => {
                 if let Some(renderer) = &mut self.renderer {
-                    // Render shapes
-                    renderer.queue_rectangle(50.0, 50.0, 100.0, 80.0, [1.0, 0.0, 0.0, 1.0]);
-                    renderer.queue_square(300.0, 100.0, 60.0, [0.0, 1.0, 0.0, 1.0]);
-                    renderer.queue_circle(600.0, 150.0, 40.0, [0.0, 0.0, 1.0, 1.0]);
+                    #[cfg(feature = "hot-reload")]
+                    if let Err(e) = renderer.poll_shader_changes() {
+                        log::error!("{e}");
+                    }
+
+                    // Work other than queueing shapes and text goes through the renderer before
+                    // the frame starts. What is queued here is drawn in the frame as well
+                    let time = self.start.elapsed().as_secs_f32();
+                    if let Some(pulse) = self.pulse {
+                        let _ = renderer.set_material_uniforms(pulse, &time);
+                    }
+                    if let Some(particles) = &mut self.particles {
+                        let dt = self.last_frame.elapsed().as_secs_f32();
+                        self.last_frame = Instant::now();
+                        if let Err(e) = particles.update(renderer, dt) {
+                            log::error!("{e}");
+                        }
+                    }
+                    // The text input measures its text, so it is drawn on the renderer too
+                    renderer.queue_rectangle(100.0, 200.0, 300.0, 40.0, [0.15, 0.15, 0.15, 1.0]);
+                    self.input.draw(renderer);
+                    // Stats of the previous frame
+                    let stats = renderer.frame_stats();
+
+                    match renderer.begin_frame() {
+                        Ok(mut frame) => {
+                            if let Some(background) = self.background {
+                                frame.draw_mesh(background, glam::Affine2::IDENTITY, [1.0; 4]);
+                            }
+
+                            // Render shapes, which block the point lights
+                            frame.set_casting_shadows(true);
+                            frame.queue_rectangle(50.0, 50.0, 100.0, 80.0, [1.0, 0.0, 0.0, 1.0]);
+                            frame.queue_square(300.0, 100.0, 60.0, [0.0, 1.0, 0.0, 1.0]);
+                            frame.queue_circle(600.0, 150.0, 40.0, [0.0, 0.0, 1.0, 1.0]);
+                            frame.set_casting_shadows(false);
+
+                            // Shapes drawn with a custom material
+                            if let Some(pulse) = self.pulse {
+                                frame.set_material(Some(pulse));
+                                frame.queue_circle(700.0, 80.0, 30.0, [1.0, 0.5, 0.0, 1.0]);
+                                frame.set_material(None);
+                            }
+
+                            // Shapes recorded on a worker thread
+                            let orbit = std::thread::spawn(move || {
+                                let mut draw_list = DrawList::new(0);
+                                for i in 0..6 {
+                                    let angle = time + (i as f32) * std::f32::consts::TAU / 6.0;
+                                    let (x, y) =
+                                        (600.0 + angle.cos() * 70.0, 150.0 + angle.sin() * 70.0);
+                                    draw_list.queue_circle(x, y, 8.0, [1.0, 1.0, 1.0, 0.8]);
+                                }
+                                draw_list
+                            });
+                            if let Ok(draw_list) = orbit.join() {
+                                frame.submit(draw_list);
+                            }
+
+                            // Particles simulated by a compute shader
+                            if let Some(particles) = &self.particles {
+                                let instances: Vec<_> = particles
+                                    .state
+                                    .iter()
+                                    .map(|[x, y, vx, vy]| {
+                                        let color = [0.6, 0.8, 1.0, 1.0];
+                                        ShapeInstance::rectangle(x - 2.0, y - 2.0, 4.0, 4.0, color)
+                                            .with_rotation(vy.atan2(*vx))
+                                    })
+                                    .collect();
+                                frame.queue_rectangle_instances(&instances);
+                            }
+
+                            // Queue text
+                            frame.queue_styled_text(
+                                "Hello, WGPU!",
+                                (100.0, 300.0),
+                                &TextStyle::new(32.0, [1.0, 1.0, 1.0, 1.0])
+                                    .with_outline([0.0, 0.0, 0.0, 1.0], 2.0, 0.0)
+                                    .with_shadow([0.0, 0.0, 0.0, 0.6], (4.0, 4.0), 3.0),
+                            );
+                            frame.queue_text(
+                                "Rectangle | Square | Circle",
+                                (350.0, 350.0),
+                                16.0,
+                                [1.0, 1.0, 0.0, 1.0],
+                            );
+                            frame.queue_text_object(&Text {
+                                position: (0.0, 450.0).into(),
+                                bounds: (800.0, 150.0).into(),
+                                text: "Centered and wrapped inside its bounds".to_string(),
+                                visible: true,
+                                centered: true,
+                                ..Default::default()
+                            });
+                            frame.queue_rich_text(
+                                &RichText::new(100.0, 400.0)
+                                    .span("Score: ", TextStyle::new(16.0, [0.8, 0.8, 0.8, 1.0]))
+                                    .span("1200", TextStyle::new(24.0, [1.0, 0.8, 0.0, 1.0])),
+                            );
+
+                            frame.queue_msdf_text(&MsdfText {
+                                text: "Scalable".to_string(),
+                                position: (600.0, 250.0).into(),
+                                size: 40.0,
+                                rotation: 0.2,
+                                ..Default::default()
+                            });
 
-                    // Queue text
-                    renderer.queue_text("Hello, WGPU!", (100.0, 300.0), 32.0, [1.0, 1.0, 1.0, 1.0]);
-                    renderer.queue_text("Rectangle | Square | Circle", (350.0, 350.0), 16.0, [1.0, 1.0, 0.0, 1.0]);
+                            let gpu_time = stats.gpu_time().map_or("-".to_string(), |time| {
+                                format!("{:.2} ms", time.as_secs_f64() * 1000.0)
+                            });
+                            frame.queue_text(
+                                &format!(
+                                    "GPU {} | CPU {:.2} ms | {} draws | {} triangles | {} bytes",
+                                    gpu_time,
+                                    stats.cpu_encode_time.as_secs_f64() * 1000.0,
+                                    stats.draw_calls,
+                                    stats.triangles,
+                                    stats.bytes_uploaded,
+                                ),
+                                (10.0, 580.0),
+                                14.0,
+                                [0.8, 0.8, 0.8, 1.0],
+                            );
 
-                    // Render frame (which includes shapes and text)
-                    let _ = renderer.render_frame();
+                            // Draw and present the shapes and text queued on the frame
+                            frame.end_frame();
+                        }
+                        Err(e) => log::warn!("Skipping frame: {e}"),
+                    }
                 }
 
                 if let Some(window) = &self.window {
@@ -0,0 +1,13 @@
+#version 450
+
+layout(set = 1, binding = 0) uniform texture2D tPage;
+layout(set = 1, binding = 1) uniform sampler sPage;
+
+layout(location = 0) in vec2 vUv;
+layout(location = 1) in vec4 vColor;
+
+layout(location = 0) out vec4 fColor;
+
+void main() {
+    fColor = texture(sampler2D(tPage, sPage), vUv) * vColor;
+}
@@ -0,0 +1,28 @@
+struct FragmentOutput {
+    @location(0) fColor: vec4<f32>,
+}
+
+@group(1) @binding(0) 
+var tPage: texture_2d<f32>;
+@group(1) @binding(1) 
+var sPage: sampler;
+var<private> vUv_1: vec2<f32>;
+var<private> vColor_1: vec4<f32>;
+var<private> fColor: vec4<f32>;
+
+fn main_1() {
+    let _e6: vec2<f32> = vUv_1;
+    let _e7: vec4<f32> = textureSample(tPage, sPage, _e6);
+    let _e8: vec4<f32> = vColor_1;
+    fColor = (_e7 * _e8);
+    return;
+}
+
+@fragment 
+fn main(@location(0) vUv: vec2<f32>, @location(1) vColor: vec4<f32>) -> FragmentOutput {
+    vUv_1 = vUv;
+    vColor_1 = vColor;
+    main_1();
+    let _e15: vec4<f32> = fColor;
+    return FragmentOutput(_e15);
+}
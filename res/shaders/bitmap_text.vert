@@ -0,0 +1,16 @@
+#version 450
+
+#include "globals.glsl"
+
+layout(location = 0) in vec2 aPosition;
+layout(location = 1) in vec2 aUv;
+layout(location = 2) in vec4 aColor;
+
+layout(location = 0) out vec2 vUv;
+layout(location = 1) out vec4 vColor;
+
+void main() {
+    gl_Position = screen_to_clip(aPosition);
+    vUv = aUv;
+    vColor = aColor;
+}
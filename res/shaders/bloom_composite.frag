@@ -0,0 +1,11 @@
+#version 450
+
+#include "post.glsl"
+
+// Adds the blurred bright parts in tAux back onto the scene, scaled by `params.x`
+void main() {
+    vec4 scene = texture(sampler2D(tInput, sInput), vUv);
+    vec3 bloom = texture(sampler2D(tAux, sInput), vUv).rgb;
+
+    fColor = vec4(scene.rgb + bloom * params.x, scene.a);
+}
@@ -0,0 +1,47 @@
+struct PostParams {
+    texel_size: vec2<f32>,
+    direction: vec2<f32>,
+    params: vec4<f32>,
+}
+
+struct FragmentOutput {
+    @location(0) fColor: vec4<f32>,
+}
+
+@group(0) @binding(0) 
+var tInput: texture_2d<f32>;
+@group(0) @binding(1) 
+var sInput: sampler;
+@group(0) @binding(2) 
+var<uniform> global: PostParams;
+@group(0) @binding(3) 
+var tAux: texture_2d<f32>;
+var<private> vUv_1: vec2<f32>;
+var<private> fColor: vec4<f32>;
+
+fn main_1() {
+    var scene: vec4<f32>;
+    var bloom: vec3<f32>;
+
+    let _e12: vec2<f32> = vUv_1;
+    let _e13: vec4<f32> = textureSample(tInput, sInput, _e12);
+    scene = _e13;
+    let _e16: vec2<f32> = vUv_1;
+    let _e17: vec4<f32> = textureSample(tAux, sInput, _e16);
+    bloom = _e17.xyz;
+    let _e20: vec4<f32> = scene;
+    let _e22: vec3<f32> = bloom;
+    let _e23: vec4<f32> = global.params;
+    let _e26: vec3<f32> = (_e20.xyz + (_e22 * _e23.x));
+    let _e27: vec4<f32> = scene;
+    fColor = vec4<f32>(_e26.x, _e26.y, _e26.z, _e27.w);
+    return;
+}
+
+@fragment 
+fn main(@location(0) vUv: vec2<f32>) -> FragmentOutput {
+    vUv_1 = vUv;
+    main_1();
+    let _e19: vec4<f32> = fColor;
+    return FragmentOutput(_e19);
+}
@@ -0,0 +1,12 @@
+#version 450
+
+#include "post.glsl"
+
+// Keeps the part of each color brighter than `params.x`
+void main() {
+    vec4 color = texture(sampler2D(tInput, sInput), vUv);
+    float brightness = max(color.r, max(color.g, color.b));
+    float factor = max(brightness - params.x, 0.0) / max(brightness, 0.0001);
+
+    fColor = vec4(color.rgb * factor, 1.0);
+}
@@ -0,0 +1,60 @@
+struct PostParams {
+    texel_size: vec2<f32>,
+    direction: vec2<f32>,
+    params: vec4<f32>,
+}
+
+struct FragmentOutput {
+    @location(0) fColor: vec4<f32>,
+}
+
+@group(0) @binding(0) 
+var tInput: texture_2d<f32>;
+@group(0) @binding(1) 
+var sInput: sampler;
+@group(0) @binding(2) 
+var<uniform> global: PostParams;
+@group(0) @binding(3) 
+var tAux: texture_2d<f32>;
+var<private> vUv_1: vec2<f32>;
+var<private> fColor: vec4<f32>;
+
+fn main_1() {
+    var color: vec4<f32>;
+    var brightness: f32;
+    var factor: f32;
+
+    let _e12: vec2<f32> = vUv_1;
+    let _e13: vec4<f32> = textureSample(tInput, sInput, _e12);
+    color = _e13;
+    let _e15: vec4<f32> = color;
+    let _e17: vec4<f32> = color;
+    let _e19: vec4<f32> = color;
+    let _e21: vec4<f32> = color;
+    let _e23: vec4<f32> = color;
+    let _e26: vec4<f32> = color;
+    let _e28: vec4<f32> = color;
+    let _e30: vec4<f32> = color;
+    let _e32: vec4<f32> = color;
+    let _e34: vec4<f32> = color;
+    brightness = max(_e26.x, max(_e32.y, _e34.z));
+    let _e39: f32 = brightness;
+    let _e40: vec4<f32> = global.params;
+    let _e44: f32 = brightness;
+    let _e45: vec4<f32> = global.params;
+    let _e52: f32 = brightness;
+    factor = (max((_e44 - _e45.x), 0f) / max(_e52, 0.0001f));
+    let _e57: vec4<f32> = color;
+    let _e59: f32 = factor;
+    let _e60: vec3<f32> = (_e57.xyz * _e59);
+    fColor = vec4<f32>(_e60.x, _e60.y, _e60.z, 1f);
+    return;
+}
+
+@fragment 
+fn main(@location(0) vUv: vec2<f32>) -> FragmentOutput {
+    vUv_1 = vUv;
+    main_1();
+    let _e19: vec4<f32> = fColor;
+    return FragmentOutput(_e19);
+}
@@ -0,0 +1,18 @@
+#version 450
+
+#include "post.glsl"
+
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+// One direction of a separable Gaussian blur, with taps `params.x` pixels apart
+void main() {
+    vec2 offset = direction * texel_size * params.x;
+
+    vec4 result = texture(sampler2D(tInput, sInput), vUv) * WEIGHTS[0];
+    for (int i = 1; i < 5; i++) {
+        result += texture(sampler2D(tInput, sInput), vUv + offset * float(i)) * WEIGHTS[i];
+        result += texture(sampler2D(tInput, sInput), vUv - offset * float(i)) * WEIGHTS[i];
+    }
+
+    fColor = result;
+}
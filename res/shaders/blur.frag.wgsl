@@ -0,0 +1,83 @@
+struct PostParams {
+    texel_size: vec2<f32>,
+    direction: vec2<f32>,
+    params: vec4<f32>,
+}
+
+struct FragmentOutput {
+    @location(0) fColor: vec4<f32>,
+}
+
+const WEIGHTS: array<f32, 5> = array<f32, 5>(0.227027f, 0.1945946f, 0.1216216f, 0.054054f, 0.016216f);
+
+@group(0) @binding(0) 
+var tInput: texture_2d<f32>;
+@group(0) @binding(1) 
+var sInput: sampler;
+@group(0) @binding(2) 
+var<uniform> global: PostParams;
+@group(0) @binding(3) 
+var tAux: texture_2d<f32>;
+var<private> vUv_1: vec2<f32>;
+var<private> fColor: vec4<f32>;
+
+fn main_1() {
+    var offset: vec2<f32>;
+    var result: vec4<f32>;
+    var i: i32 = 1i;
+    var local: array<f32, 5> = WEIGHTS;
+    var local_1: array<f32, 5> = WEIGHTS;
+
+    let _e12: vec2<f32> = global.direction;
+    let _e13: vec2<f32> = global.texel_size;
+    let _e15: vec4<f32> = global.params;
+    offset = ((_e12 * _e13) * _e15.x);
+    let _e20: vec2<f32> = vUv_1;
+    let _e21: vec4<f32> = textureSample(tInput, sInput, _e20);
+    result = (_e21 * 0.227027f);
+    loop {
+        let _e33: i32 = i;
+        if !((_e33 < 5i)) {
+            break;
+        }
+        {
+            let _e40: vec4<f32> = result;
+            let _e41: vec2<f32> = vUv_1;
+            let _e42: vec2<f32> = offset;
+            let _e43: i32 = i;
+            let _e47: vec2<f32> = vUv_1;
+            let _e48: vec2<f32> = offset;
+            let _e49: i32 = i;
+            let _e53: vec4<f32> = textureSample(tInput, sInput, (_e47 + (_e48 * f32(_e49))));
+            let _e54: i32 = i;
+            let _e58: f32 = local[_e54];
+            result = (_e40 + (_e53 * _e58));
+            let _e61: vec4<f32> = result;
+            let _e62: vec2<f32> = vUv_1;
+            let _e63: vec2<f32> = offset;
+            let _e64: i32 = i;
+            let _e68: vec2<f32> = vUv_1;
+            let _e69: vec2<f32> = offset;
+            let _e70: i32 = i;
+            let _e74: vec4<f32> = textureSample(tInput, sInput, (_e68 - (_e69 * f32(_e70))));
+            let _e75: i32 = i;
+            let _e79: f32 = local_1[_e75];
+            result = (_e61 + (_e74 * _e79));
+        }
+        continuing {
+            let _e37: i32 = i;
+            i = (_e37 + 1i);
+        }
+    }
+    let _e82: vec4<f32> = result;
+    fColor = _e82;
+    return;
+}
+
+@fragment 
+fn main(@location(0) vUv: vec2<f32>) -> FragmentOutput {
+    vUv_1 = vUv;
+    main_1();
+    let _e21: vec4<f32> = fColor;
+    return FragmentOutput(_e21);
+}
@@ -0,0 +1,34 @@
+#version 450
+
+#include "post.glsl"
+
+// Looks colors up in a LUT strip of `params.y` slices of `params.y` squared texels, one slice per
+// blue level, and blends the result in by `params.x`. With `params.z` set the target is sRGB, so
+// colors are encoded before the lookup.
+vec3 linear_to_srgb(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, vec3(lessThanEqual(color, vec3(0.0031308))));
+}
+
+void main() {
+    vec4 color = texture(sampler2D(tInput, sInput), vUv);
+    vec3 coord = clamp(color.rgb, 0.0, 1.0);
+    if (params.z > 0.5) {
+        coord = linear_to_srgb(coord);
+    }
+
+    float size = params.y;
+    float slice = coord.b * (size - 1.0);
+    float slice0 = floor(slice);
+    float slice1 = min(slice0 + 1.0, size - 1.0);
+    float x = coord.r * (size - 1.0) + 0.5;
+    float y = (coord.g * (size - 1.0) + 0.5) / size;
+
+    float width = size * size;
+    vec3 graded0 = texture(sampler2D(tAux, sInput), vec2((slice0 * size + x) / width, y)).rgb;
+    vec3 graded1 = texture(sampler2D(tAux, sInput), vec2((slice1 * size + x) / width, y)).rgb;
+    vec3 graded = mix(graded0, graded1, slice - slice0);
+
+    fColor = vec4(mix(color.rgb, graded, params.x), color.a);
+}
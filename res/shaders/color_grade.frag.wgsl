@@ -0,0 +1,137 @@
+struct PostParams {
+    texel_size: vec2<f32>,
+    direction: vec2<f32>,
+    params: vec4<f32>,
+}
+
+struct FragmentOutput {
+    @location(0) fColor: vec4<f32>,
+}
+
+@group(0) @binding(0) 
+var tInput: texture_2d<f32>;
+@group(0) @binding(1) 
+var sInput: sampler;
+@group(0) @binding(2) 
+var<uniform> global: PostParams;
+@group(0) @binding(3) 
+var tAux: texture_2d<f32>;
+var<private> vUv_1: vec2<f32>;
+var<private> fColor: vec4<f32>;
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    var color_1: vec3<f32>;
+    var low: vec3<f32>;
+    var high: vec3<f32>;
+
+    color_1 = color;
+    let _e13: vec3<f32> = color_1;
+    low = (_e13 * 12.92f);
+    let _e23: vec3<f32> = color_1;
+    high = ((1.055f * pow(_e23, vec3(0.41666666f))) - vec3(0.055f));
+    let _e39: vec3<f32> = color_1;
+    let _e48: vec3<f32> = high;
+    let _e49: vec3<f32> = low;
+    let _e53: vec3<f32> = color_1;
+    return mix(_e48, _e49, select(vec3(0f), vec3(1f), (_e53 <= vec3(0.0031308f))));
+}
+
+fn main_1() {
+    var color_2: vec4<f32>;
+    var coord: vec3<f32>;
+    var size: f32;
+    var slice: f32;
+    var slice0_: f32;
+    var slice1_: f32;
+    var x: f32;
+    var y: f32;
+    var width: f32;
+    var graded0_: vec3<f32>;
+    var graded1_: vec3<f32>;
+    var graded: vec3<f32>;
+
+    let _e12: vec2<f32> = vUv_1;
+    let _e13: vec4<f32> = textureSample(tInput, sInput, _e12);
+    color_2 = _e13;
+    let _e15: vec4<f32> = color_2;
+    let _e19: vec4<f32> = color_2;
+    coord = clamp(_e19.xyz, vec3(0f), vec3(1f));
+    let _e27: vec4<f32> = global.params;
+    if (_e27.z > 0.5f) {
+        {
+            let _e32: vec3<f32> = coord;
+            let _e33: vec3<f32> = linear_to_srgb(_e32);
+            coord = _e33;
+        }
+    }
+    let _e34: vec4<f32> = global.params;
+    size = _e34.y;
+    let _e37: vec3<f32> = coord;
+    let _e39: f32 = size;
+    slice = (_e37.z * (_e39 - 1f));
+    let _e45: f32 = slice;
+    slice0_ = floor(_e45);
+    let _e48: f32 = slice0_;
+    let _e51: f32 = size;
+    let _e54: f32 = slice0_;
+    let _e57: f32 = size;
+    slice1_ = min((_e54 + 1f), (_e57 - 1f));
+    let _e62: vec3<f32> = coord;
+    let _e64: f32 = size;
+    x = ((_e62.x * (_e64 - 1f)) + 0.5f);
+    let _e71: vec3<f32> = coord;
+    let _e73: f32 = size;
+    let _e79: f32 = size;
+    y = (((_e71.y * (_e73 - 1f)) + 0.5f) / _e79);
+    let _e82: f32 = size;
+    let _e83: f32 = size;
+    width = (_e82 * _e83);
+    let _e86: f32 = slice0_;
+    let _e87: f32 = size;
+    let _e89: f32 = x;
+    let _e91: f32 = width;
+    let _e93: f32 = y;
+    let _e95: f32 = slice0_;
+    let _e96: f32 = size;
+    let _e98: f32 = x;
+    let _e100: f32 = width;
+    let _e102: f32 = y;
+    let _e104: vec4<f32> = textureSample(tAux, sInput, vec2<f32>((((_e95 * _e96) + _e98) / _e100), _e102));
+    graded0_ = _e104.xyz;
+    let _e107: f32 = slice1_;
+    let _e108: f32 = size;
+    let _e110: f32 = x;
+    let _e112: f32 = width;
+    let _e114: f32 = y;
+    let _e116: f32 = slice1_;
+    let _e117: f32 = size;
+    let _e119: f32 = x;
+    let _e121: f32 = width;
+    let _e123: f32 = y;
+    let _e125: vec4<f32> = textureSample(tAux, sInput, vec2<f32>((((_e116 * _e117) + _e119) / _e121), _e123));
+    graded1_ = _e125.xyz;
+    let _e130: f32 = slice;
+    let _e131: f32 = slice0_;
+    let _e133: vec3<f32> = graded0_;
+    let _e134: vec3<f32> = graded1_;
+    let _e135: f32 = slice;
+    let _e136: f32 = slice0_;
+    graded = mix(_e133, _e134, vec3((_e135 - _e136)));
+    let _e141: vec4<f32> = color_2;
+    let _e144: vec4<f32> = global.params;
+    let _e146: vec4<f32> = color_2;
+    let _e148: vec3<f32> = graded;
+    let _e149: vec4<f32> = global.params;
+    let _e152: vec3<f32> = mix(_e146.xyz, _e148, vec3(_e149.x));
+    let _e153: vec4<f32> = color_2;
+    fColor = vec4<f32>(_e152.x, _e152.y, _e152.z, _e153.w);
+    return;
+}
+
+@fragment 
+fn main(@location(0) vUv: vec2<f32>) -> FragmentOutput {
+    vUv_1 = vUv;
+    main_1();
+    let _e19: vec4<f32> = fColor;
+    return FragmentOutput(_e19);
+}
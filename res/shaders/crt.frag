@@ -0,0 +1,21 @@
+#version 450
+
+#include "post.glsl"
+
+// Curves the screen by `params.y` and darkens every other row by `params.x`
+void main() {
+    vec2 centered = vUv * 2.0 - 1.0;
+    centered *= 1.0 + params.y * centered.yx * centered.yx;
+    vec2 uv = centered * 0.5 + 0.5;
+
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        fColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec4 color = texture(sampler2D(tInput, sInput), uv);
+    float row = uv.y / texel_size.y;
+    float scanline = 1.0 - params.x * (0.5 + 0.5 * cos(row * 3.14159265));
+
+    fColor = vec4(color.rgb * scanline, color.a);
+}
@@ -0,0 +1,72 @@
+struct PostParams {
+    texel_size: vec2<f32>,
+    direction: vec2<f32>,
+    params: vec4<f32>,
+}
+
+struct FragmentOutput {
+    @location(0) fColor: vec4<f32>,
+}
+
+@group(0) @binding(0) 
+var tInput: texture_2d<f32>;
+@group(0) @binding(1) 
+var sInput: sampler;
+@group(0) @binding(2) 
+var<uniform> global: PostParams;
+@group(0) @binding(3) 
+var tAux: texture_2d<f32>;
+var<private> vUv_1: vec2<f32>;
+var<private> fColor: vec4<f32>;
+
+fn main_1() {
+    var centered: vec2<f32>;
+    var uv: vec2<f32>;
+    var color: vec4<f32>;
+    var row: f32;
+    var scanline: f32;
+
+    let _e11: vec2<f32> = vUv_1;
+    centered = ((_e11 * 2f) - vec2(1f));
+    let _e18: vec2<f32> = centered;
+    let _e20: vec4<f32> = global.params;
+    let _e22: vec2<f32> = centered;
+    let _e25: vec2<f32> = centered;
+    centered = (_e18 * (vec2(1f) + ((_e20.y * _e22.yx) * _e25.yx)));
+    let _e31: vec2<f32> = centered;
+    uv = ((_e31 * 0.5f) + vec2(0.5f));
+    let _e38: vec2<f32> = uv;
+    let _e42: vec2<f32> = uv;
+    let _e47: vec2<f32> = uv;
+    let _e52: vec2<f32> = uv;
+    if ((((_e38.x < 0f) || (_e42.x > 1f)) || (_e47.y < 0f)) || (_e52.y > 1f)) {
+        {
+            fColor = vec4<f32>(0f, 0f, 0f, 1f);
+            return;
+        }
+    }
+    let _e63: vec2<f32> = uv;
+    let _e64: vec4<f32> = textureSample(tInput, sInput, _e63);
+    color = _e64;
+    let _e66: vec2<f32> = uv;
+    let _e68: vec2<f32> = global.texel_size;
+    row = (_e66.y / _e68.y);
+    let _e73: vec4<f32> = global.params;
+    let _e77: f32 = row;
+    let _e80: f32 = row;
+    scanline = (1f - (_e73.x * (0.5f + (0.5f * cos((_e80 * 3.1415927f))))));
+    let _e89: vec4<f32> = color;
+    let _e91: f32 = scanline;
+    let _e92: vec3<f32> = (_e89.xyz * _e91);
+    let _e93: vec4<f32> = color;
+    fColor = vec4<f32>(_e92.x, _e92.y, _e92.z, _e93.w);
+    return;
+}
+
+@fragment 
+fn main(@location(0) vUv: vec2<f32>) -> FragmentOutput {
+    vUv_1 = vUv;
+    main_1();
+    let _e19: vec4<f32> = fColor;
+    return FragmentOutput(_e19);
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) out vec2 vUv;
+
+// One triangle covering the screen, drawn with three vertices and no vertex buffer
+void main() {
+    vec2 position = vec2(float((gl_VertexIndex << 1) & 2), float(gl_VertexIndex & 2));
+    vUv = vec2(position.x, 1.0 - position.y);
+
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
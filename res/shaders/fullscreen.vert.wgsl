@@ -0,0 +1,32 @@
+struct VertexOutput {
+    @location(0) vUv: vec2<f32>,
+    @builtin(position) gl_Position: vec4<f32>,
+}
+
+var<private> vUv: vec2<f32>;
+var<private> gl_VertexIndex_1: u32;
+var<private> gl_Position: vec4<f32>;
+
+fn main_1() {
+    var position: vec2<f32>;
+
+    let _e2: u32 = gl_VertexIndex_1;
+    let _e10: u32 = gl_VertexIndex_1;
+    position = vec2<f32>(f32(((_e2 << 1u) & 2u)), f32((_e10 & 2u)));
+    let _e17: vec2<f32> = position;
+    let _e20: vec2<f32> = position;
+    vUv = vec2<f32>(_e17.x, (1f - _e20.y));
+    let _e25: vec2<f32> = position;
+    let _e30: vec2<f32> = ((_e25 * 2f) - vec2(1f));
+    gl_Position = vec4<f32>(_e30.x, _e30.y, 0f, 1f);
+    return;
+}
+
+@vertex 
+fn main(@builtin(vertex_index) gl_VertexIndex: u32) -> VertexOutput {
+    gl_VertexIndex_1 = gl_VertexIndex;
+    main_1();
+    let _e5: vec2<f32> = vUv;
+    let _e7: vec4<f32> = gl_Position;
+    return VertexOutput(_e5, _e7);
+}
@@ -0,0 +1,13 @@
+// Per-frame values shared by the screen-space shaders
+layout(binding = 0) uniform Globals {
+    vec2 screen_size;
+    mat4 view;
+};
+
+// Converts a position in pixels, with the origin at the top left, to clip space
+vec4 screen_to_clip(vec2 position) {
+    float ndc_x = (position.x / screen_size.x) * 2.0 - 1.0;
+    float ndc_y = 1.0 - (position.y / screen_size.y) * 2.0;
+
+    return vec4(ndc_x, ndc_y, 0.0, 1.0);
+}
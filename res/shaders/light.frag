@@ -0,0 +1,69 @@
+#version 450
+
+// Unlit shapes, drawn offscreen
+layout(set = 0, binding = 0) uniform texture2D tScene;
+layout(set = 0, binding = 1) uniform sampler sScene;
+
+#include "lights.glsl"
+
+// Written by shadow_map.comp
+layout(set = 0, binding = 3) readonly buffer ShadowMap {
+    float distances[];
+};
+
+// Normal maps drawn over the shapes, cleared to flat with zero coverage
+layout(set = 0, binding = 4) uniform texture2D tNormals;
+
+layout(location = 0) in vec2 vUv;
+
+layout(location = 0) out vec4 fColor;
+
+const int SHADOW_TAPS = 5;
+// Height of every light above normal-mapped surfaces, as a fraction of its radius
+const float LIGHT_HEIGHT = 0.25;
+
+// Fraction of light `i` reaching a fragment `offset` away from it. Soft shadows compare against
+// the shadow map over an arc `softness` pixels wide
+float visibility(uint i, vec2 offset) {
+    float softness = lights[i].softness;
+    if (softness < 0.0) {
+        return 1.0;
+    }
+
+    float dist = length(offset);
+    float angle = atan(offset.y, offset.x);
+    float spread = softness / max(dist, 1.0);
+
+    float lit = 0.0;
+    for (int tap = 0; tap < SHADOW_TAPS; tap++) {
+        float tap_angle = angle + spread * (float(tap) / float(SHADOW_TAPS - 1) - 0.5);
+        // Wrap back into -PI..PI
+        tap_angle = mod(tap_angle + PI, 2.0 * PI) - PI;
+        // Fragments on or inside the occluder are lit, those past its far side are not
+        float shadow_start = distances[i * SHADOW_RESOLUTION + shadow_texel(tap_angle)];
+        lit += dist <= shadow_start ? 1.0 : 0.0;
+    }
+    return lit / float(SHADOW_TAPS);
+}
+
+// Scales the scene by the ambient color plus every point light, each fading out quadratically
+// to nothing at its radius. Where a normal map covers the scene, lights are also scaled by the
+// cosine of their angle to its normal
+void main() {
+    vec4 scene = texture(sampler2D(tScene, sScene), vUv);
+    vec4 normals = texture(sampler2D(tNormals, sScene), vUv);
+    // Normal maps point green up, the screen's y axis points down
+    vec3 normal = normalize((normals.rgb * 2.0 - 1.0) * vec3(1.0, -1.0, 1.0));
+    vec3 light = ambient;
+
+    for (uint i = 0u; i < light_count; i++) {
+        vec2 offset = gl_FragCoord.xy - lights[i].position;
+        float falloff = clamp(1.0 - length(offset) / max(lights[i].radius, 0.0001), 0.0, 1.0);
+        vec3 to_light = normalize(vec3(-offset, LIGHT_HEIGHT * max(lights[i].radius, 1.0)));
+        float shading = mix(1.0, max(dot(normal, to_light), 0.0), normals.a);
+        light += lights[i].color * lights[i].intensity * falloff * falloff * shading *
+            visibility(i, offset);
+    }
+
+    fColor = vec4(scene.rgb * light, scene.a);
+}
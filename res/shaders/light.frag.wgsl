@@ -0,0 +1,236 @@
+struct Light {
+    position: vec2<f32>,
+    radius: f32,
+    intensity: f32,
+    color: vec3<f32>,
+    softness: f32,
+}
+
+struct LightParams {
+    ambient: vec3<f32>,
+    light_count: u32,
+    lights: array<Light, 64>,
+}
+
+struct ShadowMap {
+    distances: array<f32>,
+}
+
+struct FragmentOutput {
+    @location(0) fColor: vec4<f32>,
+}
+
+const SHADOW_RESOLUTION: u32 = 720u;
+const PI: f32 = 3.1415927f;
+const SHADOW_TAPS: i32 = 5i;
+const LIGHT_HEIGHT: f32 = 0.25f;
+
+@group(0) @binding(0) 
+var tScene: texture_2d<f32>;
+@group(0) @binding(1) 
+var sScene: sampler;
+@group(0) @binding(2) 
+var<uniform> global: LightParams;
+@group(0) @binding(3) 
+var<storage> global_1: ShadowMap;
+@group(0) @binding(4) 
+var tNormals: texture_2d<f32>;
+var<private> vUv_1: vec2<f32>;
+var<private> fColor: vec4<f32>;
+var<private> gl_FragCoord_1: vec4<f32>;
+
+fn shadow_texel_angle(texel: u32) -> f32 {
+    var texel_1: u32;
+
+    texel_1 = texel;
+    let _e12: u32 = texel_1;
+    return (((((f32(_e12) + 0.5f) / 720f) * 2f) * PI) - PI);
+}
+
+fn shadow_texel(angle: f32) -> u32 {
+    var angle_1: f32;
+    var u: f32;
+
+    angle_1 = angle;
+    let _e12: f32 = angle_1;
+    u = ((_e12 + PI) / 6.2831855f);
+    let _e19: f32 = u;
+    let _e27: f32 = u;
+    return min(u32((_e27 * 720f)), 719u);
+}
+
+fn visibility(i: u32, offset: vec2<f32>) -> f32 {
+    var i_1: u32;
+    var offset_1: vec2<f32>;
+    var softness: f32;
+    var dist: f32;
+    var angle_2: f32;
+    var spread: f32;
+    var lit: f32 = 0f;
+    var tap: i32 = 0i;
+    var tap_angle: f32;
+    var shadow_start: f32;
+    var local: f32;
+
+    i_1 = i;
+    offset_1 = offset;
+    let _e21: u32 = i_1;
+    let _e23: Light = global.lights[_e21];
+    softness = _e23.softness;
+    let _e26: f32 = softness;
+    if (_e26 < 0f) {
+        {
+            return 1f;
+        }
+    }
+    let _e31: vec2<f32> = offset_1;
+    dist = length(_e31);
+    let _e34: vec2<f32> = offset_1;
+    let _e36: vec2<f32> = offset_1;
+    let _e38: vec2<f32> = offset_1;
+    let _e40: vec2<f32> = offset_1;
+    angle_2 = atan2(_e38.y, _e40.x);
+    let _e44: f32 = softness;
+    let _e47: f32 = dist;
+    spread = (_e44 / max(_e47, 1f));
+    loop {
+        let _e56: i32 = tap;
+        if !((_e56 < SHADOW_TAPS)) {
+            break;
+        }
+        {
+            let _e62: f32 = angle_2;
+            let _e63: f32 = spread;
+            let _e64: i32 = tap;
+            tap_angle = (_e62 + (_e63 * ((f32(_e64) / 4f) - 0.5f)));
+            let _e76: f32 = tap_angle;
+            let _e81: f32 = tap_angle;
+            let _e82: f32 = (_e81 + PI);
+            tap_angle = ((_e82 - (floor((_e82 / 6.2831855f)) * 6.2831855f)) - PI);
+            let _e91: u32 = i_1;
+            let _e94: f32 = tap_angle;
+            let _e95: u32 = shadow_texel(_e94);
+            let _e98: f32 = global_1.distances[((_e91 * SHADOW_RESOLUTION) + _e95)];
+            shadow_start = _e98;
+            let _e100: f32 = lit;
+            let _e101: f32 = dist;
+            let _e102: f32 = shadow_start;
+            if (_e101 <= _e102) {
+                local = 1f;
+            } else {
+                local = 0f;
+            }
+            let _e107: f32 = local;
+            lit = (_e100 + _e107);
+        }
+        continuing {
+            let _e59: i32 = tap;
+            tap = (_e59 + 1i);
+        }
+    }
+    let _e109: f32 = lit;
+    return (_e109 / 5f);
+}
+
+fn main_1() {
+    var scene: vec4<f32>;
+    var normals: vec4<f32>;
+    var normal: vec3<f32>;
+    var light: vec3<f32>;
+    var i_2: u32 = 0u;
+    var offset_2: vec2<f32>;
+    var falloff: f32;
+    var to_light: vec3<f32>;
+    var shading: f32;
+
+    let _e18: vec2<f32> = vUv_1;
+    let _e19: vec4<f32> = textureSample(tScene, sScene, _e18);
+    scene = _e19;
+    let _e22: vec2<f32> = vUv_1;
+    let _e23: vec4<f32> = textureSample(tNormals, sScene, _e22);
+    normals = _e23;
+    let _e25: vec4<f32> = normals;
+    let _e38: vec4<f32> = normals;
+    normal = normalize((((_e38.xyz * 2f) - vec3(1f)) * vec3<f32>(1f, -1f, 1f)));
+    let _e53: vec3<f32> = global.ambient;
+    light = _e53;
+    loop {
+        let _e57: u32 = i_2;
+        let _e58: u32 = global.light_count;
+        if !((_e57 < _e58)) {
+            break;
+        }
+        {
+            let _e65: vec4<f32> = gl_FragCoord_1;
+            let _e67: u32 = i_2;
+            let _e69: Light = global.lights[_e67];
+            offset_2 = (_e65.xy - _e69.position);
+            let _e75: vec2<f32> = offset_2;
+            let _e77: u32 = i_2;
+            let _e79: Light = global.lights[_e77];
+            let _e82: u32 = i_2;
+            let _e84: Light = global.lights[_e82];
+            let _e94: vec2<f32> = offset_2;
+            let _e96: u32 = i_2;
+            let _e98: Light = global.lights[_e96];
+            let _e101: u32 = i_2;
+            let _e103: Light = global.lights[_e101];
+            falloff = clamp((1f - (length(_e94) / max(_e103.radius, 0.0001f))), 0f, 1f);
+            let _e113: vec2<f32> = offset_2;
+            let _e114: vec2<f32> = -(_e113);
+            let _e115: u32 = i_2;
+            let _e117: Light = global.lights[_e115];
+            let _e120: u32 = i_2;
+            let _e122: Light = global.lights[_e120];
+            let _e130: vec2<f32> = offset_2;
+            let _e131: vec2<f32> = -(_e130);
+            let _e132: u32 = i_2;
+            let _e134: Light = global.lights[_e132];
+            let _e137: u32 = i_2;
+            let _e139: Light = global.lights[_e137];
+            to_light = normalize(vec3<f32>(_e131.x, _e131.y, (LIGHT_HEIGHT * max(_e139.radius, 1f))));
+            let _e152: vec3<f32> = normal;
+            let _e153: vec3<f32> = to_light;
+            let _e158: vec3<f32> = normal;
+            let _e159: vec3<f32> = to_light;
+            let _e163: vec4<f32> = normals;
+            let _e168: vec3<f32> = normal;
+            let _e169: vec3<f32> = to_light;
+            let _e174: vec3<f32> = normal;
+            let _e175: vec3<f32> = to_light;
+            let _e179: vec4<f32> = normals;
+            shading = mix(1f, max(dot(_e174, _e175), 0f), _e179.w);
+            let _e183: vec3<f32> = light;
+            let _e184: u32 = i_2;
+            let _e186: Light = global.lights[_e184];
+            let _e188: u32 = i_2;
+            let _e190: Light = global.lights[_e188];
+            let _e193: f32 = falloff;
+            let _e195: f32 = falloff;
+            let _e197: f32 = shading;
+            let _e201: u32 = i_2;
+            let _e202: vec2<f32> = offset_2;
+            let _e203: f32 = visibility(_e201, _e202);
+            light = (_e183 + (((((_e186.color * _e190.intensity) * _e193) * _e195) * _e197) * _e203));
+        }
+        continuing {
+            let _e61: u32 = i_2;
+            i_2 = (_e61 + 1u);
+        }
+    }
+    let _e206: vec4<f32> = scene;
+    let _e208: vec3<f32> = light;
+    let _e209: vec3<f32> = (_e206.xyz * _e208);
+    let _e210: vec4<f32> = scene;
+    fColor = vec4<f32>(_e209.x, _e209.y, _e209.z, _e210.w);
+    return;
+}
+
+@fragment 
+fn main(@location(0) vUv: vec2<f32>, @builtin(position) gl_FragCoord: vec4<f32>) -> FragmentOutput {
+    vUv_1 = vUv;
+    gl_FragCoord_1 = gl_FragCoord;
+    main_1();
+    let _e31: vec4<f32> = fColor;
+    return FragmentOutput(_e31);
+}
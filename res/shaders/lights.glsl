@@ -0,0 +1,35 @@
+// Light list shared by the lighting pass and the shadow map compute shader
+
+struct Light {
+    // Screen pixels
+    vec2 position;
+    float radius;
+    float intensity;
+    vec3 color;
+    // Width of the shadow penumbra in pixels. 0 casts hard shadows, negative casts none
+    float softness;
+};
+
+// Array length must match MAX_LIGHTS in lighting.rs
+layout(set = 0, binding = 2) uniform LightParams {
+    vec3 ambient;
+    uint light_count;
+    Light lights[64];
+};
+
+// Distance to the far side of the nearest occluder in each direction around a light, one row of
+// texels per light.
+// Must match SHADOW_RESOLUTION in lighting.rs
+const uint SHADOW_RESOLUTION = 720u;
+const float PI = 3.14159265;
+
+// Angle of texel `texel` in a shadow map row, from -PI to PI
+float shadow_texel_angle(uint texel) {
+    return (float(texel) + 0.5) / float(SHADOW_RESOLUTION) * 2.0 * PI - PI;
+}
+
+// Shadow map texel covering the direction `angle`, in radians from -PI to PI
+uint shadow_texel(float angle) {
+    float u = (angle + PI) / (2.0 * PI);
+    return min(uint(u * float(SHADOW_RESOLUTION)), SHADOW_RESOLUTION - 1u);
+}
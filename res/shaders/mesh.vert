@@ -0,0 +1,22 @@
+#version 450
+
+#include "globals.glsl"
+
+// Same layout as textured.vert, so meshes are built from the public `Vertex`
+layout(location = 0) in vec2 aPosition;
+layout(location = 1) in vec4 aColor;
+
+// Affine transform of the mesh, as the columns of its 2x2 part plus the translation
+layout(location = 2) in vec2 iAxisX;
+layout(location = 3) in vec2 iAxisY;
+layout(location = 4) in vec2 iTranslation;
+layout(location = 5) in vec4 iTint;
+
+layout(location = 0) out vec4 vColor;
+
+void main() {
+    vec2 position = iAxisX * aPosition.x + iAxisY * aPosition.y + iTranslation;
+
+    gl_Position = screen_to_clip(position);
+    vColor = aColor * iTint;
+}
@@ -0,0 +1,69 @@
+struct Globals {
+    screen_size: vec2<f32>,
+    view: mat4x4<f32>,
+}
+
+struct VertexOutput {
+    @location(0) vColor: vec4<f32>,
+    @builtin(position) gl_Position: vec4<f32>,
+}
+
+@group(0) @binding(0) 
+var<uniform> global: Globals;
+var<private> aPosition_1: vec2<f32>;
+var<private> aColor_1: vec4<f32>;
+var<private> iAxisX_1: vec2<f32>;
+var<private> iAxisY_1: vec2<f32>;
+var<private> iTranslation_1: vec2<f32>;
+var<private> iTint_1: vec4<f32>;
+var<private> vColor: vec4<f32>;
+var<private> gl_Position: vec4<f32>;
+
+fn screen_to_clip(position: vec2<f32>) -> vec4<f32> {
+    var position_1: vec2<f32>;
+    var ndc_x: f32;
+    var ndc_y: f32;
+
+    position_1 = position;
+    let _e6: vec2<f32> = position_1;
+    let _e8: vec2<f32> = global.screen_size;
+    ndc_x = (((_e6.x / _e8.x) * 2f) - 1f);
+    let _e17: vec2<f32> = position_1;
+    let _e19: vec2<f32> = global.screen_size;
+    ndc_y = (1f - ((_e17.y / _e19.y) * 2f));
+    let _e26: f32 = ndc_x;
+    let _e27: f32 = ndc_y;
+    return vec4<f32>(_e26, _e27, 0f, 1f);
+}
+
+fn main_1() {
+    var position_2: vec2<f32>;
+
+    let _e11: vec2<f32> = iAxisX_1;
+    let _e12: vec2<f32> = aPosition_1;
+    let _e15: vec2<f32> = iAxisY_1;
+    let _e16: vec2<f32> = aPosition_1;
+    let _e20: vec2<f32> = iTranslation_1;
+    position_2 = (((_e11 * _e12.x) + (_e15 * _e16.y)) + _e20);
+    let _e25: vec2<f32> = position_2;
+    let _e26: vec4<f32> = screen_to_clip(_e25);
+    gl_Position = _e26;
+    let _e27: vec4<f32> = aColor_1;
+    let _e28: vec4<f32> = iTint_1;
+    vColor = (_e27 * _e28);
+    return;
+}
+
+@vertex 
+fn main(@location(0) aPosition: vec2<f32>, @location(1) aColor: vec4<f32>, @location(2) iAxisX: vec2<f32>, @location(3) iAxisY: vec2<f32>, @location(4) iTranslation: vec2<f32>, @location(5) iTint: vec4<f32>) -> VertexOutput {
+    aPosition_1 = aPosition;
+    aColor_1 = aColor;
+    iAxisX_1 = iAxisX;
+    iAxisY_1 = iAxisY;
+    iTranslation_1 = iTranslation;
+    iTint_1 = iTint;
+    main_1();
+    let _e31: vec4<f32> = vColor;
+    let _e33: vec4<f32> = gl_Position;
+    return VertexOutput(_e31, _e33);
+}
@@ -0,0 +1,25 @@
+#version 450
+
+layout(set = 1, binding = 0) uniform texture2D tAtlas;
+layout(set = 1, binding = 1) uniform sampler sAtlas;
+
+layout(location = 0) in vec2 vUv;
+layout(location = 1) in vec4 vColor;
+
+layout(location = 0) out vec4 fColor;
+
+// Must match DISTANCE_RANGE in msdf.rs
+const float DISTANCE_RANGE = 6.0;
+
+#include "sdf.glsl"
+
+void main() {
+    vec3 msd = texture(sampler2D(tAtlas, sAtlas), vUv).rgb;
+
+    // How many screen pixels one unit of encoded distance covers at the current scale
+    vec2 unit_range = vec2(DISTANCE_RANGE) / vec2(textureSize(sampler2D(tAtlas, sAtlas), 0));
+    vec2 screen_tex_size = vec2(1.0) / fwidth(vUv);
+    float screen_px_range = max(0.5 * dot(unit_range, screen_tex_size), 1.0);
+
+    fColor = vec4(vColor.rgb, vColor.a * sdf_opacity(msd, screen_px_range));
+}
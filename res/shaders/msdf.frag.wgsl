@@ -0,0 +1,98 @@
+struct FragmentOutput {
+    @location(0) fColor: vec4<f32>,
+}
+
+const DISTANCE_RANGE: f32 = 6f;
+
+@group(1) @binding(0) 
+var tAtlas: texture_2d<f32>;
+@group(1) @binding(1) 
+var sAtlas: sampler;
+var<private> vUv_1: vec2<f32>;
+var<private> vColor_1: vec4<f32>;
+var<private> fColor: vec4<f32>;
+
+fn median(r: f32, g: f32, b: f32) -> f32 {
+    var r_1: f32;
+    var g_1: f32;
+    var b_1: f32;
+
+    r_1 = r;
+    g_1 = g;
+    b_1 = b;
+    let _e14: f32 = r_1;
+    let _e15: f32 = g_1;
+    let _e19: f32 = r_1;
+    let _e20: f32 = g_1;
+    let _e25: f32 = r_1;
+    let _e26: f32 = g_1;
+    let _e28: f32 = b_1;
+    let _e32: f32 = r_1;
+    let _e33: f32 = g_1;
+    let _e37: f32 = r_1;
+    let _e38: f32 = g_1;
+    let _e43: f32 = r_1;
+    let _e44: f32 = g_1;
+    let _e46: f32 = b_1;
+    return max(min(_e32, _e33), min(max(_e43, _e44), _e46));
+}
+
+fn sdf_opacity(msd: vec3<f32>, screen_px_range: f32) -> f32 {
+    var msd_1: vec3<f32>;
+    var screen_px_range_1: f32;
+    var distance: f32;
+
+    msd_1 = msd;
+    screen_px_range_1 = screen_px_range;
+    let _e10: vec3<f32> = msd_1;
+    let _e12: vec3<f32> = msd_1;
+    let _e14: vec3<f32> = msd_1;
+    let _e16: vec3<f32> = msd_1;
+    let _e18: vec3<f32> = msd_1;
+    let _e20: vec3<f32> = msd_1;
+    let _e22: f32 = median(_e16.x, _e18.y, _e20.z);
+    distance = (_e22 - 0.5f);
+    let _e26: f32 = screen_px_range_1;
+    let _e27: f32 = distance;
+    let _e33: f32 = screen_px_range_1;
+    let _e34: f32 = distance;
+    return clamp(((_e33 * _e34) + 0.5f), 0f, 1f);
+}
+
+fn main_1() {
+    var msd_2: vec3<f32>;
+    var unit_range: vec2<f32>;
+    var screen_tex_size: vec2<f32>;
+    var screen_px_range_2: f32;
+
+    let _e7: vec2<f32> = vUv_1;
+    let _e8: vec4<f32> = textureSample(tAtlas, sAtlas, _e7);
+    msd_2 = _e8.xyz;
+    let _e15: vec2<u32> = textureDimensions(tAtlas, 0i);
+    unit_range = (vec2(6f) / vec2<f32>(vec2<i32>(_e15)));
+    let _e23: vec2<f32> = vUv_1;
+    let _e24: vec2<f32> = fwidth(_e23);
+    screen_tex_size = (vec2(1f) / _e24);
+    let _e30: vec2<f32> = unit_range;
+    let _e31: vec2<f32> = screen_tex_size;
+    let _e38: vec2<f32> = unit_range;
+    let _e39: vec2<f32> = screen_tex_size;
+    screen_px_range_2 = max((0.5f * dot(_e38, _e39)), 1f);
+    let _e45: vec4<f32> = vColor_1;
+    let _e46: vec3<f32> = _e45.xyz;
+    let _e47: vec4<f32> = vColor_1;
+    let _e51: vec3<f32> = msd_2;
+    let _e52: f32 = screen_px_range_2;
+    let _e53: f32 = sdf_opacity(_e51, _e52);
+    fColor = vec4<f32>(_e46.x, _e46.y, _e46.z, (_e47.w * _e53));
+    return;
+}
+
+@fragment 
+fn main(@location(0) vUv: vec2<f32>, @location(1) vColor: vec4<f32>) -> FragmentOutput {
+    vUv_1 = vUv;
+    vColor_1 = vColor;
+    main_1();
+    let _e17: vec4<f32> = fColor;
+    return FragmentOutput(_e17);
+}
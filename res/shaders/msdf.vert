@@ -0,0 +1,19 @@
+#version 450
+
+#include "globals.glsl"
+
+layout(location = 0) in vec2 aPosition;
+layout(location = 1) in vec2 aUv;
+layout(location = 2) in vec4 aColor;
+// 1.0 for world-space text that follows the camera, 0.0 for screen-space text
+layout(location = 3) in float aWorld;
+
+layout(location = 0) out vec2 vUv;
+layout(location = 1) out vec4 vColor;
+
+void main() {
+    vec2 world_position = (view * vec4(aPosition, 0.0, 1.0)).xy;
+    gl_Position = screen_to_clip(mix(aPosition, world_position, aWorld));
+    vUv = aUv;
+    vColor = aColor;
+}
@@ -0,0 +1,14 @@
+#version 450
+
+layout(set = 1, binding = 0) uniform texture2D tNormalMap;
+layout(set = 1, binding = 1) uniform sampler sNormalMap;
+
+layout(location = 0) in vec2 vUv;
+
+layout(location = 0) out vec4 fNormal;
+
+// Copies the tangent-space normal map as is. Its alpha becomes the coverage that light.frag
+// blends between flat and normal-mapped shading with
+void main() {
+    fNormal = texture(sampler2D(tNormalMap, sNormalMap), vUv);
+}
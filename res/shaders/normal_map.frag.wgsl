@@ -0,0 +1,25 @@
+struct FragmentOutput {
+    @location(0) fNormal: vec4<f32>,
+}
+
+@group(1) @binding(0) 
+var tNormalMap: texture_2d<f32>;
+@group(1) @binding(1) 
+var sNormalMap: sampler;
+var<private> vUv_1: vec2<f32>;
+var<private> fNormal: vec4<f32>;
+
+fn main_1() {
+    let _e5: vec2<f32> = vUv_1;
+    let _e6: vec4<f32> = textureSample(tNormalMap, sNormalMap, _e5);
+    fNormal = _e6;
+    return;
+}
+
+@fragment 
+fn main(@location(0) vUv: vec2<f32>) -> FragmentOutput {
+    vUv_1 = vUv;
+    main_1();
+    let _e11: vec4<f32> = fNormal;
+    return FragmentOutput(_e11);
+}
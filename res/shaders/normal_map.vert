@@ -0,0 +1,13 @@
+#version 450
+
+#include "globals.glsl"
+
+layout(location = 0) in vec2 aPosition;
+layout(location = 1) in vec2 aUv;
+
+layout(location = 0) out vec2 vUv;
+
+void main() {
+    gl_Position = screen_to_clip(aPosition);
+    vUv = aUv;
+}
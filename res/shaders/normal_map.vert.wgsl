@@ -0,0 +1,52 @@
+struct Globals {
+    screen_size: vec2<f32>,
+    view: mat4x4<f32>,
+}
+
+struct VertexOutput {
+    @location(0) vUv: vec2<f32>,
+    @builtin(position) gl_Position: vec4<f32>,
+}
+
+@group(0) @binding(0) 
+var<uniform> global: Globals;
+var<private> aPosition_1: vec2<f32>;
+var<private> aUv_1: vec2<f32>;
+var<private> vUv: vec2<f32>;
+var<private> gl_Position: vec4<f32>;
+
+fn screen_to_clip(position: vec2<f32>) -> vec4<f32> {
+    var position_1: vec2<f32>;
+    var ndc_x: f32;
+    var ndc_y: f32;
+
+    position_1 = position;
+    let _e6: vec2<f32> = position_1;
+    let _e8: vec2<f32> = global.screen_size;
+    ndc_x = (((_e6.x / _e8.x) * 2f) - 1f);
+    let _e17: vec2<f32> = position_1;
+    let _e19: vec2<f32> = global.screen_size;
+    ndc_y = (1f - ((_e17.y / _e19.y) * 2f));
+    let _e26: f32 = ndc_x;
+    let _e27: f32 = ndc_y;
+    return vec4<f32>(_e26, _e27, 0f, 1f);
+}
+
+fn main_1() {
+    let _e9: vec2<f32> = aPosition_1;
+    let _e10: vec4<f32> = screen_to_clip(_e9);
+    gl_Position = _e10;
+    let _e11: vec2<f32> = aUv_1;
+    vUv = _e11;
+    return;
+}
+
+@vertex 
+fn main(@location(0) aPosition: vec2<f32>, @location(1) aUv: vec2<f32>) -> VertexOutput {
+    aPosition_1 = aPosition;
+    aUv_1 = aUv;
+    main_1();
+    let _e15: vec2<f32> = vUv;
+    let _e17: vec4<f32> = gl_Position;
+    return VertexOutput(_e15, _e17);
+}
@@ -0,0 +1,75 @@
+struct Particle {
+    position: vec2<f32>,
+    velocity: vec2<f32>,
+}
+
+struct Particles {
+    particles: array<Particle>,
+}
+
+struct SimParams {
+    bounds: vec2<f32>,
+    dt: f32,
+    count: u32,
+}
+
+@group(0) @binding(0) 
+var<storage, read_write> global: Particles;
+@group(0) @binding(1) 
+var<uniform> global_1: SimParams;
+var<private> gl_GlobalInvocationID_1: vec3<u32>;
+
+fn main_1() {
+    var index: u32;
+    var particle: Particle;
+
+    let _e9: vec3<u32> = gl_GlobalInvocationID_1;
+    index = _e9.x;
+    let _e12: u32 = index;
+    let _e13: u32 = global_1.count;
+    if (_e12 >= _e13) {
+        {
+            return;
+        }
+    }
+    let _e15: u32 = index;
+    let _e17: Particle = global.particles[_e15];
+    particle = _e17;
+    let _e20: Particle = particle;
+    let _e22: Particle = particle;
+    let _e24: f32 = global_1.dt;
+    particle.position = (_e20.position + (_e22.velocity * _e24));
+    let _e27: Particle = particle;
+    let _e32: Particle = particle;
+    let _e35: vec2<f32> = global_1.bounds;
+    if ((_e27.position.x < 0f) || (_e32.position.x > _e35.x)) {
+        {
+            let _e41: Particle = particle;
+            particle.velocity.x = -(_e41.velocity.x);
+        }
+    }
+    let _e45: Particle = particle;
+    let _e50: Particle = particle;
+    let _e53: vec2<f32> = global_1.bounds;
+    if ((_e45.position.y < 0f) || (_e50.position.y > _e53.y)) {
+        {
+            let _e59: Particle = particle;
+            particle.velocity.y = -(_e59.velocity.y);
+        }
+    }
+    let _e64: Particle = particle;
+    let _e69: Particle = particle;
+    let _e73: vec2<f32> = global_1.bounds;
+    particle.position = clamp(_e69.position, vec2(0f), _e73);
+    let _e75: u32 = index;
+    let _e77: Particle = particle;
+    global.particles[_e75] = _e77;
+    return;
+}
+
+@compute @workgroup_size(64, 1, 1) 
+fn main(@builtin(global_invocation_id) gl_GlobalInvocationID: vec3<u32>) {
+    gl_GlobalInvocationID_1 = gl_GlobalInvocationID;
+    main_1();
+    return;
+}
@@ -0,0 +1,15 @@
+// Bindings shared by every post-processing pass. Must match `create_post_bind_group_layout` in
+// post.rs
+layout(set = 0, binding = 0) uniform texture2D tInput;
+layout(set = 0, binding = 1) uniform sampler sInput;
+layout(set = 0, binding = 2) uniform PostParams {
+    vec2 texel_size;
+    vec2 direction;
+    vec4 params;
+};
+// Second input, such as the bloom texture or a color lookup table
+layout(set = 0, binding = 3) uniform texture2D tAux;
+
+layout(location = 0) in vec2 vUv;
+
+layout(location = 0) out vec4 fColor;
@@ -0,0 +1,12 @@
+// Helpers for sampling multi-channel signed distance fields
+
+float median(float r, float g, float b) {
+    return max(min(r, g), min(max(r, g), b));
+}
+
+// Opacity of a fragment from its encoded distance, antialiased over one screen pixel.
+// `screen_px_range` is how many screen pixels one unit of encoded distance covers
+float sdf_opacity(vec3 msd, float screen_px_range) {
+    float distance = median(msd.r, msd.g, msd.b) - 0.5;
+    return clamp(screen_px_range * distance + 0.5, 0.0, 1.0);
+}
@@ -0,0 +1,207 @@
+struct Light {
+    position: vec2<f32>,
+    radius: f32,
+    intensity: f32,
+    color: vec3<f32>,
+    softness: f32,
+}
+
+struct LightParams {
+    ambient: vec3<f32>,
+    light_count: u32,
+    lights: array<Light, 64>,
+}
+
+struct Occluders {
+    segment_count: u32,
+    segments: array<vec4<f32>>,
+}
+
+struct ShadowMap {
+    distances: array<f32>,
+}
+
+const SHADOW_RESOLUTION: u32 = 720u;
+const PI: f32 = 3.1415927f;
+
+@group(0) @binding(2) 
+var<uniform> global: LightParams;
+@group(0) @binding(0) 
+var<storage> global_1: Occluders;
+@group(0) @binding(1) 
+var<storage, read_write> global_2: ShadowMap;
+var<private> gl_GlobalInvocationID_1: vec3<u32>;
+
+fn shadow_texel_angle(texel: u32) -> f32 {
+    var texel_1: u32;
+
+    texel_1 = texel;
+    let _e10: u32 = texel_1;
+    return (((((f32(_e10) + 0.5f) / 720f) * 2f) * PI) - PI);
+}
+
+fn shadow_texel(angle: f32) -> u32 {
+    var angle_1: f32;
+    var u: f32;
+
+    angle_1 = angle;
+    let _e10: f32 = angle_1;
+    u = ((_e10 + PI) / 6.2831855f);
+    let _e17: f32 = u;
+    let _e25: f32 = u;
+    return min(u32((_e25 * 720f)), 719u);
+}
+
+fn intersect(origin: vec2<f32>, direction: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    var origin_1: vec2<f32>;
+    var direction_1: vec2<f32>;
+    var a_1: vec2<f32>;
+    var b_1: vec2<f32>;
+    var edge: vec2<f32>;
+    var denominator: f32;
+    var offset: vec2<f32>;
+    var t: f32;
+    var u_1: f32;
+    var local: f32;
+
+    origin_1 = origin;
+    direction_1 = direction;
+    a_1 = a;
+    b_1 = b;
+    let _e22: vec2<f32> = b_1;
+    let _e23: vec2<f32> = a_1;
+    edge = (_e22 - _e23);
+    let _e26: vec2<f32> = direction_1;
+    let _e28: vec2<f32> = edge;
+    let _e31: vec2<f32> = direction_1;
+    let _e33: vec2<f32> = edge;
+    denominator = ((_e26.x * _e28.y) - (_e31.y * _e33.x));
+    let _e39: f32 = denominator;
+    if (abs(_e39) < 0.000001f) {
+        {
+            return 1000000000000000000000000000000f;
+        }
+    }
+    let _e44: vec2<f32> = a_1;
+    let _e45: vec2<f32> = origin_1;
+    offset = (_e44 - _e45);
+    let _e48: vec2<f32> = offset;
+    let _e50: vec2<f32> = edge;
+    let _e53: vec2<f32> = offset;
+    let _e55: vec2<f32> = edge;
+    let _e59: f32 = denominator;
+    t = (((_e48.x * _e50.y) - (_e53.y * _e55.x)) / _e59);
+    let _e62: vec2<f32> = offset;
+    let _e64: vec2<f32> = direction_1;
+    let _e67: vec2<f32> = offset;
+    let _e69: vec2<f32> = direction_1;
+    let _e73: f32 = denominator;
+    u_1 = (((_e62.x * _e64.y) - (_e67.y * _e69.x)) / _e73);
+    let _e76: f32 = t;
+    let _e79: f32 = u_1;
+    let _e83: f32 = u_1;
+    if (((_e76 >= 0f) && (_e79 >= 0f)) && (_e83 <= 1f)) {
+        let _e87: f32 = t;
+        local = _e87;
+    } else {
+        local = 1000000000000000000000000000000f;
+    }
+    let _e90: f32 = local;
+    return _e90;
+}
+
+fn main_1() {
+    var texel_2: u32;
+    var light: u32;
+    var angle_2: f32;
+    var direction_2: vec2<f32>;
+    var origin_2: vec2<f32>;
+    var nearest: f32;
+    var exit: f32;
+    var i: u32 = 0u;
+    var t_1: f32;
+
+    let _e15: vec3<u32> = gl_GlobalInvocationID_1;
+    texel_2 = _e15.x;
+    let _e18: vec3<u32> = gl_GlobalInvocationID_1;
+    light = _e18.y;
+    let _e21: u32 = texel_2;
+    let _e23: u32 = light;
+    let _e24: u32 = global.light_count;
+    if ((_e21 >= SHADOW_RESOLUTION) || (_e23 >= _e24)) {
+        {
+            return;
+        }
+    }
+    let _e28: u32 = texel_2;
+    let _e29: f32 = shadow_texel_angle(_e28);
+    angle_2 = _e29;
+    let _e32: f32 = angle_2;
+    let _e35: f32 = angle_2;
+    direction_2 = vec2<f32>(cos(_e32), sin(_e35));
+    let _e39: u32 = light;
+    let _e41: Light = global.lights[_e39];
+    origin_2 = _e41.position;
+    let _e44: u32 = light;
+    let _e46: Light = global.lights[_e44];
+    nearest = _e46.radius;
+    let _e49: u32 = light;
+    let _e51: Light = global.lights[_e49];
+    exit = _e51.radius;
+    loop {
+        let _e56: u32 = i;
+        let _e57: u32 = global_1.segment_count;
+        if !((_e56 < _e57)) {
+            break;
+        }
+        {
+            let _e65: u32 = i;
+            let _e67: vec4<f32> = global_1.segments[_e65];
+            let _e69: u32 = i;
+            let _e71: vec4<f32> = global_1.segments[_e69];
+            let _e73: vec2<f32> = origin_2;
+            let _e74: vec2<f32> = direction_2;
+            let _e75: u32 = i;
+            let _e77: vec4<f32> = global_1.segments[_e75];
+            let _e79: u32 = i;
+            let _e81: vec4<f32> = global_1.segments[_e79];
+            let _e83: f32 = intersect(_e73, _e74, _e77.xy, _e81.zw);
+            t_1 = _e83;
+            let _e85: f32 = t_1;
+            let _e86: f32 = nearest;
+            if (_e85 < _e86) {
+                {
+                    let _e88: f32 = nearest;
+                    exit = _e88;
+                    let _e89: f32 = t_1;
+                    nearest = _e89;
+                }
+            } else {
+                let _e90: f32 = t_1;
+                let _e91: f32 = exit;
+                if (_e90 < _e91) {
+                    {
+                        let _e93: f32 = t_1;
+                        exit = _e93;
+                    }
+                }
+            }
+        }
+        continuing {
+            let _e60: u32 = i;
+            i = (_e60 + 1u);
+        }
+    }
+    let _e94: u32 = light;
+    let _e96: u32 = texel_2;
+    let _e99: f32 = exit;
+    global_2.distances[((_e94 * SHADOW_RESOLUTION) + _e96)] = _e99;
+    return;
+}
+
+@compute @workgroup_size(64, 1, 1) 
+fn main(@builtin(global_invocation_id) gl_GlobalInvocationID: vec3<u32>) {
+    gl_GlobalInvocationID_1 = gl_GlobalInvocationID;
+    main_1();
+    return;
+}
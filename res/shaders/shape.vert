@@ -0,0 +1,24 @@
+#version 450
+
+#include "globals.glsl"
+
+// Unit mesh centered on the origin, shared by every instance
+layout(location = 0) in vec2 aPosition;
+
+layout(location = 1) in vec2 iCenter;
+layout(location = 2) in vec2 iSize;
+// Radians, around the center
+layout(location = 3) in float iRotation;
+layout(location = 4) in vec4 iColor;
+
+layout(location = 0) out vec4 vColor;
+
+void main() {
+    vec2 local = aPosition * iSize;
+    float s = sin(iRotation);
+    float c = cos(iRotation);
+    vec2 rotated = vec2(local.x * c - local.y * s, local.x * s + local.y * c);
+
+    gl_Position = screen_to_clip(iCenter + rotated);
+    vColor = iColor;
+}
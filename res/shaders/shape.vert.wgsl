@@ -0,0 +1,82 @@
+struct Globals {
+    screen_size: vec2<f32>,
+    view: mat4x4<f32>,
+}
+
+struct VertexOutput {
+    @location(0) vColor: vec4<f32>,
+    @builtin(position) gl_Position: vec4<f32>,
+}
+
+@group(0) @binding(0) 
+var<uniform> global: Globals;
+var<private> aPosition_1: vec2<f32>;
+var<private> iCenter_1: vec2<f32>;
+var<private> iSize_1: vec2<f32>;
+var<private> iRotation_1: f32;
+var<private> iColor_1: vec4<f32>;
+var<private> vColor: vec4<f32>;
+var<private> gl_Position: vec4<f32>;
+
+fn screen_to_clip(position: vec2<f32>) -> vec4<f32> {
+    var position_1: vec2<f32>;
+    var ndc_x: f32;
+    var ndc_y: f32;
+
+    position_1 = position;
+    let _e6: vec2<f32> = position_1;
+    let _e8: vec2<f32> = global.screen_size;
+    ndc_x = (((_e6.x / _e8.x) * 2f) - 1f);
+    let _e17: vec2<f32> = position_1;
+    let _e19: vec2<f32> = global.screen_size;
+    ndc_y = (1f - ((_e17.y / _e19.y) * 2f));
+    let _e26: f32 = ndc_x;
+    let _e27: f32 = ndc_y;
+    return vec4<f32>(_e26, _e27, 0f, 1f);
+}
+
+fn main_1() {
+    var local: vec2<f32>;
+    var s: f32;
+    var c: f32;
+    var rotated: vec2<f32>;
+
+    let _e10: vec2<f32> = aPosition_1;
+    let _e11: vec2<f32> = iSize_1;
+    local = (_e10 * _e11);
+    let _e15: f32 = iRotation_1;
+    s = sin(_e15);
+    let _e19: f32 = iRotation_1;
+    c = cos(_e19);
+    let _e22: vec2<f32> = local;
+    let _e24: f32 = c;
+    let _e26: vec2<f32> = local;
+    let _e28: f32 = s;
+    let _e31: vec2<f32> = local;
+    let _e33: f32 = s;
+    let _e35: vec2<f32> = local;
+    let _e37: f32 = c;
+    rotated = vec2<f32>(((_e22.x * _e24) - (_e26.y * _e28)), ((_e31.x * _e33) + (_e35.y * _e37)));
+    let _e43: vec2<f32> = iCenter_1;
+    let _e44: vec2<f32> = rotated;
+    let _e46: vec2<f32> = iCenter_1;
+    let _e47: vec2<f32> = rotated;
+    let _e49: vec4<f32> = screen_to_clip((_e46 + _e47));
+    gl_Position = _e49;
+    let _e50: vec4<f32> = iColor_1;
+    vColor = _e50;
+    return;
+}
+
+@vertex 
+fn main(@location(0) aPosition: vec2<f32>, @location(1) iCenter: vec2<f32>, @location(2) iSize: vec2<f32>, @location(3) iRotation: f32, @location(4) iColor: vec4<f32>) -> VertexOutput {
+    aPosition_1 = aPosition;
+    iCenter_1 = iCenter;
+    iSize_1 = iSize;
+    iRotation_1 = iRotation;
+    iColor_1 = iColor;
+    main_1();
+    let _e27: vec4<f32> = vColor;
+    let _e29: vec4<f32> = gl_Position;
+    return VertexOutput(_e27, _e29);
+}
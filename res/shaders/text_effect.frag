@@ -0,0 +1,88 @@
+#version 450
+
+// Effects are filtered in two passes of one row or column each. The horizontal variants filter
+// the glyphs along x into an intermediate target, the others filter that along y and composite
+// the result. Outlines dilate the glyphs, the base variants blur them into a shadow.
+#pragma variant horizontal HORIZONTAL
+#pragma variant outline OUTLINE
+#pragma variant outline_horizontal OUTLINE HORIZONTAL
+
+// The effect glyphs, rendered offscreen with premultiplied alpha, for the horizontal variants.
+// The intermediate target for the others.
+layout(set = 1, binding = 0) uniform texture2D tSource;
+layout(set = 1, binding = 1) uniform sampler sSource;
+layout(set = 1, binding = 2) uniform EffectParams {
+    float thickness;
+    float blur;
+    // Only read on the CPU, to pick this variant or the outline one
+    uint mode;
+};
+
+layout(location = 0) out vec4 fColor;
+
+const int MAX_RADIUS = 16;
+// Outline distances are stored as `FAR - distance`, so cleared texels read as out of reach
+const float FAR = float(MAX_RADIUS + 2);
+
+void main() {
+    ivec2 size = textureSize(sampler2D(tSource, sSource), 0);
+    ivec2 center = ivec2(gl_FragCoord.xy);
+    int radius = min(int(ceil(thickness + blur)), MAX_RADIUS);
+#ifdef HORIZONTAL
+    ivec2 axis = ivec2(1, 0);
+#else
+    ivec2 axis = ivec2(0, 1);
+#endif
+
+#ifdef OUTLINE
+    // Distance to the closest glyph texel, where partially covered texels count as farther
+    float closest = FAR;
+    vec3 color = vec3(0.0);
+#else
+    float sigma = max(blur * 0.5, 0.5);
+    vec4 result = vec4(0.0);
+    float total = 0.0;
+#endif
+
+    for (int i = -radius; i <= radius; i++) {
+        ivec2 coord = clamp(center + axis * i, ivec2(0), size - ivec2(1));
+        vec4 texel = texelFetch(sampler2D(tSource, sSource), coord, 0);
+
+#ifdef OUTLINE
+        if (texel.a > 0.0) {
+#ifdef HORIZONTAL
+            float dist = float(abs(i)) + 1.0 - texel.a;
+            vec3 texel_color = texel.rgb / texel.a;
+#else
+            // The horizontal pass left the distance along x and the unpremultiplied color
+            float dist = length(vec2(FAR - texel.a, float(i)));
+            vec3 texel_color = texel.rgb;
+#endif
+            if (dist < closest) {
+                closest = dist;
+                color = texel_color;
+            }
+        }
+#else
+        // Gaussian blur, which is separable
+        float weight = exp(-float(i * i) / (2.0 * sigma * sigma));
+        result += texel * weight;
+        total += weight;
+#endif
+    }
+
+#ifdef OUTLINE
+#ifdef HORIZONTAL
+    fColor = vec4(color, FAR - closest);
+#else
+    // Dilate by `thickness`, then fade out over `blur` pixels
+    float weight = 0.0;
+    if (closest < FAR) {
+        weight = 1.0 - clamp((closest - thickness) / max(blur, 1.0), 0.0, 1.0);
+    }
+    fColor = vec4(color * weight, weight);
+#endif
+#else
+    fColor = result / max(total, 0.0001);
+#endif
+}
@@ -0,0 +1,107 @@
+struct EffectParams {
+    thickness: f32,
+    blur: f32,
+    mode: u32,
+}
+
+struct FragmentOutput {
+    @location(0) fColor: vec4<f32>,
+}
+
+const MAX_RADIUS: i32 = 16i;
+const FAR: f32 = 18f;
+
+@group(1) @binding(0) 
+var tSource: texture_2d<f32>;
+@group(1) @binding(1) 
+var sSource: sampler;
+@group(1) @binding(2) 
+var<uniform> global: EffectParams;
+var<private> fColor: vec4<f32>;
+var<private> gl_FragCoord_1: vec4<f32>;
+
+fn main_1() {
+    var size: vec2<i32>;
+    var center: vec2<i32>;
+    var radius: i32;
+    var axis: vec2<i32> = vec2<i32>(0i, 1i);
+    var sigma: f32;
+    var result: vec4<f32> = vec4(0f);
+    var total: f32 = 0f;
+    var i: i32;
+    var coord: vec2<i32>;
+    var texel: vec4<f32>;
+    var weight: f32;
+
+    let _e13: vec2<u32> = textureDimensions(tSource, 0i);
+    size = vec2<i32>(_e13);
+    let _e17: vec4<f32> = gl_FragCoord_1;
+    center = vec2<i32>(_e17.xy);
+    let _e21: f32 = global.thickness;
+    let _e22: f32 = global.blur;
+    let _e24: f32 = global.thickness;
+    let _e25: f32 = global.blur;
+    let _e29: f32 = global.thickness;
+    let _e30: f32 = global.blur;
+    let _e32: f32 = global.thickness;
+    let _e33: f32 = global.blur;
+    radius = min(i32(ceil((_e32 + _e33))), MAX_RADIUS);
+    let _e43: f32 = global.blur;
+    let _e47: f32 = global.blur;
+    sigma = max((_e47 * 0.5f), 0.5f);
+    let _e58: i32 = radius;
+    i = -(_e58);
+    loop {
+        let _e61: i32 = i;
+        let _e62: i32 = radius;
+        if !((_e61 <= _e62)) {
+            break;
+        }
+        {
+            let _e68: vec2<i32> = center;
+            let _e69: vec2<i32> = axis;
+            let _e70: i32 = i;
+            let _e75: vec2<i32> = size;
+            let _e79: vec2<i32> = center;
+            let _e80: vec2<i32> = axis;
+            let _e81: i32 = i;
+            let _e86: vec2<i32> = size;
+            coord = clamp((_e79 + (_e80 * _e81)), vec2(0i), (_e86 - vec2(1i)));
+            let _e94: vec2<i32> = coord;
+            let _e96: vec4<f32> = textureLoad(tSource, _e94, 0i);
+            texel = _e96;
+            let _e98: i32 = i;
+            let _e99: i32 = i;
+            let _e104: f32 = sigma;
+            let _e106: f32 = sigma;
+            let _e109: i32 = i;
+            let _e110: i32 = i;
+            let _e115: f32 = sigma;
+            let _e117: f32 = sigma;
+            weight = exp((-(f32((_e109 * _e110))) / ((2f * _e115) * _e117)));
+            let _e122: vec4<f32> = result;
+            let _e123: vec4<f32> = texel;
+            let _e124: f32 = weight;
+            result = (_e122 + (_e123 * _e124));
+            let _e127: f32 = total;
+            let _e128: f32 = weight;
+            total = (_e127 + _e128);
+        }
+        continuing {
+            let _e65: i32 = i;
+            i = (_e65 + 1i);
+        }
+    }
+    let _e130: vec4<f32> = result;
+    let _e133: f32 = total;
+    fColor = (_e130 / vec4(max(_e133, 0.0001f)));
+    return;
+}
+
+@fragment 
+fn main(@builtin(position) gl_FragCoord: vec4<f32>) -> FragmentOutput {
+    gl_FragCoord_1 = gl_FragCoord;
+    main_1();
+    let _e19: vec4<f32> = fColor;
+    return FragmentOutput(_e19);
+}
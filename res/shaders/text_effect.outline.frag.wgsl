@@ -0,0 +1,125 @@
+struct EffectParams {
+    thickness: f32,
+    blur: f32,
+    mode: u32,
+}
+
+struct FragmentOutput {
+    @location(0) fColor: vec4<f32>,
+}
+
+const MAX_RADIUS: i32 = 16i;
+const FAR: f32 = 18f;
+
+@group(1) @binding(0) 
+var tSource: texture_2d<f32>;
+@group(1) @binding(1) 
+var sSource: sampler;
+@group(1) @binding(2) 
+var<uniform> global: EffectParams;
+var<private> fColor: vec4<f32>;
+var<private> gl_FragCoord_1: vec4<f32>;
+
+fn main_1() {
+    var size: vec2<i32>;
+    var center: vec2<i32>;
+    var radius: i32;
+    var axis: vec2<i32> = vec2<i32>(0i, 1i);
+    var closest: f32 = FAR;
+    var color: vec3<f32> = vec3(0f);
+    var i: i32;
+    var coord: vec2<i32>;
+    var texel: vec4<f32>;
+    var dist: f32;
+    var texel_color: vec3<f32>;
+    var weight: f32 = 0f;
+
+    let _e13: vec2<u32> = textureDimensions(tSource, 0i);
+    size = vec2<i32>(_e13);
+    let _e17: vec4<f32> = gl_FragCoord_1;
+    center = vec2<i32>(_e17.xy);
+    let _e21: f32 = global.thickness;
+    let _e22: f32 = global.blur;
+    let _e24: f32 = global.thickness;
+    let _e25: f32 = global.blur;
+    let _e29: f32 = global.thickness;
+    let _e30: f32 = global.blur;
+    let _e32: f32 = global.thickness;
+    let _e33: f32 = global.blur;
+    radius = min(i32(ceil((_e32 + _e33))), MAX_RADIUS);
+    let _e47: i32 = radius;
+    i = -(_e47);
+    loop {
+        let _e50: i32 = i;
+        let _e51: i32 = radius;
+        if !((_e50 <= _e51)) {
+            break;
+        }
+        {
+            let _e57: vec2<i32> = center;
+            let _e58: vec2<i32> = axis;
+            let _e59: i32 = i;
+            let _e64: vec2<i32> = size;
+            let _e68: vec2<i32> = center;
+            let _e69: vec2<i32> = axis;
+            let _e70: i32 = i;
+            let _e75: vec2<i32> = size;
+            coord = clamp((_e68 + (_e69 * _e70)), vec2(0i), (_e75 - vec2(1i)));
+            let _e83: vec2<i32> = coord;
+            let _e85: vec4<f32> = textureLoad(tSource, _e83, 0i);
+            texel = _e85;
+            let _e87: vec4<f32> = texel;
+            if (_e87.w > 0f) {
+                {
+                    let _e91: vec4<f32> = texel;
+                    let _e94: i32 = i;
+                    let _e97: vec4<f32> = texel;
+                    let _e100: i32 = i;
+                    dist = length(vec2<f32>((FAR - _e97.w), f32(_e100)));
+                    let _e105: vec4<f32> = texel;
+                    texel_color = _e105.xyz;
+                    let _e108: f32 = dist;
+                    let _e109: f32 = closest;
+                    if (_e108 < _e109) {
+                        {
+                            let _e111: f32 = dist;
+                            closest = _e111;
+                            let _e112: vec3<f32> = texel_color;
+                            color = _e112;
+                        }
+                    }
+                }
+            }
+        }
+        continuing {
+            let _e54: i32 = i;
+            i = (_e54 + 1i);
+        }
+    }
+    let _e115: f32 = closest;
+    if (_e115 < FAR) {
+        {
+            let _e118: f32 = closest;
+            let _e119: f32 = global.thickness;
+            let _e123: f32 = global.blur;
+            let _e129: f32 = closest;
+            let _e130: f32 = global.thickness;
+            let _e134: f32 = global.blur;
+            weight = (1f - clamp(((_e129 - _e130) / max(_e134, 1f)), 0f, 1f));
+        }
+    }
+    let _e142: vec3<f32> = color;
+    let _e143: f32 = weight;
+    let _e144: vec3<f32> = (_e142 * _e143);
+    let _e145: f32 = weight;
+    fColor = vec4<f32>(_e144.x, _e144.y, _e144.z, _e145);
+    return;
+}
+
+@fragment 
+fn main(@builtin(position) gl_FragCoord: vec4<f32>) -> FragmentOutput {
+    gl_FragCoord_1 = gl_FragCoord;
+    main_1();
+    let _e19: vec4<f32> = fColor;
+    return FragmentOutput(_e19);
+}
@@ -0,0 +1,9 @@
+#version 450
+
+#include "globals.glsl"
+
+layout(location = 0) in vec2 aPosition;
+
+void main() {
+    gl_Position = screen_to_clip(aPosition);
+}
@@ -0,0 +1,12 @@
+#version 450
+
+#include "post.glsl"
+
+// Darkens the corners by `params.x`, starting at radius `params.y` and fading in over `params.z`
+void main() {
+    vec4 color = texture(sampler2D(tInput, sInput), vUv);
+    float dist = length(vUv - 0.5) * 1.41421356;
+    float vignette = smoothstep(params.y, params.y + max(params.z, 0.0001), dist);
+
+    fColor = vec4(color.rgb * (1.0 - params.x * vignette), color.a);
+}
@@ -0,0 +1,58 @@
+struct PostParams {
+    texel_size: vec2<f32>,
+    direction: vec2<f32>,
+    params: vec4<f32>,
+}
+
+struct FragmentOutput {
+    @location(0) fColor: vec4<f32>,
+}
+
+@group(0) @binding(0) 
+var tInput: texture_2d<f32>;
+@group(0) @binding(1) 
+var sInput: sampler;
+@group(0) @binding(2) 
+var<uniform> global: PostParams;
+@group(0) @binding(3) 
+var tAux: texture_2d<f32>;
+var<private> vUv_1: vec2<f32>;
+var<private> fColor: vec4<f32>;
+
+fn main_1() {
+    var color: vec4<f32>;
+    var dist: f32;
+    var vignette: f32;
+
+    let _e12: vec2<f32> = vUv_1;
+    let _e13: vec4<f32> = textureSample(tInput, sInput, _e12);
+    color = _e13;
+    let _e15: vec2<f32> = vUv_1;
+    let _e19: vec2<f32> = vUv_1;
+    dist = (length((_e19 - vec2(0.5f))) * 1.4142135f);
+    let _e27: vec4<f32> = global.params;
+    let _e29: vec4<f32> = global.params;
+    let _e31: vec4<f32> = global.params;
+    let _e34: vec4<f32> = global.params;
+    let _e40: vec4<f32> = global.params;
+    let _e42: vec4<f32> = global.params;
+    let _e44: vec4<f32> = global.params;
+    let _e47: vec4<f32> = global.params;
+    let _e52: f32 = dist;
+    vignette = smoothstep(_e40.y, (_e42.y + max(_e47.z, 0.0001f)), _e52);
+    let _e55: vec4<f32> = color;
+    let _e58: vec4<f32> = global.params;
+    let _e60: f32 = vignette;
+    let _e63: vec3<f32> = (_e55.xyz * (1f - (_e58.x * _e60)));
+    let _e64: vec4<f32> = color;
+    fColor = vec4<f32>(_e63.x, _e63.y, _e63.z, _e64.w);
+    return;
+}
+
+@fragment 
+fn main(@location(0) vUv: vec2<f32>) -> FragmentOutput {
+    vUv_1 = vUv;
+    main_1();
+    let _e19: vec4<f32> = fColor;
+    return FragmentOutput(_e19);
+}
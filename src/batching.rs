@@ -0,0 +1,370 @@
+//! Sorting and merging of queued shapes into as few draw calls as possible.
+//!
+//! Every queued shape, every call queueing instanced shapes and every mesh draw is recorded as a
+//! `Primitive` with its layer, the state it needs and its screen bounds. At the end of the frame
+//! primitives are ordered by layer, then grouped by state within each layer. A primitive may only
+//! join an earlier group with the same state if it does not overlap anything queued in between,
+//! so reordering never changes what ends up on screen. Adjacent groups that share a state are
+//! merged into one draw call.
+
+use std::ops::Range;
+
+use crate::types::{ MaterialId, MeshId, Rect };
+
+/// What a primitive draws, which decides the list of items its range refers to: the queued
+/// indices for shapes, per-instance records for instanced shapes and meshes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum DrawKind {
+    Shapes,
+    Rectangles,
+    Circles,
+    Meshes,
+}
+
+/// Pipeline state a primitive is drawn with. Primitives with equal keys can share a draw call.
+/// Textures and blend modes join the material here once shapes can use them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BatchKey {
+    pub(crate) kind: DrawKind,
+    /// `None` is the built-in pipeline of the kind. Only shapes take materials.
+    pub(crate) material: Option<MaterialId>,
+    /// The mesh whose buffers `Meshes` primitives draw.
+    pub(crate) mesh: Option<MeshId>,
+}
+
+impl BatchKey {
+    pub(crate) fn new(kind: DrawKind) -> Self {
+        Self { kind, material: None, mesh: None }
+    }
+}
+
+/// A queued shape, group of instances or mesh draw: a range of the items of its kind plus what it
+/// takes to draw it.
+pub(crate) struct Primitive {
+    pub(crate) layer: i32,
+    pub(crate) key: BatchKey,
+    pub(crate) items: Range<u32>,
+    pub(crate) bounds: Rect,
+}
+
+/// A draw call over a range of the sorted items of its kind.
+pub(crate) struct Batch {
+    pub(crate) key: BatchKey,
+    pub(crate) items: Range<u32>,
+}
+
+struct Group {
+    key: BatchKey,
+    primitives: Vec<usize>,
+    bounds: Rect,
+}
+
+/// Orders `primitives` into batches. Returns the primitives in draw order and the batches, whose
+/// ranges refer to the items of their kind rearranged in that order, see `sorted_items`.
+pub(crate) fn build_batches(primitives: &[Primitive]) -> (Vec<usize>, Vec<Batch>) {
+    // Stable, so submission order is kept within a layer
+    let mut order: Vec<usize> = (0..primitives.len()).collect();
+    order.sort_by_key(|&i| primitives[i].layer);
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut layer_start = 0;
+    let mut current_layer = None;
+    for i in order {
+        let primitive = &primitives[i];
+        if current_layer != Some(primitive.layer) {
+            current_layer = Some(primitive.layer);
+            layer_start = groups.len();
+        }
+
+        // Walk back through this layer's groups until one with the same state, stopping at
+        // anything the primitive would have to be drawn after
+        let mut target = None;
+        for (index, group) in groups[layer_start..].iter().enumerate().rev() {
+            if group.key == primitive.key {
+                target = Some(layer_start + index);
+                break;
+            }
+            if group.bounds.intersects(&primitive.bounds) {
+                break;
+            }
+        }
+
+        match target {
+            Some(index) => {
+                let group = &mut groups[index];
+                group.primitives.push(i);
+                group.bounds = group.bounds.union(&primitive.bounds);
+            }
+            None => {
+                groups.push(Group {
+                    key: primitive.key,
+                    primitives: vec![i],
+                    bounds: primitive.bounds,
+                });
+            }
+        }
+    }
+
+    let mut sorted = Vec::with_capacity(primitives.len());
+    let mut batches: Vec<Batch> = Vec::new();
+    // Items of each kind placed so far
+    let mut counts: Vec<(DrawKind, u32)> = Vec::new();
+    for group in groups {
+        let count = match counts.iter_mut().find(|(kind, _)| *kind == group.key.kind) {
+            Some((_, count)) => count,
+            None => {
+                counts.push((group.key.kind, 0));
+                &mut counts.last_mut().unwrap().1
+            }
+        };
+        let start = *count;
+        for &i in &group.primitives {
+            *count += primitives[i].items.len() as u32;
+            sorted.push(i);
+        }
+        let end = *count;
+
+        match batches.last_mut() {
+            Some(batch) if batch.key == group.key => batch.items.end = end,
+            _ => batches.push(Batch { key: group.key, items: start..end }),
+        }
+    }
+
+    (sorted, batches)
+}
+
+/// Copies the items of the primitives of `kind` out of `items`, in the draw order returned by
+/// `build_batches`, which is the order its batch ranges refer to.
+pub(crate) fn sorted_items<T: Copy>(
+    primitives: &[Primitive],
+    order: &[usize],
+    kind: DrawKind,
+    items: &[T]
+) -> Vec<T> {
+    order
+        .iter()
+        .map(|&i| &primitives[i])
+        .filter(|primitive| primitive.key.kind == kind)
+        .flat_map(|primitive| &items[primitive.items.start as usize..primitive.items.end as usize])
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONE: Option<usize> = None;
+
+    /// Primitive number `index`, drawn as indices `index * 3..index * 3 + 3`.
+    fn primitive(index: u32, layer: i32, material: Option<usize>, bounds: Rect) -> Primitive {
+        Primitive {
+            layer,
+            key: BatchKey {
+                material: material.map(MaterialId),
+                ..BatchKey::new(DrawKind::Shapes)
+            },
+            items: index * 3..index * 3 + 3,
+            bounds,
+        }
+    }
+
+    /// Primitive number `index`, drawn as the `kind` instances `index * 3..index * 3 + 3`.
+    fn instances(index: u32, layer: i32, kind: DrawKind, bounds: Rect) -> Primitive {
+        Primitive { key: BatchKey::new(kind), ..primitive(index, layer, None, bounds) }
+    }
+
+    /// Builds batches and returns the primitives in draw order and each batch's material and
+    /// primitive count.
+    fn batch(primitives: &[Primitive]) -> (Vec<u32>, Vec<(Option<usize>, u32)>) {
+        let (order, batches) = build_batches(primitives);
+
+        let order = order.iter().map(|&i| i as u32).collect();
+        let batches = batches
+            .iter()
+            .map(|batch| {
+                let material = batch.key.material.map(|material| material.0);
+                (material, (batch.items.end - batch.items.start) / 3)
+            })
+            .collect();
+        (order, batches)
+    }
+
+    #[test]
+    fn disjoint_primitives_share_a_batch() {
+        let (order, batches) = batch(
+            &[
+                primitive(0, 0, NONE, Rect::new(0.0, 0.0, 10.0, 10.0)),
+                primitive(1, 0, Some(1), Rect::new(20.0, 0.0, 10.0, 10.0)),
+                primitive(2, 0, NONE, Rect::new(40.0, 0.0, 10.0, 10.0)),
+            ]
+        );
+
+        assert_eq!(order, [0, 2, 1]);
+        assert_eq!(batches, [(None, 2), (Some(1), 1)]);
+    }
+
+    #[test]
+    fn overlapping_primitives_keep_their_order() {
+        let (order, batches) = batch(
+            &[
+                primitive(0, 0, NONE, Rect::new(0.0, 0.0, 10.0, 10.0)),
+                primitive(1, 0, Some(1), Rect::new(20.0, 0.0, 10.0, 10.0)),
+                primitive(2, 0, NONE, Rect::new(25.0, 5.0, 10.0, 10.0)),
+            ]
+        );
+
+        assert_eq!(order, [0, 1, 2]);
+        assert_eq!(batches, [(None, 1), (Some(1), 1), (None, 1)]);
+    }
+
+    #[test]
+    fn primitives_only_join_groups_past_what_they_overlap() {
+        // Primitive 3 overlaps primitive 1, so it cannot join primitive 0. Primitive 4 overlaps
+        // nothing and moves back to primitive 1
+        let (order, batches) = batch(
+            &[
+                primitive(0, 0, Some(1), Rect::new(0.0, 0.0, 10.0, 10.0)),
+                primitive(1, 0, NONE, Rect::new(0.0, 0.0, 10.0, 10.0)),
+                primitive(2, 0, Some(2), Rect::new(20.0, 0.0, 10.0, 10.0)),
+                primitive(3, 0, Some(1), Rect::new(5.0, 5.0, 10.0, 10.0)),
+                primitive(4, 0, NONE, Rect::new(40.0, 0.0, 10.0, 10.0)),
+            ]
+        );
+
+        assert_eq!(order, [0, 1, 4, 2, 3]);
+        assert_eq!(batches, [(Some(1), 1), (None, 2), (Some(2), 1), (Some(1), 1)]);
+    }
+
+    #[test]
+    fn layers_are_drawn_in_order() {
+        let (order, batches) = batch(
+            &[
+                primitive(0, 1, NONE, Rect::new(0.0, 0.0, 10.0, 10.0)),
+                primitive(1, 0, Some(1), Rect::new(20.0, 0.0, 10.0, 10.0)),
+                primitive(2, 1, NONE, Rect::new(40.0, 0.0, 10.0, 10.0)),
+                primitive(3, 0, NONE, Rect::new(60.0, 0.0, 10.0, 10.0)),
+                primitive(4, -1, Some(1), Rect::new(80.0, 0.0, 10.0, 10.0)),
+            ]
+        );
+
+        // Adjacent groups of neighbouring layers still merge into one draw call
+        assert_eq!(order, [4, 1, 3, 0, 2]);
+        assert_eq!(batches, [(Some(1), 2), (None, 3)]);
+    }
+
+    #[test]
+    fn primitives_never_join_a_lower_layer() {
+        let (order, batches) = batch(
+            &[
+                primitive(0, 0, NONE, Rect::new(0.0, 0.0, 10.0, 10.0)),
+                primitive(1, 0, Some(1), Rect::new(20.0, 0.0, 10.0, 10.0)),
+                primitive(2, 1, NONE, Rect::new(40.0, 0.0, 10.0, 10.0)),
+            ]
+        );
+
+        assert_eq!(order, [0, 1, 2]);
+        assert_eq!(batches, [(None, 1), (Some(1), 1), (None, 1)]);
+    }
+
+    #[test]
+    fn negative_sizes_count_as_overlapping() {
+        // Spans 0..10 on both axes, like the second primitive
+        let flipped = Rect::new(10.0, 10.0, -10.0, -10.0);
+        let (order, _) = batch(
+            &[
+                primitive(0, 0, NONE, Rect::new(40.0, 0.0, 10.0, 10.0)),
+                primitive(1, 0, Some(1), Rect::new(0.0, 0.0, 10.0, 10.0)),
+                primitive(2, 0, NONE, flipped),
+            ]
+        );
+
+        assert_eq!(order, [0, 1, 2]);
+        assert_eq!(flipped.normalized(), Rect::new(0.0, 0.0, 10.0, 10.0));
+        assert_eq!(
+            flipped.union(&Rect::new(20.0, 20.0, -5.0, 5.0)),
+            Rect::new(0.0, 0.0, 20.0, 25.0)
+        );
+    }
+
+    #[test]
+    fn instanced_shapes_are_ordered_by_layer() {
+        let (order, batches) = build_batches(
+            &[
+                instances(0, 1, DrawKind::Rectangles, Rect::new(0.0, 0.0, 10.0, 10.0)),
+                primitive(1, 0, NONE, Rect::new(0.0, 0.0, 10.0, 10.0)),
+                instances(2, 0, DrawKind::Circles, Rect::new(5.0, 5.0, 10.0, 10.0)),
+                primitive(3, 1, NONE, Rect::new(5.0, 5.0, 10.0, 10.0)),
+            ]
+        );
+
+        assert_eq!(order, [1, 2, 0, 3]);
+        let kinds: Vec<_> = batches
+            .iter()
+            .map(|batch| batch.key.kind)
+            .collect();
+        assert_eq!(kinds, [
+            DrawKind::Shapes,
+            DrawKind::Circles,
+            DrawKind::Rectangles,
+            DrawKind::Shapes,
+        ]);
+    }
+
+    #[test]
+    fn batch_ranges_refer_to_the_sorted_items_of_their_kind() {
+        // The two rectangle groups are separated by an overlapping shape and stay apart
+        let primitives = [
+            instances(0, 0, DrawKind::Rectangles, Rect::new(0.0, 0.0, 10.0, 10.0)),
+            primitive(1, 0, NONE, Rect::new(5.0, 5.0, 10.0, 10.0)),
+            instances(2, 0, DrawKind::Rectangles, Rect::new(10.0, 10.0, 10.0, 10.0)),
+            instances(3, -1, DrawKind::Rectangles, Rect::new(40.0, 0.0, 10.0, 10.0)),
+        ];
+        let items: Vec<u32> = (0..12).collect();
+        let (order, batches) = build_batches(&primitives);
+        let rectangles = sorted_items(&primitives, &order, DrawKind::Rectangles, &items);
+        let shapes = sorted_items(&primitives, &order, DrawKind::Shapes, &items);
+
+        assert_eq!(rectangles, [9, 10, 11, 0, 1, 2, 6, 7, 8]);
+        assert_eq!(shapes, [3, 4, 5]);
+        let ranges: Vec<_> = batches
+            .iter()
+            .map(|batch| (batch.key.kind, batch.items.clone()))
+            .collect();
+        assert_eq!(ranges, [
+            (DrawKind::Rectangles, 0..6),
+            (DrawKind::Shapes, 0..3),
+            (DrawKind::Rectangles, 6..9),
+        ]);
+    }
+
+    #[test]
+    fn mesh_draws_batch_per_mesh() {
+        let draw = |index, layer, mesh, bounds| Primitive {
+            key: BatchKey { mesh: Some(MeshId(mesh)), ..BatchKey::new(DrawKind::Meshes) },
+            ..instances(index, layer, DrawKind::Meshes, bounds)
+        };
+        let primitives = [
+            draw(0, 1, 0, Rect::new(0.0, 0.0, 10.0, 10.0)),
+            draw(1, 0, 1, Rect::new(0.0, 0.0, 10.0, 10.0)),
+            draw(2, 1, 0, Rect::new(20.0, 0.0, 10.0, 10.0)),
+            primitive(3, 0, NONE, Rect::new(5.0, 5.0, 10.0, 10.0)),
+        ];
+        let items: Vec<u32> = (0..12).collect();
+        let (order, batches) = build_batches(&primitives);
+
+        assert_eq!(order, [1, 3, 0, 2]);
+        assert_eq!(sorted_items(&primitives, &order, DrawKind::Meshes, &items), [
+            3, 4, 5, 0, 1, 2, 6, 7, 8,
+        ]);
+        let ranges: Vec<_> = batches
+            .iter()
+            .map(|batch| (batch.key.kind, batch.key.mesh, batch.items.clone()))
+            .collect();
+        assert_eq!(ranges, [
+            (DrawKind::Meshes, Some(MeshId(1)), 0..3),
+            (DrawKind::Shapes, None, 0..3),
+            (DrawKind::Meshes, Some(MeshId(0)), 3..9),
+        ]);
+    }
+}
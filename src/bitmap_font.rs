@@ -0,0 +1,948 @@
+//! AngelCode BMFont bitmap fonts.
+//!
+//! A `.fnt` descriptor, in either the text or the XML flavor, lists where every character sits on
+//! the font's page images. Text is laid out with the descriptor's advances and kerning pairs and
+//! drawn as textured quads with nearest-neighbor sampling, so pixel fonts stay crisp.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use wgpu::{
+    BindGroup,
+    BindGroupLayout,
+    BlendState,
+    Buffer,
+    BufferAddress,
+    BufferDescriptor,
+    BufferUsages,
+    Device,
+    Sampler,
+    ShaderModule,
+};
+
+use crate::error::RenderError;
+use crate::init::grow_buffer;
+use crate::profiler::FrameCounters;
+use crate::shader_types::BitmapTextVertex;
+use crate::types::{ FontId, HorizontalAlign, Rect, VerticalAlign, U32_SIZE };
+
+/// Where a character sits on its page, in page pixels.
+#[derive(Copy, Clone)]
+struct BitmapChar {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    x_offset: f32,
+    y_offset: f32,
+    x_advance: f32,
+    page: usize,
+}
+
+/// The parsed contents of a `.fnt` descriptor.
+pub(crate) struct FontDescriptor {
+    // Pixel size the font was generated at
+    size: f32,
+    line_height: f32,
+    page_size: (f32, f32),
+    pub(crate) pages: Vec<String>,
+    chars: HashMap<char, BitmapChar>,
+    kerning: HashMap<(char, char), f32>,
+}
+
+/// A glyph placed on a line, relative to the line's left edge.
+pub(crate) struct PlacedGlyph {
+    pub(crate) byte_index: usize,
+    pub(crate) x: f32,
+    pub(crate) advance: f32,
+    c: char,
+}
+
+#[derive(Default)]
+pub(crate) struct BitmapLine {
+    // Width up to the last non-space glyph
+    pub(crate) width: f32,
+    pub(crate) glyphs: Vec<PlacedGlyph>,
+}
+
+impl BitmapLine {
+    fn finish(mut self) -> Self {
+        self.width = self.glyphs
+            .iter()
+            .rev()
+            .find(|glyph| !glyph.c.is_whitespace())
+            .map_or(0.0, |glyph| glyph.x + glyph.advance);
+        self
+    }
+}
+
+impl FontDescriptor {
+    /// Parses a descriptor in the text or XML format. Malformed values are reported with the
+    /// line they are on.
+    pub(crate) fn parse(source: &str) -> Result<Self, RenderError> {
+        let source = source.trim_start_matches('\u{feff}');
+
+        // Each tag with the 1-based line it starts on
+        let mut elements = Vec::new();
+        if source.trim_start().starts_with('<') {
+            let mut line_number = 1;
+            for element in source.split('<') {
+                let start_line = line_number;
+                line_number += element.matches('\n').count();
+                let element = element.split('>').next().unwrap_or("").trim_end_matches('/');
+                // Skips the declaration, closing tags and comments
+                if element.trim().is_empty() || element.starts_with(['?', '/', '!']) {
+                    continue;
+                }
+                elements.push((start_line, element));
+            }
+        } else {
+            elements.extend(source.lines().enumerate().map(|(index, line)| (index + 1, line)));
+        }
+
+        let mut descriptor = FontDescriptor {
+            size: 0.0,
+            line_height: 0.0,
+            page_size: (0.0, 0.0),
+            pages: Vec::new(),
+            chars: HashMap::new(),
+            kerning: HashMap::new(),
+        };
+
+        for (line_number, element) in elements {
+            let error = |message: String| {
+                RenderError::ResourceError(format!("BMFont line {}: {}", line_number, message))
+            };
+            let (tag, attributes) = parse_tag(element).map_err(error)?;
+            let attribute = |name: &str| {
+                attributes
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| *value)
+            };
+            // Missing attributes default to zero, like in BMFont itself
+            let number = |name: &str| -> Result<f32, RenderError> {
+                match attribute(name) {
+                    Some(value) =>
+                        value
+                            .parse::<f32>()
+                            .map_err(|_| error(format!("`{}` is not a number: `{}`", name, value))),
+                    None => Ok(0.0),
+                }
+            };
+            let index = |name: &str| -> Result<usize, RenderError> {
+                let value = number(name)?;
+                if value < 0.0 || value.fract() != 0.0 {
+                    return Err(error(format!("`{}` is not an index: {}", name, value)));
+                }
+                Ok(value as usize)
+            };
+            let character = |name: &str| -> Result<char, RenderError> {
+                let value = attribute(name).ok_or_else(|| error(format!("`{}` is missing", name)))?;
+                value
+                    .parse::<u32>()
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| {
+                        error(format!("`{}` is not a character code: `{}`", name, value))
+                    })
+            };
+
+            match tag {
+                // Negative sizes mean the size was matched to the character height
+                "info" => {
+                    descriptor.size = number("size")?.abs();
+                }
+                "common" => {
+                    descriptor.line_height = number("lineHeight")?;
+                    descriptor.page_size = (number("scaleW")?, number("scaleH")?);
+                }
+                "page" => {
+                    let id = index("id")?;
+                    let file = attribute("file").ok_or_else(|| {
+                        error(format!("page {} has no file", id))
+                    })?;
+                    if descriptor.pages.len() <= id {
+                        descriptor.pages.resize(id + 1, String::new());
+                    }
+                    descriptor.pages[id] = file.to_string();
+                }
+                "char" => {
+                    let c = character("id")?;
+                    descriptor.chars.insert(c, BitmapChar {
+                        x: number("x")?,
+                        y: number("y")?,
+                        width: number("width")?,
+                        height: number("height")?,
+                        x_offset: number("xoffset")?,
+                        y_offset: number("yoffset")?,
+                        x_advance: number("xadvance")?,
+                        page: index("page")?,
+                    });
+                }
+                "kerning" => {
+                    let pair = (character("first")?, character("second")?);
+                    descriptor.kerning.insert(pair, number("amount")?);
+                }
+                _ => {}
+            }
+        }
+
+        if descriptor.line_height <= 0.0 || descriptor.page_size.0 <= 0.0 {
+            return Err(
+                RenderError::ResourceError("BMFont descriptor has no common block".to_string())
+            );
+        }
+        if descriptor.pages.is_empty() {
+            return Err(RenderError::ResourceError("BMFont descriptor has no pages".to_string()));
+        }
+        if let Some(id) = descriptor.pages.iter().position(String::is_empty) {
+            return Err(RenderError::ResourceError(format!("BMFont page {} is missing", id)));
+        }
+        let page_count = descriptor.pages.len();
+        let missing_page = descriptor.chars.iter().find(|(_, glyph)| glyph.page >= page_count);
+        if let Some((c, glyph)) = missing_page {
+            return Err(
+                RenderError::ResourceError(
+                    format!("BMFont character {:?} is on missing page {}", c, glyph.page)
+                )
+            );
+        }
+        if descriptor.size <= 0.0 {
+            descriptor.size = descriptor.line_height;
+        }
+
+        Ok(descriptor)
+    }
+
+    fn scale(&self, size: f32) -> f32 {
+        size / self.size
+    }
+
+    pub(crate) fn line_height(&self, size: f32) -> f32 {
+        self.line_height * self.scale(size)
+    }
+
+    /// Breaks `text` into lines at newlines and, once a line is wider than `max_width`, after its
+    /// last space. Characters missing from the font are skipped.
+    pub(crate) fn layout(&self, text: &str, size: f32, max_width: f32) -> Vec<BitmapLine> {
+        let scale = self.scale(size);
+        let mut lines = Vec::new();
+        let mut offset = 0;
+
+        for paragraph in text.split('\n') {
+            let mut line = BitmapLine::default();
+            let mut pen = 0.0;
+            let mut previous = None;
+            // Glyph index just after the last space, where the line can be broken
+            let mut break_index = None;
+
+            for (index, c) in paragraph.char_indices() {
+                let Some(glyph) = self.chars.get(&c) else {
+                    continue;
+                };
+                if let Some(previous) = previous {
+                    pen += self.kerning.get(&(previous, c)).copied().unwrap_or(0.0) * scale;
+                }
+                let advance = glyph.x_advance * scale;
+
+                if
+                    !c.is_whitespace() &&
+                    pen + advance > max_width &&
+                    let Some(break_index) = break_index.take()
+                {
+                    let tail: Vec<PlacedGlyph> = line.glyphs.split_off(break_index);
+                    let shift = tail.first().map_or(pen, |glyph| glyph.x);
+                    lines.push(line.finish());
+
+                    line = BitmapLine {
+                        width: 0.0,
+                        glyphs: tail
+                            .into_iter()
+                            .map(|glyph| PlacedGlyph { x: glyph.x - shift, ..glyph })
+                            .collect(),
+                    };
+                    pen -= shift;
+                }
+
+                line.glyphs.push(PlacedGlyph { byte_index: offset + index, x: pen, advance, c });
+                pen += advance;
+                if c == ' ' {
+                    break_index = Some(line.glyphs.len());
+                }
+                previous = Some(c);
+            }
+
+            lines.push(line.finish());
+            offset += paragraph.len() + 1;
+        }
+
+        lines
+    }
+}
+
+/// A tag name with its `key=value` attributes.
+type Tag<'a> = (&'a str, Vec<(&'a str, &'a str)>);
+
+/// Splits a `tag key=value key="quoted value"` line into its tag and attributes. XML elements
+/// have the same shape once the angle brackets are stripped.
+fn parse_tag(line: &str) -> Result<Tag<'_>, String> {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+    let mut attributes = Vec::new();
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(format!("expected `key=value`, found `{}=`", key));
+        }
+        let value = value.trim_start();
+        let (value, remainder) = match value.strip_prefix('"') {
+            Some(quoted) =>
+                quoted
+                    .split_once('"')
+                    .ok_or_else(|| format!("unterminated quote in `{}`", key))?,
+            None => value.split_once(char::is_whitespace).unwrap_or((value, "")),
+        };
+        attributes.push((key, value));
+        rest = remainder;
+    }
+
+    Ok((tag, attributes))
+}
+
+/// Text to lay out with a bitmap font. Mirrors the parts of a glyph brush `Section` that bitmap
+/// fonts support.
+pub(crate) struct BitmapSection<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) font: FontId,
+    pub(crate) position: (f32, f32),
+    pub(crate) size: f32,
+    pub(crate) color: [f32; 4],
+    pub(crate) h_align: HorizontalAlign,
+    pub(crate) v_align: VerticalAlign,
+    pub(crate) max_width: f32,
+    pub(crate) clip: Option<Rect>,
+}
+
+struct BitmapFont {
+    descriptor: FontDescriptor,
+    pages: Vec<BindGroup>,
+}
+
+struct QueuedQuad {
+    font: usize,
+    page: usize,
+    vertices: [BitmapTextVertex; 4],
+}
+
+/// A run of indices drawn with one page texture.
+struct PageDraw {
+    font: usize,
+    page: usize,
+    indices: Range<u32>,
+}
+
+pub(crate) struct BitmapFontRenderer {
+    pipeline: wgpu::RenderPipeline,
+    page_layout: BindGroupLayout,
+    sampler: Sampler,
+    // Keyed by the font's `FontId`
+    fonts: HashMap<usize, BitmapFont>,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    queued: Vec<QueuedQuad>,
+    draws: Vec<PageDraw>,
+}
+
+impl BitmapFontRenderer {
+    pub(crate) fn new(
+        device: &Device,
+        globals_layout: &BindGroupLayout,
+        format: wgpu::TextureFormat,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Self {
+        let page_layout = create_page_bind_group_layout(device);
+        let (vert_shader, frag_shader) = create_bitmap_shaders(device);
+        let pipeline = create_bitmap_pipeline(
+            device,
+            globals_layout,
+            &page_layout,
+            format,
+            &vert_shader,
+            &frag_shader,
+            cache
+        );
+        let sampler = device.create_sampler(
+            &(wgpu::SamplerDescriptor {
+                label: Some("Bitmap Font Sampler"),
+                mag_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            })
+        );
+
+        let vertex_buffer = device.create_buffer(
+            &(BufferDescriptor {
+                label: Some("Bitmap Font Vertex Buffer"),
+                size: BitmapTextVertex::SIZE * 256,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+        let index_buffer = device.create_buffer(
+            &(BufferDescriptor {
+                label: Some("Bitmap Font Index Buffer"),
+                size: U32_SIZE * 384,
+                usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+
+        Self {
+            pipeline,
+            page_layout,
+            sampler,
+            fonts: HashMap::new(),
+            vertex_buffer,
+            index_buffer,
+            queued: Vec::new(),
+            draws: Vec::new(),
+        }
+    }
+
+    /// Uploads the page images of a font. `pages` must be in page id order.
+    pub(crate) fn add_font(
+        &mut self,
+        device: &Device,
+        queue: &wgpu::Queue,
+        id: FontId,
+        descriptor: FontDescriptor,
+        pages: &[&[u8]]
+    ) -> Result<(), RenderError> {
+        if pages.len() < descriptor.pages.len() {
+            return Err(
+                RenderError::ResourceError(
+                    format!(
+                        "BMFont has {} pages but {} page images were given",
+                        descriptor.pages.len(),
+                        pages.len()
+                    )
+                )
+            );
+        }
+
+        let mut bind_groups = Vec::with_capacity(descriptor.pages.len());
+        for (index, bytes) in pages.iter().take(descriptor.pages.len()).enumerate() {
+            let pixels = image
+                ::load_from_memory(bytes)
+                .map_err(|e| {
+                    RenderError::ResourceError(format!("Invalid BMFont page {}: {}", index, e))
+                })?
+                .to_rgba8();
+            let size = wgpu::Extent3d {
+                width: pixels.width(),
+                height: pixels.height(),
+                depth_or_array_layers: 1,
+            };
+
+            let texture = device.create_texture(
+                &(wgpu::TextureDescriptor {
+                    label: Some("Bitmap Font Page"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                })
+            );
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &pixels,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(pixels.width() * 4),
+                    rows_per_image: None,
+                },
+                size
+            );
+
+            let view = texture.create_view(&Default::default());
+            bind_groups.push(
+                device.create_bind_group(
+                    &(wgpu::BindGroupDescriptor {
+                        label: Some("Bitmap Font Page BG"),
+                        layout: &self.page_layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::TextureView(&view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::Sampler(&self.sampler),
+                            },
+                        ],
+                    })
+                )
+            );
+        }
+
+        self.fonts.insert(id.0, BitmapFont { descriptor, pages: bind_groups });
+        Ok(())
+    }
+
+    /// Rebuilds the pipeline from recompiled shaders, keeping the old one if they are invalid.
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn reload_shaders(
+        &mut self,
+        device: &Device,
+        globals_layout: &BindGroupLayout,
+        format: wgpu::TextureFormat,
+        vert_shader: &ShaderModule,
+        frag_shader: &ShaderModule,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Result<(), RenderError> {
+        self.pipeline = crate::init::validated(device, || {
+            create_bitmap_pipeline(
+                device,
+                globals_layout,
+                &self.page_layout,
+                format,
+                vert_shader,
+                frag_shader,
+                cache
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Returns the metrics of a bitmap font, or `None` if `id` is not one.
+    pub(crate) fn font(&self, id: FontId) -> Option<&FontDescriptor> {
+        self.fonts.get(&id.0).map(|font| &font.descriptor)
+    }
+
+    /// Lays out a section and queues one quad per visible glyph.
+    pub(crate) fn queue(&mut self, section: &BitmapSection) {
+        let Some(font) = self.fonts.get(&section.font.0) else {
+            return;
+        };
+        let descriptor = &font.descriptor;
+        let scale = descriptor.scale(section.size);
+        let line_height = descriptor.line_height(section.size);
+        let (page_width, page_height) = descriptor.page_size;
+
+        let lines = descriptor.layout(section.text, section.size, section.max_width);
+        let block_height = line_height * (lines.len() as f32);
+        let top =
+            section.position.1 -
+            (match section.v_align {
+                VerticalAlign::Top => 0.0,
+                VerticalAlign::Middle => block_height * 0.5,
+                VerticalAlign::Bottom => block_height,
+            });
+
+        for (line_index, line) in lines.iter().enumerate() {
+            let left =
+                section.position.0 -
+                (match section.h_align {
+                    HorizontalAlign::Left => 0.0,
+                    HorizontalAlign::Center => line.width * 0.5,
+                    HorizontalAlign::Right => line.width,
+                });
+            let line_top = top + line_height * (line_index as f32);
+
+            for placed in &line.glyphs {
+                let glyph = descriptor.chars[&placed.c];
+                if glyph.width <= 0.0 || glyph.height <= 0.0 {
+                    continue;
+                }
+
+                // Snapped to whole pixels so nearest sampling maps texels evenly
+                let rect = Rect::new(
+                    (left + placed.x + glyph.x_offset * scale).round(),
+                    (line_top + glyph.y_offset * scale).round(),
+                    glyph.width * scale,
+                    glyph.height * scale
+                );
+                let uv = Rect::new(
+                    glyph.x / page_width,
+                    glyph.y / page_height,
+                    glyph.width / page_width,
+                    glyph.height / page_height
+                );
+                let Some((rect, uv)) = (match section.clip {
+                    Some(clip) => clip_quad(rect, uv, clip),
+                    None => Some((rect, uv)),
+                }) else {
+                    continue;
+                };
+
+                let vertex = |x: f32, y: f32, u: f32, v: f32| BitmapTextVertex {
+                    position: [x, y],
+                    uv: [u, v],
+                    color: section.color,
+                };
+                self.queued.push(QueuedQuad {
+                    font: section.font.0,
+                    page: glyph.page,
+                    vertices: [
+                        vertex(rect.x, rect.y, uv.x, uv.y),
+                        vertex(rect.right(), rect.y, uv.right(), uv.y),
+                        vertex(rect.right(), rect.bottom(), uv.right(), uv.bottom()),
+                        vertex(rect.x, rect.bottom(), uv.x, uv.bottom()),
+                    ],
+                });
+            }
+        }
+    }
+
+    /// Uploads this frame's quads, grouped by page texture.
+    pub(crate) fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &wgpu::Queue,
+        counters: &mut FrameCounters
+    ) {
+        self.draws.clear();
+        if self.queued.is_empty() {
+            return;
+        }
+
+        // Stable, so quads sharing a page keep their queue order
+        self.queued.sort_by_key(|quad| (quad.font, quad.page));
+
+        let mut vertices = Vec::with_capacity(self.queued.len() * 4);
+        let mut indices = Vec::with_capacity(self.queued.len() * 6);
+        for quad in self.queued.drain(..) {
+            let vertex_offset = vertices.len() as u32;
+            let index_offset = indices.len() as u32;
+            vertices.extend_from_slice(&quad.vertices);
+            indices.extend_from_slice(
+                &[
+                    vertex_offset,
+                    vertex_offset + 1,
+                    vertex_offset + 2,
+                    vertex_offset,
+                    vertex_offset + 2,
+                    vertex_offset + 3,
+                ]
+            );
+
+            match self.draws.last_mut() {
+                Some(draw) if draw.font == quad.font && draw.page == quad.page => {
+                    draw.indices.end += 6;
+                }
+                _ =>
+                    self.draws.push(PageDraw {
+                        font: quad.font,
+                        page: quad.page,
+                        indices: index_offset..index_offset + 6,
+                    }),
+            }
+        }
+
+        grow_buffer(
+            device,
+            &mut self.vertex_buffer,
+            Some("Bitmap Font Vertex Buffer"),
+            BitmapTextVertex::SIZE * (vertices.len() as BufferAddress)
+        );
+        grow_buffer(
+            device,
+            &mut self.index_buffer,
+            Some("Bitmap Font Index Buffer"),
+            U32_SIZE * (indices.len() as BufferAddress)
+        );
+        let vertex_bytes: &[u8] = bytemuck::cast_slice(&vertices);
+        let index_bytes: &[u8] = bytemuck::cast_slice(&indices);
+        queue.write_buffer(&self.vertex_buffer, 0, vertex_bytes);
+        queue.write_buffer(&self.index_buffer, 0, index_bytes);
+        counters.upload(vertex_bytes.len() + index_bytes.len());
+    }
+
+    pub(crate) fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        globals_bind_group: &BindGroup,
+        counters: &mut FrameCounters
+    ) {
+        if self.draws.is_empty() {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(
+            &(wgpu::RenderPassDescriptor {
+                label: Some("Bitmap Text Render Pass"),
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    }),
+                ],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            })
+        );
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, globals_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+
+        for draw in &self.draws {
+            let Some(page) = self.fonts[&draw.font].pages.get(draw.page) else {
+                continue;
+            };
+            render_pass.set_bind_group(1, page, &[]);
+            render_pass.draw_indexed(draw.indices.clone(), 0, 0..1);
+            counters.draw(draw.indices.len() as u32, 1);
+        }
+    }
+}
+
+/// Clips a quad and its texture coordinates to `clip`. Returns `None` if nothing is left.
+fn clip_quad(rect: Rect, uv: Rect, clip: Rect) -> Option<(Rect, Rect)> {
+    let min_x = rect.x.max(clip.x);
+    let min_y = rect.y.max(clip.y);
+    let max_x = rect.right().min(clip.right());
+    let max_y = rect.bottom().min(clip.bottom());
+    if min_x >= max_x || min_y >= max_y {
+        return None;
+    }
+
+    let u = |x: f32| uv.x + ((x - rect.x) / rect.width) * uv.width;
+    let v = |y: f32| uv.y + ((y - rect.y) / rect.height) * uv.height;
+
+    Some((
+        Rect::new(min_x, min_y, max_x - min_x, max_y - min_y),
+        Rect::new(u(min_x), v(min_y), u(max_x) - u(min_x), v(max_y) - v(min_y)),
+    ))
+}
+
+fn create_page_bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(
+        &(wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bitmap Font Page BGL"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    )
+}
+
+fn create_bitmap_shaders(device: &Device) -> (ShaderModule, ShaderModule) {
+    let vert_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("bitmap text vertex shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            std::borrow::Cow::Borrowed(include_str!("../res/shaders/bitmap_text.vert.wgsl"))
+        ),
+    });
+    let frag_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("bitmap text fragment shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            std::borrow::Cow::Borrowed(include_str!("../res/shaders/bitmap_text.frag.wgsl"))
+        ),
+    });
+
+    (vert_shader, frag_shader)
+}
+
+fn create_bitmap_pipeline(
+    device: &Device,
+    globals_layout: &BindGroupLayout,
+    page_layout: &BindGroupLayout,
+    format: wgpu::TextureFormat,
+    vert_shader: &ShaderModule,
+    frag_shader: &ShaderModule,
+    cache: Option<&wgpu::PipelineCache>
+) -> wgpu::RenderPipeline {
+
+    let pipeline_layout = device.create_pipeline_layout(
+        &(wgpu::PipelineLayoutDescriptor {
+            label: Some("Bitmap Text Pipeline Layout"),
+            bind_group_layouts: &[globals_layout, page_layout],
+            push_constant_ranges: &[],
+        })
+    );
+
+    device.create_render_pipeline(
+        &(wgpu::RenderPipelineDescriptor {
+            label: Some("Bitmap Text Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: vert_shader,
+                entry_point: Some("main"),
+                buffers: &[BitmapTextVertex::DESC],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: frag_shader,
+                entry_point: Some("main"),
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache,
+        })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT_FONT: &str = r#"info face="Test Font" size=-16 bold=0 padding=0,0,0,0
+common lineHeight=20 base=16 scaleW=64 scaleH=64 pages=2
+page id=0 file="test_0.png"
+page id=1 file="test_1.png"
+chars count=3
+char id=65   x=0  y=0  width=8 height=12 xoffset=0 yoffset=4 xadvance=9  page=0
+char id=86   x=10 y=0  width=8 height=12 xoffset=1 yoffset=4 xadvance=10 page=1
+char id=32   x=0  y=0  width=0 height=0  xoffset=0 yoffset=0 xadvance=4  page=0
+kernings count=1
+kerning first=65 second=86 amount=-2
+"#;
+
+    const XML_FONT: &str = r#"<?xml version="1.0"?>
+<font>
+  <!-- generated -->
+  <info face="Test Font" size="32" />
+  <common lineHeight="36" base="28" scaleW="128" scaleH="128" pages="2" />
+  <pages>
+    <page id="1" file="xml_1.png" />
+    <page id="0" file="xml_0.png" />
+  </pages>
+  <chars count="2">
+    <char id="65" x="0" y="0" width="16" height="24" xoffset="0" yoffset="4"
+      xadvance="18" page="1" />
+    <char id="86" x="20" y="0" width="16" height="24" xoffset="0" yoffset="4"
+      xadvance="17" page="0" />
+  </chars>
+  <kernings count="1">
+    <kerning first="86" second="65" amount="-3" />
+  </kernings>
+</font>
+"#;
+
+    fn error_message(source: &str) -> String {
+        match FontDescriptor::parse(source) {
+            Ok(_) => panic!("parsed a malformed descriptor"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    fn positions(descriptor: &FontDescriptor, text: &str, size: f32) -> Vec<f32> {
+        let lines = descriptor.layout(text, size, f32::INFINITY);
+        lines[0].glyphs
+            .iter()
+            .map(|glyph| glyph.x)
+            .collect()
+    }
+
+    #[test]
+    fn parses_text_descriptor() {
+        let descriptor = FontDescriptor::parse(TEXT_FONT).unwrap();
+
+        assert_eq!(descriptor.size, 16.0);
+        assert_eq!(descriptor.line_height(32.0), 40.0);
+        assert_eq!(descriptor.pages, ["test_0.png", "test_1.png"]);
+        assert_eq!(descriptor.chars[&'V'].page, 1);
+        assert_eq!(descriptor.kerning[&('A', 'V')], -2.0);
+        // The kerning pair pulls V two pixels closer, but only in that order
+        assert_eq!(positions(&descriptor, "AV", 16.0), [0.0, 7.0]);
+        assert_eq!(positions(&descriptor, "VA", 16.0), [0.0, 10.0]);
+        assert_eq!(positions(&descriptor, "AV", 32.0), [0.0, 14.0]);
+    }
+
+    #[test]
+    fn parses_xml_descriptor() {
+        let descriptor = FontDescriptor::parse(XML_FONT).unwrap();
+
+        assert_eq!(descriptor.size, 32.0);
+        // Pages are placed by id, not by the order they are listed in
+        assert_eq!(descriptor.pages, ["xml_0.png", "xml_1.png"]);
+        assert_eq!(descriptor.chars[&'A'].page, 1);
+        assert_eq!(descriptor.chars[&'V'].x, 20.0);
+        assert_eq!(positions(&descriptor, "VA", 32.0), [0.0, 14.0]);
+    }
+
+    #[test]
+    fn wraps_after_the_last_space() {
+        let descriptor = FontDescriptor::parse(TEXT_FONT).unwrap();
+        let lines = descriptor.layout("AV AV", 16.0, 20.0);
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].width, 17.0);
+        assert_eq!(lines[1].glyphs[0].byte_index, 3);
+        assert_eq!(lines[1].glyphs[0].x, 0.0);
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        let bad_number = TEXT_FONT.replace("xadvance=10", "xadvance=ten");
+        let message = error_message(&bad_number);
+        assert!(message.contains("line 7") && message.contains("xadvance"), "{}", message);
+
+        let bad_quote = TEXT_FONT.replace("file=\"test_1.png\"", "file=\"test_1.png");
+        let message = error_message(&bad_quote);
+        assert!(message.contains("line 4") && message.contains("unterminated"), "{}", message);
+
+        let bad_kerning = TEXT_FONT.replace("second=86", "second=-1");
+        assert!(error_message(&bad_kerning).contains("line 10"));
+
+        let bad_xml = XML_FONT.replace("page=\"0\"", "page=\"zero\"");
+        let message = error_message(&bad_xml);
+        assert!(message.contains("line 13") && message.contains("page"), "{}", message);
+    }
+
+    #[test]
+    fn reports_missing_pages() {
+        let missing_page = TEXT_FONT.replace("page id=0 file=\"test_0.png\"\n", "");
+        assert!(error_message(&missing_page).contains("page 0 is missing"));
+
+        let unknown_page = TEXT_FONT.replace("xadvance=10 page=1", "xadvance=10 page=5");
+        assert!(error_message(&unknown_page).contains("missing page 5"));
+
+        let no_common = TEXT_FONT.replace("common", "uncommon");
+        assert!(error_message(&no_common).contains("no common block"));
+    }
+}
@@ -0,0 +1,824 @@
+//! Compute shader dispatch.
+//!
+//! Compute pipelines are built from WGSL, typically the `.comp.wgsl` output of build.rs, with
+//! their bind group layout derived from the shader. The bindings of that layout are also
+//! reflected with naga, so dispatches are checked against them on the CPU. Dispatches and
+//! readback copies are queued in order and recorded at the start of the next frame, before any
+//! render pass. Readbacks complete asynchronously once the GPU has finished the copy and the
+//! device has been polled.
+
+use std::sync::{ Arc, Mutex };
+
+use wgpu::{ Buffer, BufferAddress, BufferUsages, Device, Queue, TextureUsages };
+
+use crate::error::RenderError;
+use crate::init::validated;
+use crate::types::{ ComputeBinding, ComputePipelineId, StorageBufferId, StorageTextureId };
+
+type ReadbackResult = Arc<Mutex<Option<Result<Vec<u8>, RenderError>>>>;
+
+/// Bytes copied back from a storage buffer or texture. The result arrives a frame or more after
+/// the read was queued, see `Renderer::poll_readbacks`.
+pub struct Readback {
+    result: ReadbackResult,
+}
+
+impl Readback {
+    /// Whether the data arrived and has not been taken yet.
+    pub fn is_ready(&self) -> bool {
+        self.result.lock().is_ok_and(|result| result.is_some())
+    }
+
+    /// Takes the data once it arrived. Returns `None` while the copy is in flight.
+    pub fn try_take(&self) -> Option<Result<Vec<u8>, RenderError>> {
+        self.result.lock().ok()?.take()
+    }
+
+    /// Takes the data as a vector of `T`.
+    pub fn try_take_as<T: bytemuck::Pod>(&self) -> Option<Result<Vec<T>, RenderError>> {
+        self.try_take().map(|bytes| bytes.map(|bytes| bytemuck::pod_collect_to_vec(&bytes)))
+    }
+}
+
+/// A binding of group 0 that a compute entry point uses, as wgpu derives it into the layout.
+#[derive(Clone, Debug, PartialEq)]
+enum ShaderBinding {
+    /// The smallest buffer the shader's type fits in
+    Buffer {
+        min_size: BufferAddress,
+        uniform: bool,
+    },
+    StorageTexture(naga::StorageFormat),
+    SampledTexture(naga::ScalarKind),
+    /// Resources dispatches cannot bind, described for the error
+    Unsupported(&'static str),
+}
+
+/// A resource passed to a dispatch, as far as its compatibility with a binding goes.
+#[derive(Copy, Clone, Debug)]
+enum BoundResource {
+    Buffer(BufferAddress),
+    Texture(wgpu::TextureFormat),
+}
+
+struct ComputePipeline {
+    pipeline: wgpu::ComputePipeline,
+    // Sorted by binding index
+    bindings: Vec<(u32, ShaderBinding)>,
+}
+
+struct StorageTexture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    bytes_per_pixel: u32,
+}
+
+enum ComputeCommand {
+    Dispatch {
+        pipeline: ComputePipelineId,
+        bind_group: Option<wgpu::BindGroup>,
+        workgroups: [u32; 3],
+    },
+    ReadBuffer(StorageBufferId, PendingReadback),
+    ReadTexture(StorageTextureId, PendingReadback),
+}
+
+/// A copy into a mappable staging buffer, waiting to be mapped and read.
+struct PendingReadback {
+    staging: Buffer,
+    /// Unpadded and padded bytes per row of texture copies
+    rows: Option<(u32, u32)>,
+    mapped: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+    result: ReadbackResult,
+}
+
+impl PendingReadback {
+    fn new(device: &Device, size: BufferAddress, rows: Option<(u32, u32)>) -> (Self, Readback) {
+        let staging = device.create_buffer(
+            &(wgpu::BufferDescriptor {
+                label: Some("Readback Staging Buffer"),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+        let result = ReadbackResult::default();
+
+        (
+            Self {
+                staging,
+                rows,
+                mapped: Arc::default(),
+                result: result.clone(),
+            },
+            Readback { result },
+        )
+    }
+
+    /// Copies the mapped staging buffer out, dropping row padding.
+    fn finish(self, mapped: Result<(), wgpu::BufferAsyncError>) {
+        let bytes = mapped
+            .map(|()| {
+                let data = self.staging.slice(..).get_mapped_range();
+                let bytes = match self.rows {
+                    Some((unpadded, padded)) =>
+                        data
+                            .chunks(padded as usize)
+                            .flat_map(|row| &row[..unpadded as usize])
+                            .copied()
+                            .collect(),
+                    None => data.to_vec(),
+                };
+                drop(data);
+                self.staging.unmap();
+                bytes
+            })
+            .map_err(|e| RenderError::ResourceError(format!("Readback failed: {}", e)));
+
+        if let Ok(mut result) = self.result.lock() {
+            *result = Some(bytes);
+        }
+    }
+}
+
+pub(crate) struct ComputeRunner {
+    pipelines: Vec<ComputePipeline>,
+    buffers: Vec<Buffer>,
+    textures: Vec<StorageTexture>,
+    commands: Vec<ComputeCommand>,
+    // Copies recorded in the last encoder, mapped once it is submitted
+    submitted: Vec<PendingReadback>,
+    mapping: Vec<PendingReadback>,
+}
+
+impl ComputeRunner {
+    pub(crate) fn new() -> Self {
+        Self {
+            pipelines: Vec::new(),
+            buffers: Vec::new(),
+            textures: Vec::new(),
+            commands: Vec::new(),
+            submitted: Vec::new(),
+            mapping: Vec::new(),
+        }
+    }
+
+    /// Compiles `source`, which needs exactly one compute entry point. Shader and pipeline
+    /// validation errors are returned as `RenderError::ShaderError`.
+    pub(crate) fn create_pipeline(
+        &mut self,
+        device: &Device,
+        source: &str,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Result<ComputePipelineId, RenderError> {
+        let bindings = reflect_bindings(source)?;
+
+        let pipeline = validated(device, || {
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("compute shader"),
+                source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(source)),
+            });
+            // The bind group layouts are derived from the shader's bindings
+            device.create_compute_pipeline(
+                &(wgpu::ComputePipelineDescriptor {
+                    label: Some("Compute Pipeline"),
+                    layout: None,
+                    module: &shader,
+                    entry_point: None,
+                    compilation_options: Default::default(),
+                    cache,
+                })
+            )
+        })?;
+
+        self.pipelines.push(ComputePipeline { pipeline, bindings });
+        Ok(ComputePipelineId(self.pipelines.len() - 1))
+    }
+
+    pub(crate) fn create_buffer(
+        &mut self,
+        device: &Device,
+        size: BufferAddress
+    ) -> StorageBufferId {
+        // Copies work in multiples of four bytes
+        let buffer = device.create_buffer(
+            &(wgpu::BufferDescriptor {
+                label: Some("Storage Buffer"),
+                size: size.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+                usage: BufferUsages::STORAGE |
+                    BufferUsages::UNIFORM |
+                    BufferUsages::COPY_SRC |
+                    BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+
+        self.buffers.push(buffer);
+        StorageBufferId(self.buffers.len() - 1)
+    }
+
+    pub(crate) fn write_buffer(
+        &self,
+        queue: &Queue,
+        id: StorageBufferId,
+        offset: BufferAddress,
+        bytes: &[u8]
+    ) -> Result<(), RenderError> {
+        let buffer = self.buffer(id)?;
+        let end = offset + (bytes.len() as BufferAddress);
+
+        if end > buffer.size() {
+            return Err(
+                RenderError::ResourceError(
+                    format!(
+                        "Write of {}..{} exceeds storage buffer of {} bytes",
+                        offset,
+                        end,
+                        buffer.size()
+                    )
+                )
+            );
+        }
+        if
+            !offset.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) ||
+            !(bytes.len() as BufferAddress).is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+        {
+            return Err(
+                RenderError::ResourceError(
+                    "Storage buffer writes must be aligned to four bytes".to_string()
+                )
+            );
+        }
+
+        queue.write_buffer(buffer, offset, bytes);
+        Ok(())
+    }
+
+    pub(crate) fn create_texture(
+        &mut self,
+        device: &Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat
+    ) -> Result<StorageTextureId, RenderError> {
+        let features = format.guaranteed_format_features(device.features());
+        let bytes_per_pixel = format.block_copy_size(None);
+        let Some(bytes_per_pixel) = bytes_per_pixel.filter(|_| {
+            features.allowed_usages.contains(TextureUsages::STORAGE_BINDING)
+        }) else {
+            return Err(
+                RenderError::ResourceError(
+                    format!("{:?} cannot be used as a storage texture", format)
+                )
+            );
+        };
+
+        let texture = device.create_texture(
+            &(wgpu::TextureDescriptor {
+                label: Some("Storage Texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: TextureUsages::STORAGE_BINDING |
+                    TextureUsages::TEXTURE_BINDING |
+                    TextureUsages::COPY_SRC |
+                    TextureUsages::COPY_DST,
+                view_formats: &[],
+            })
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.textures.push(StorageTexture { texture, view, bytes_per_pixel });
+        Ok(StorageTextureId(self.textures.len() - 1))
+    }
+
+    pub(crate) fn write_texture(
+        &self,
+        queue: &Queue,
+        id: StorageTextureId,
+        bytes: &[u8]
+    ) -> Result<(), RenderError> {
+        let storage = self.texture(id)?;
+        let size = storage.texture.size();
+        let bytes_per_row = size.width * storage.bytes_per_pixel;
+        let expected = (bytes_per_row as usize) * (size.height as usize);
+
+        if bytes.len() != expected {
+            return Err(
+                RenderError::ResourceError(
+                    format!(
+                        "Texture data is {} bytes but the texture holds {}",
+                        bytes.len(),
+                        expected
+                    )
+                )
+            );
+        }
+
+        queue.write_texture(
+            storage.texture.as_image_copy(),
+            bytes,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+            size
+        );
+        Ok(())
+    }
+
+    /// Queues a dispatch with `bindings` bound in order to group 0. Bindings that do not match
+    /// the shader are rejected here, so no invalid bind group reaches the encoder.
+    pub(crate) fn dispatch(
+        &mut self,
+        device: &Device,
+        pipeline: ComputePipelineId,
+        bindings: &[ComputeBinding],
+        workgroups: [u32; 3]
+    ) -> Result<(), RenderError> {
+        let compute_pipeline = self.pipelines
+            .get(pipeline.0)
+            .ok_or_else(|| RenderError::ResourceError(format!("Unknown {:?}", pipeline)))?;
+
+        let resources = bindings
+            .iter()
+            .map(|binding| {
+                Ok(match *binding {
+                    ComputeBinding::Buffer(id) => BoundResource::Buffer(self.buffer(id)?.size()),
+                    ComputeBinding::Texture(id) =>
+                        BoundResource::Texture(self.texture(id)?.texture.format()),
+                })
+            })
+            .collect::<Result<Vec<_>, RenderError>>()?;
+        check_bindings(&compute_pipeline.bindings, &resources, &device.limits())?;
+
+        let bind_group = if bindings.is_empty() {
+            None
+        } else {
+            let resources = bindings
+                .iter()
+                .map(|binding| {
+                    Ok(match *binding {
+                        ComputeBinding::Buffer(id) => self.buffer(id)?.as_entire_binding(),
+                        ComputeBinding::Texture(id) =>
+                            wgpu::BindingResource::TextureView(&self.texture(id)?.view),
+                    })
+                })
+                .collect::<Result<Vec<_>, RenderError>>()?;
+            let entries: Vec<_> = resources
+                .into_iter()
+                .enumerate()
+                .map(|(binding, resource)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource,
+                })
+                .collect();
+
+            Some(
+                device.create_bind_group(
+                    &(wgpu::BindGroupDescriptor {
+                        label: Some("Compute BG"),
+                        layout: &compute_pipeline.pipeline.get_bind_group_layout(0),
+                        entries: &entries,
+                    })
+                )
+            )
+        };
+
+        self.commands.push(ComputeCommand::Dispatch { pipeline, bind_group, workgroups });
+        Ok(())
+    }
+
+    pub(crate) fn read_buffer(
+        &mut self,
+        device: &Device,
+        id: StorageBufferId
+    ) -> Result<Readback, RenderError> {
+        let size = self.buffer(id)?.size();
+        let (pending, readback) = PendingReadback::new(device, size, None);
+
+        self.commands.push(ComputeCommand::ReadBuffer(id, pending));
+        Ok(readback)
+    }
+
+    pub(crate) fn read_texture(
+        &mut self,
+        device: &Device,
+        id: StorageTextureId
+    ) -> Result<Readback, RenderError> {
+        let storage = self.texture(id)?;
+        let size = storage.texture.size();
+        // Rows of texture copies are padded to 256 bytes
+        let unpadded = size.width * storage.bytes_per_pixel;
+        let padded = unpadded.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let (pending, readback) = PendingReadback::new(
+            device,
+            (padded as BufferAddress) * (size.height as BufferAddress),
+            Some((unpadded, padded))
+        );
+
+        self.commands.push(ComputeCommand::ReadTexture(id, pending));
+        Ok(readback)
+    }
+
+    pub(crate) fn has_work(&self) -> bool {
+        !self.commands.is_empty()
+    }
+
+    /// Records the queued dispatches and readback copies into `encoder`.
+    pub(crate) fn encode(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        for command in self.commands.drain(..) {
+            match command {
+                ComputeCommand::Dispatch { pipeline, bind_group, workgroups } => {
+                    let mut compute_pass = encoder.begin_compute_pass(
+                        &(wgpu::ComputePassDescriptor {
+                            label: Some("Compute Pass"),
+                            timestamp_writes: None,
+                        })
+                    );
+                    compute_pass.set_pipeline(&self.pipelines[pipeline.0].pipeline);
+                    if let Some(bind_group) = &bind_group {
+                        compute_pass.set_bind_group(0, bind_group, &[]);
+                    }
+                    compute_pass.dispatch_workgroups(workgroups[0], workgroups[1], workgroups[2]);
+                }
+                ComputeCommand::ReadBuffer(id, pending) => {
+                    encoder.copy_buffer_to_buffer(
+                        &self.buffers[id.0],
+                        0,
+                        &pending.staging,
+                        0,
+                        pending.staging.size()
+                    );
+                    self.submitted.push(pending);
+                }
+                ComputeCommand::ReadTexture(id, pending) => {
+                    let texture = &self.textures[id.0].texture;
+                    encoder.copy_texture_to_buffer(
+                        texture.as_image_copy(),
+                        wgpu::TexelCopyBufferInfo {
+                            buffer: &pending.staging,
+                            layout: wgpu::TexelCopyBufferLayout {
+                                offset: 0,
+                                bytes_per_row: pending.rows.map(|(_, padded)| padded),
+                                rows_per_image: None,
+                            },
+                        },
+                        texture.size()
+                    );
+                    self.submitted.push(pending);
+                }
+            }
+        }
+    }
+
+    /// Starts mapping the staging buffers of readbacks recorded in the submitted encoder.
+    pub(crate) fn after_submit(&mut self) {
+        for pending in self.submitted.drain(..) {
+            let mapped = pending.mapped.clone();
+            pending.staging.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+                if let Ok(mut mapped) = mapped.lock() {
+                    *mapped = Some(result);
+                }
+            });
+            self.mapping.push(pending);
+        }
+    }
+
+    /// Polls the device and hands the data of finished readbacks to their `Readback`. With
+    /// `wait`, blocks until all submitted work has completed.
+    pub(crate) fn poll(&mut self, device: &Device, wait: bool) {
+        if self.mapping.is_empty() {
+            return;
+        }
+        let poll_type = if wait { wgpu::PollType::Wait } else { wgpu::PollType::Poll };
+        if let Err(e) = device.poll(poll_type) {
+            log::warn!("Failed to poll device: {}", e);
+        }
+
+        let mut index = 0;
+        while index < self.mapping.len() {
+            let mapped = self.mapping[index].mapped
+                .lock()
+                .ok()
+                .and_then(|mut mapped| mapped.take());
+            match mapped {
+                Some(mapped) => self.mapping.swap_remove(index).finish(mapped),
+                None => {
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    fn buffer(&self, id: StorageBufferId) -> Result<&Buffer, RenderError> {
+        self.buffers
+            .get(id.0)
+            .ok_or_else(|| RenderError::ResourceError(format!("Unknown {:?}", id)))
+    }
+
+    fn texture(&self, id: StorageTextureId) -> Result<&StorageTexture, RenderError> {
+        self.textures
+            .get(id.0)
+            .ok_or_else(|| RenderError::ResourceError(format!("Unknown {:?}", id)))
+    }
+}
+
+/// Returns the group 0 bindings of the compute entry point of the WGSL `source`, sorted by
+/// binding index. Like the layout wgpu derives, it leaves out bindings the entry point never uses.
+fn reflect_bindings(source: &str) -> Result<Vec<(u32, ShaderBinding)>, RenderError> {
+    let module = naga::front::wgsl
+        ::parse_str(source)
+        .map_err(|error| RenderError::ShaderError(error.emit_to_string(source)))?;
+    let info = naga::valid::Validator
+        ::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+        .validate(&module)
+        .map_err(|error| RenderError::ShaderError(error.emit_to_string(source)))?;
+
+    let entry_point = module.entry_points
+        .iter()
+        .position(|entry_point| entry_point.stage == naga::ShaderStage::Compute)
+        .ok_or_else(|| RenderError::ShaderError("Shader has no compute entry point".to_string()))?;
+    let uses = info.get_entry_point(entry_point);
+
+    let mut bindings = Vec::new();
+    for (handle, global) in module.global_variables.iter() {
+        let Some(binding) = &global.binding else {
+            continue;
+        };
+        if binding.group != 0 || uses[handle].is_empty() {
+            continue;
+        }
+
+        let shader_binding = match module.types[global.ty].inner {
+            naga::TypeInner::Image { dim, arrayed, .. } if
+                dim != naga::ImageDimension::D2 ||
+                arrayed
+            => ShaderBinding::Unsupported("a texture that is not a single 2D layer"),
+            naga::TypeInner::Image { class: naga::ImageClass::Storage { format, .. }, .. } =>
+                ShaderBinding::StorageTexture(format),
+            naga::TypeInner::Image {
+                class: naga::ImageClass::Sampled { kind, multi: false },
+                ..
+            } => ShaderBinding::SampledTexture(kind),
+            naga::TypeInner::Image { .. } =>
+                ShaderBinding::Unsupported("a depth or multisampled texture"),
+            naga::TypeInner::Sampler { .. } => ShaderBinding::Unsupported("a sampler"),
+            naga::TypeInner::BindingArray { .. } => ShaderBinding::Unsupported("a binding array"),
+            naga::TypeInner::AccelerationStructure { .. } =>
+                ShaderBinding::Unsupported("an acceleration structure"),
+            ref inner =>
+                ShaderBinding::Buffer {
+                    min_size: inner.size(module.to_ctx()) as BufferAddress,
+                    uniform: global.space == naga::AddressSpace::Uniform,
+                },
+        };
+        bindings.push((binding.binding, shader_binding));
+    }
+
+    bindings.sort_by_key(|(binding, _)| *binding);
+    Ok(bindings)
+}
+
+/// Checks the resources of a dispatch, bound in order from binding 0, against the bindings of
+/// its shader.
+fn check_bindings(
+    bindings: &[(u32, ShaderBinding)],
+    resources: &[BoundResource],
+    limits: &wgpu::Limits
+) -> Result<(), RenderError> {
+    let error = |message: String| Err(RenderError::ResourceError(message));
+
+    if resources.len() != bindings.len() {
+        return error(
+            format!(
+                "Dispatch binds {} resources but the shader uses {}",
+                resources.len(),
+                bindings.len()
+            )
+        );
+    }
+
+    for (index, ((binding, expected), resource)) in bindings.iter().zip(resources).enumerate() {
+        if *binding != (index as u32) {
+            return error(
+                format!(
+                    "The shader uses binding {} but dispatch bindings fill 0..{} in order",
+                    binding,
+                    bindings.len()
+                )
+            );
+        }
+
+        match (expected, *resource) {
+            (ShaderBinding::Buffer { min_size, uniform }, BoundResource::Buffer(size)) => {
+                let max_size = if *uniform {
+                    limits.max_uniform_buffer_binding_size
+                } else {
+                    limits.max_storage_buffer_binding_size
+                };
+                if size < *min_size {
+                    return error(
+                        format!(
+                            "Binding {} needs at least {} bytes but the buffer has {}",
+                            binding,
+                            min_size,
+                            size
+                        )
+                    );
+                }
+                if size > (max_size as BufferAddress) {
+                    return error(
+                        format!(
+                            "Binding {} is limited to {} bytes but the buffer has {}",
+                            binding,
+                            max_size,
+                            size
+                        )
+                    );
+                }
+            }
+            (ShaderBinding::StorageTexture(format), BoundResource::Texture(texture_format)) => {
+                if storage_format(texture_format) != Some(*format) {
+                    return error(
+                        format!(
+                            "Binding {} is a {:?} storage texture but the texture is {:?}",
+                            binding,
+                            format,
+                            texture_format
+                        )
+                    );
+                }
+            }
+            (ShaderBinding::SampledTexture(kind), BoundResource::Texture(texture_format)) => {
+                let matches = match texture_format.sample_type(None, None) {
+                    Some(wgpu::TextureSampleType::Float { .. }) => *kind == naga::ScalarKind::Float,
+                    Some(wgpu::TextureSampleType::Uint) => *kind == naga::ScalarKind::Uint,
+                    Some(wgpu::TextureSampleType::Sint) => *kind == naga::ScalarKind::Sint,
+                    _ => false,
+                };
+                if !matches {
+                    return error(
+                        format!(
+                            "Binding {} samples {:?} texels but the texture is {:?}",
+                            binding,
+                            kind,
+                            texture_format
+                        )
+                    );
+                }
+            }
+            (ShaderBinding::Unsupported(what), _) => {
+                return error(
+                    format!("Binding {} is {}, which dispatches cannot bind", binding, what)
+                );
+            }
+            (ShaderBinding::Buffer { .. }, BoundResource::Texture(_)) => {
+                return error(format!("Binding {} is a buffer but a texture was bound", binding));
+            }
+            (_, BoundResource::Buffer(_)) => {
+                return error(format!("Binding {} is a texture but a buffer was bound", binding));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the WGSL storage format of `format`, if it has one.
+fn storage_format(format: wgpu::TextureFormat) -> Option<naga::StorageFormat> {
+    use naga::StorageFormat as Storage;
+    use wgpu::TextureFormat as Texture;
+
+    Some(match format {
+        Texture::R8Unorm => Storage::R8Unorm,
+        Texture::R8Snorm => Storage::R8Snorm,
+        Texture::R8Uint => Storage::R8Uint,
+        Texture::R8Sint => Storage::R8Sint,
+        Texture::R16Uint => Storage::R16Uint,
+        Texture::R16Sint => Storage::R16Sint,
+        Texture::R16Float => Storage::R16Float,
+        Texture::R16Unorm => Storage::R16Unorm,
+        Texture::R16Snorm => Storage::R16Snorm,
+        Texture::Rg8Unorm => Storage::Rg8Unorm,
+        Texture::Rg8Snorm => Storage::Rg8Snorm,
+        Texture::Rg8Uint => Storage::Rg8Uint,
+        Texture::Rg8Sint => Storage::Rg8Sint,
+        Texture::R32Uint => Storage::R32Uint,
+        Texture::R32Sint => Storage::R32Sint,
+        Texture::R32Float => Storage::R32Float,
+        Texture::Rg16Uint => Storage::Rg16Uint,
+        Texture::Rg16Sint => Storage::Rg16Sint,
+        Texture::Rg16Float => Storage::Rg16Float,
+        Texture::Rg16Unorm => Storage::Rg16Unorm,
+        Texture::Rg16Snorm => Storage::Rg16Snorm,
+        Texture::Rgba8Unorm => Storage::Rgba8Unorm,
+        Texture::Rgba8Snorm => Storage::Rgba8Snorm,
+        Texture::Rgba8Uint => Storage::Rgba8Uint,
+        Texture::Rgba8Sint => Storage::Rgba8Sint,
+        Texture::Bgra8Unorm => Storage::Bgra8Unorm,
+        Texture::Rgb10a2Uint => Storage::Rgb10a2Uint,
+        Texture::Rgb10a2Unorm => Storage::Rgb10a2Unorm,
+        Texture::Rg11b10Ufloat => Storage::Rg11b10Ufloat,
+        Texture::R64Uint => Storage::R64Uint,
+        Texture::Rg32Uint => Storage::Rg32Uint,
+        Texture::Rg32Sint => Storage::Rg32Sint,
+        Texture::Rg32Float => Storage::Rg32Float,
+        Texture::Rgba16Uint => Storage::Rgba16Uint,
+        Texture::Rgba16Sint => Storage::Rgba16Sint,
+        Texture::Rgba16Float => Storage::Rgba16Float,
+        Texture::Rgba16Unorm => Storage::Rgba16Unorm,
+        Texture::Rgba16Snorm => Storage::Rgba16Snorm,
+        Texture::Rgba32Uint => Storage::Rgba32Uint,
+        Texture::Rgba32Sint => Storage::Rgba32Sint,
+        Texture::Rgba32Float => Storage::Rgba32Float,
+        _ => {
+            return None;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = "
+        struct Params { count: u32, scale: f32 }
+
+        @group(0) @binding(0) var<uniform> params: Params;
+        @group(0) @binding(1) var<storage, read_write> values: array<f32>;
+        @group(0) @binding(2) var output: texture_storage_2d<rgba8unorm, write>;
+        @group(0) @binding(3) var unused: texture_2d<f32>;
+
+        @compute @workgroup_size(64)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            values[id.x] *= params.scale;
+            textureStore(output, vec2<i32>(id.xy), vec4<f32>(1.0));
+        }
+    ";
+
+    fn resources(texture: wgpu::TextureFormat) -> [BoundResource; 3] {
+        [BoundResource::Buffer(16), BoundResource::Buffer(256), BoundResource::Texture(texture)]
+    }
+
+    #[test]
+    fn bindings_are_reflected_like_the_derived_layout() {
+        let bindings = reflect_bindings(SHADER).unwrap();
+        assert_eq!(bindings, [
+            (0, ShaderBinding::Buffer { min_size: 8, uniform: true }),
+            // Runtime-sized arrays need room for one element
+            (1, ShaderBinding::Buffer { min_size: 4, uniform: false }),
+            (2, ShaderBinding::StorageTexture(naga::StorageFormat::Rgba8Unorm)),
+        ]);
+    }
+
+    #[test]
+    fn matching_bindings_are_accepted() {
+        let bindings = reflect_bindings(SHADER).unwrap();
+        let limits = wgpu::Limits::default();
+        let resources = resources(wgpu::TextureFormat::Rgba8Unorm);
+        assert!(check_bindings(&bindings, &resources, &limits).is_ok());
+    }
+
+    #[test]
+    fn mismatched_bindings_are_rejected() {
+        let bindings = reflect_bindings(SHADER).unwrap();
+        let limits = wgpu::Limits::default();
+        let check = |resources: &[BoundResource]| check_bindings(&bindings, resources, &limits);
+
+        let [params, values, output] = resources(wgpu::TextureFormat::Rgba8Unorm);
+        assert!(check(&[params, values]).is_err());
+        assert!(check(&[params, output, values]).is_err());
+        assert!(check(&[BoundResource::Buffer(4), values, output]).is_err());
+        assert!(check(&resources(wgpu::TextureFormat::Rgba32Float)).is_err());
+
+        let too_large = BoundResource::Buffer(limits.max_uniform_buffer_binding_size as u64 + 16);
+        assert!(check(&[too_large, values, output]).is_err());
+    }
+
+    #[test]
+    fn bindings_must_start_at_zero() {
+        let source = "
+            @group(0) @binding(1) var<storage, read_write> values: array<f32>;
+
+            @compute @workgroup_size(1)
+            fn main() { values[0] = 1.0; }
+        ";
+        let bindings = reflect_bindings(source).unwrap();
+        let limits = wgpu::Limits::default();
+        assert!(check_bindings(&bindings, &[BoundResource::Buffer(4)], &limits).is_err());
+    }
+
+    #[test]
+    fn shader_errors_are_reported() {
+        assert!(matches!(reflect_bindings("fn main() {}"), Err(RenderError::ShaderError(_))));
+        assert!(matches!(reflect_bindings("@compute fn main("), Err(RenderError::ShaderError(_))));
+    }
+}
@@ -0,0 +1,355 @@
+//! Draw lists recorded away from the renderer.
+//!
+//! A `DrawList` records the same shape and text calls as the `Renderer`, but into commands it
+//! owns, so it can be built on any thread without access to the renderer. Submitted lists are
+//! replayed into the renderer's queues when the frame is recorded, ordered by their sort key.
+//! Replaying turns the lists into one stream of commands, see `replay_commands`, which is then
+//! applied to the renderer.
+
+use crate::types::*;
+
+pub(crate) enum DrawCommand {
+    Text {
+        text: String,
+        position: (f32, f32),
+        style: TextStyle,
+    },
+    ClippedText {
+        text: String,
+        position: (f32, f32),
+        style: TextStyle,
+        clip: Rect,
+    },
+    TextObject(Text),
+    RichText(RichText),
+    MsdfText(MsdfText),
+    Rectangle {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: [f32; 4],
+    },
+    Circle {
+        center_x: f32,
+        center_y: f32,
+        radius: f32,
+        color: [f32; 4],
+    },
+    RectangleInstances(Vec<ShapeInstance>),
+    CircleInstances(Vec<ShapeInstance>),
+    Occluder(Vec<glam::Vec2>),
+    Mesh {
+        mesh: MeshId,
+        transform: glam::Affine2,
+        tint: [f32; 4],
+    },
+    NormalMap {
+        normal_map: NormalMapId,
+        rect: Rect,
+    },
+    SetMaterial(Option<MaterialId>),
+    SetLayer(i32),
+    SetCastingShadows(bool),
+}
+
+/// Shapes and text recorded for one frame, submitted with `Renderer::submit`.
+///
+/// The methods mirror the renderer's queue methods. A list starts with no material, layer 0 and
+/// shadow casting off, and its state calls only affect the rest of the list.
+///
+/// Lists are queued in ascending `sort_key` order, lists with the same key in the order they were
+/// submitted, after everything queued on the renderer directly. Drawing then follows the same
+/// rules as the renderer's own queue methods: shapes, instanced shapes and meshes are ordered by
+/// layer first and by queue order within a layer, so a list's shapes only cover the overlapping
+/// shapes of lists with lower keys on the same layer, and text is drawn over every shape.
+#[derive(Default)]
+pub struct DrawList {
+    sort_key: u64,
+    commands: Vec<DrawCommand>,
+}
+
+impl DrawList {
+    pub fn new(sort_key: u64) -> Self {
+        Self {
+            sort_key,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn sort_key(&self) -> u64 {
+        self.sort_key
+    }
+
+    pub fn set_sort_key(&mut self, sort_key: u64) {
+        self.sort_key = sort_key;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Removes every recorded command, keeping the allocation and the sort key.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    pub fn queue_text(&mut self, text: &str, position: (f32, f32), size: f32, color: [f32; 4]) {
+        self.queue_styled_text(text, position, &TextStyle::new(size, color))
+    }
+
+    pub fn queue_styled_text(&mut self, text: &str, position: (f32, f32), style: &TextStyle) {
+        self.commands.push(DrawCommand::Text {
+            text: text.to_owned(),
+            position,
+            style: *style,
+        });
+    }
+
+    /// See `Renderer::queue_clipped_text`.
+    pub fn queue_clipped_text(
+        &mut self,
+        text: &str,
+        position: (f32, f32),
+        style: &TextStyle,
+        clip: Rect
+    ) {
+        self.commands.push(DrawCommand::ClippedText {
+            text: text.to_owned(),
+            position,
+            style: *style,
+            clip,
+        });
+    }
+
+    /// See `Renderer::queue_text_object`. Invisible text is not recorded.
+    pub fn queue_text_object(&mut self, text: &Text) {
+        if text.visible && !text.text.is_empty() {
+            self.commands.push(DrawCommand::TextObject(text.clone()));
+        }
+    }
+
+    pub fn queue_rich_text(&mut self, text: &RichText) {
+        self.commands.push(DrawCommand::RichText(text.clone()));
+    }
+
+    pub fn queue_msdf_text(&mut self, text: &MsdfText) {
+        self.commands.push(DrawCommand::MsdfText(text.clone()));
+    }
+
+    pub fn queue_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        self.commands.push(DrawCommand::Rectangle { x, y, width, height, color });
+    }
+
+    pub fn queue_square(&mut self, x: f32, y: f32, size: f32, color: [f32; 4]) {
+        self.queue_rectangle(x, y, size, size, color)
+    }
+
+    pub fn queue_circle(&mut self, center_x: f32, center_y: f32, radius: f32, color: [f32; 4]) {
+        self.commands.push(DrawCommand::Circle { center_x, center_y, radius, color });
+    }
+
+    /// See `Renderer::queue_rectangle_instances`.
+    pub fn queue_rectangle_instances(&mut self, instances: &[ShapeInstance]) {
+        self.commands.push(DrawCommand::RectangleInstances(instances.to_vec()));
+    }
+
+    pub fn queue_circle_instances(&mut self, instances: &[ShapeInstance]) {
+        self.commands.push(DrawCommand::CircleInstances(instances.to_vec()));
+    }
+
+    pub fn queue_occluder(&mut self, polygon: &[glam::Vec2]) {
+        self.commands.push(DrawCommand::Occluder(polygon.to_vec()));
+    }
+
+    /// See `Renderer::draw_mesh`. The mesh must still exist when the list is submitted.
+    pub fn draw_mesh(&mut self, mesh: MeshId, transform: glam::Affine2, tint: [f32; 4]) {
+        self.commands.push(DrawCommand::Mesh { mesh, transform, tint });
+    }
+
+    /// See `Renderer::queue_normal_map`.
+    pub fn queue_normal_map(
+        &mut self,
+        normal_map: NormalMapId,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32
+    ) {
+        self.commands.push(DrawCommand::NormalMap {
+            normal_map,
+            rect: Rect::new(x, y, width, height),
+        });
+    }
+
+    pub fn set_material(&mut self, material: Option<MaterialId>) {
+        self.commands.push(DrawCommand::SetMaterial(material));
+    }
+
+    pub fn set_layer(&mut self, layer: i32) {
+        self.commands.push(DrawCommand::SetLayer(layer));
+    }
+
+    pub fn set_casting_shadows(&mut self, enabled: bool) {
+        self.commands.push(DrawCommand::SetCastingShadows(enabled));
+    }
+}
+
+/// The renderer state that the state calls of a list change.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) struct ListState {
+    pub(crate) material: Option<MaterialId>,
+    pub(crate) layer: i32,
+    pub(crate) casting_shadows: bool,
+}
+
+impl ListState {
+    fn commands(self) -> [DrawCommand; 3] {
+        [
+            DrawCommand::SetMaterial(self.material),
+            DrawCommand::SetLayer(self.layer),
+            DrawCommand::SetCastingShadows(self.casting_shadows),
+        ]
+    }
+}
+
+/// Returns the commands of `lists` in the order they are queued: by ascending sort key, lists
+/// with equal keys in their order in `lists`, each list starting from the default state. The
+/// stream ends by setting `restore`, the renderer's own state.
+pub(crate) fn replay_commands(
+    mut lists: Vec<DrawList>,
+    restore: ListState
+) -> impl Iterator<Item = DrawCommand> {
+    // Stable, so lists with the same key keep their submission order
+    lists.sort_by_key(DrawList::sort_key);
+    lists
+        .into_iter()
+        .flat_map(|list| ListState::default().commands().into_iter().chain(list.commands))
+        .chain(restore.commands())
+}
+
+impl DrawCommand {
+    /// Queues the command on `renderer`.
+    pub(crate) fn apply(self, renderer: &mut crate::Renderer) {
+        match self {
+            DrawCommand::Text { text, position, style } => {
+                renderer.queue_styled_text(&text, position, &style);
+            }
+            DrawCommand::ClippedText { text, position, style, clip } => {
+                renderer.queue_clipped_text(&text, position, &style, clip);
+            }
+            DrawCommand::TextObject(text) => renderer.queue_text_object(&text),
+            DrawCommand::RichText(text) => renderer.queue_rich_text(&text),
+            DrawCommand::MsdfText(text) => renderer.queue_msdf_text(&text),
+            DrawCommand::Rectangle { x, y, width, height, color } => {
+                renderer.queue_rectangle(x, y, width, height, color);
+            }
+            DrawCommand::Circle { center_x, center_y, radius, color } => {
+                renderer.queue_circle(center_x, center_y, radius, color);
+            }
+            DrawCommand::RectangleInstances(instances) => {
+                renderer.queue_rectangle_instances(&instances);
+            }
+            DrawCommand::CircleInstances(instances) => {
+                renderer.queue_circle_instances(&instances);
+            }
+            DrawCommand::Occluder(polygon) => renderer.queue_occluder(&polygon),
+            DrawCommand::Mesh { mesh, transform, tint } => {
+                renderer.draw_mesh(mesh, transform, tint);
+            }
+            DrawCommand::NormalMap { normal_map, rect } => {
+                renderer.queue_normal_map(normal_map, rect.x, rect.y, rect.width, rect.height);
+            }
+            DrawCommand::SetMaterial(material) => renderer.set_material(material),
+            DrawCommand::SetLayer(layer) => renderer.set_layer(layer),
+            DrawCommand::SetCastingShadows(enabled) => renderer.set_casting_shadows(enabled),
+        }
+    }
+}
+
+// Lists are recorded on worker threads, so they must stay `Send`
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<DrawList>();
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The rectangles and state calls of a command stream, by the rectangle's `x`.
+    fn describe(commands: impl Iterator<Item = DrawCommand>) -> Vec<String> {
+        commands
+            .map(|command| match command {
+                DrawCommand::Rectangle { x, .. } => format!("rectangle {}", x),
+                DrawCommand::SetMaterial(material) => {
+                    format!("material {:?}", material.map(|material| material.0))
+                }
+                DrawCommand::SetLayer(layer) => format!("layer {}", layer),
+                DrawCommand::SetCastingShadows(enabled) => format!("shadows {}", enabled),
+                _ => "other".to_owned(),
+            })
+            .collect()
+    }
+
+    fn list(sort_key: u64, xs: &[f32]) -> DrawList {
+        let mut list = DrawList::new(sort_key);
+        for &x in xs {
+            list.queue_rectangle(x, 0.0, 1.0, 1.0, [1.0; 4]);
+        }
+        list
+    }
+
+    const DEFAULT_STATE: [&str; 3] = ["material None", "layer 0", "shadows false"];
+
+    #[test]
+    fn lists_replay_in_sort_key_order() {
+        let lists = vec![list(2, &[0.0]), list(1, &[1.0, 2.0]), list(2, &[3.0]), list(1, &[4.0])];
+        let commands = describe(replay_commands(lists, ListState::default()));
+
+        let rectangles: Vec<_> = commands
+            .iter()
+            .filter(|command| command.starts_with("rectangle"))
+            .map(String::as_str)
+            .collect();
+        // Lists with equal keys keep their submission order
+        assert_eq!(rectangles, [
+            "rectangle 1",
+            "rectangle 2",
+            "rectangle 4",
+            "rectangle 0",
+            "rectangle 3",
+        ]);
+    }
+
+    #[test]
+    fn every_list_starts_from_the_default_state() {
+        let mut first = list(0, &[0.0]);
+        first.set_material(Some(MaterialId(3)));
+        first.set_layer(5);
+        first.set_casting_shadows(true);
+        first.queue_rectangle(1.0, 0.0, 1.0, 1.0, [1.0; 4]);
+        let second = list(1, &[2.0]);
+
+        let restore = ListState {
+            material: Some(MaterialId(7)),
+            layer: -2,
+            casting_shadows: true,
+        };
+        let commands = describe(replay_commands(vec![second, first], restore));
+
+        let mut expected = DEFAULT_STATE.to_vec();
+        expected.extend([
+            "rectangle 0",
+            "material Some(3)",
+            "layer 5",
+            "shadows true",
+            "rectangle 1",
+        ]);
+        expected.extend(DEFAULT_STATE);
+        expected.push("rectangle 2");
+        // The renderer's own state comes back after the last list
+        expected.extend(["material Some(7)", "layer -2", "shadows true"]);
+        assert_eq!(commands, expected);
+    }
+}
@@ -0,0 +1,165 @@
+//! A frame being recorded, returned by `Renderer::begin_frame`.
+
+use crate::draw_list::DrawList;
+use crate::renderer::Renderer;
+use crate::types::*;
+
+pub(crate) type CustomPass<'a> = Box<
+    dyn FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView) + 'a
+>;
+
+/// Holds the surface texture, its view and the command encoder of one frame.
+///
+/// Shapes, text and meshes are queued on the frame with the same methods as on the `Renderer`.
+/// Anything else, such as measuring text or dispatching compute work, goes through the renderer
+/// before `begin_frame`, and whatever was queued on the renderer then is drawn in this frame too.
+/// Nothing is drawn until `end_frame`, which records the queued work and presents the surface
+/// texture. Dropping the frame ends it the same way.
+pub struct Frame<'a> {
+    renderer: &'a mut Renderer,
+    // Both are taken when the frame ends
+    surface_texture: Option<wgpu::SurfaceTexture>,
+    encoder: Option<wgpu::CommandEncoder>,
+    view: wgpu::TextureView,
+    custom_passes: Vec<CustomPass<'a>>,
+}
+
+impl<'a> Frame<'a> {
+    pub(crate) fn new(
+        renderer: &'a mut Renderer,
+        surface_texture: wgpu::SurfaceTexture,
+        encoder: wgpu::CommandEncoder
+    ) -> Self {
+        let view = surface_texture.texture.create_view(&Default::default());
+
+        Self {
+            renderer,
+            surface_texture: Some(surface_texture),
+            encoder: Some(encoder),
+            view,
+            custom_passes: Vec::new(),
+        }
+    }
+
+    /// View of the surface texture that is presented.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Adds a pass of your own, recorded into the frame's encoder after the queued shapes and
+    /// text and before post effects. `record` receives the encoder and the view the scene is
+    /// drawn into, which is an offscreen texture of the surface format while post effects are
+    /// active. Passes run in the order they were added.
+    pub fn add_pass(
+        &mut self,
+        record: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView) + 'a
+    ) {
+        self.custom_passes.push(Box::new(record));
+    }
+
+    pub fn queue_text(&mut self, text: &str, position: (f32, f32), size: f32, color: [f32; 4]) {
+        self.renderer.queue_text(text, position, size, color)
+    }
+
+    pub fn queue_styled_text(&mut self, text: &str, position: (f32, f32), style: &TextStyle) {
+        self.renderer.queue_styled_text(text, position, style)
+    }
+
+    /// See `Renderer::queue_clipped_text`.
+    pub fn queue_clipped_text(
+        &mut self,
+        text: &str,
+        position: (f32, f32),
+        style: &TextStyle,
+        clip: Rect
+    ) {
+        self.renderer.queue_clipped_text(text, position, style, clip)
+    }
+
+    /// See `Renderer::queue_text_object`.
+    pub fn queue_text_object(&mut self, text: &Text) {
+        self.renderer.queue_text_object(text)
+    }
+
+    pub fn queue_rich_text(&mut self, text: &RichText) {
+        self.renderer.queue_rich_text(text)
+    }
+
+    pub fn queue_msdf_text(&mut self, text: &MsdfText) {
+        self.renderer.queue_msdf_text(text)
+    }
+
+    pub fn queue_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        self.renderer.queue_rectangle(x, y, width, height, color)
+    }
+
+    pub fn queue_square(&mut self, x: f32, y: f32, size: f32, color: [f32; 4]) {
+        self.renderer.queue_square(x, y, size, color)
+    }
+
+    pub fn queue_circle(&mut self, center_x: f32, center_y: f32, radius: f32, color: [f32; 4]) {
+        self.renderer.queue_circle(center_x, center_y, radius, color)
+    }
+
+    /// See `Renderer::queue_rectangle_instances`.
+    pub fn queue_rectangle_instances(&mut self, instances: &[ShapeInstance]) {
+        self.renderer.queue_rectangle_instances(instances)
+    }
+
+    pub fn queue_circle_instances(&mut self, instances: &[ShapeInstance]) {
+        self.renderer.queue_circle_instances(instances)
+    }
+
+    pub fn queue_occluder(&mut self, polygon: &[glam::Vec2]) {
+        self.renderer.queue_occluder(polygon)
+    }
+
+    /// See `Renderer::draw_mesh`.
+    pub fn draw_mesh(&mut self, mesh: MeshId, transform: glam::Affine2, tint: [f32; 4]) {
+        self.renderer.draw_mesh(mesh, transform, tint)
+    }
+
+    pub fn set_material(&mut self, material: Option<MaterialId>) {
+        self.renderer.set_material(material)
+    }
+
+    pub fn set_layer(&mut self, layer: i32) {
+        self.renderer.set_layer(layer)
+    }
+
+    pub fn set_casting_shadows(&mut self, enabled: bool) {
+        self.renderer.set_casting_shadows(enabled)
+    }
+
+    /// See `Renderer::submit`.
+    pub fn submit(&mut self, draw_list: DrawList) {
+        self.renderer.submit(draw_list)
+    }
+
+    /// Draws everything queued for the frame and presents it.
+    pub fn end_frame(mut self) {
+        self.finish();
+    }
+
+    fn finish(&mut self) {
+        let (Some(surface_texture), Some(mut encoder)) = (
+            self.surface_texture.take(),
+            self.encoder.take(),
+        ) else {
+            return;
+        };
+
+        let custom_passes = std::mem::take(&mut self.custom_passes);
+        self.renderer.record_frame(&mut encoder, &self.view, custom_passes);
+        self.renderer.submit_frame(encoder, surface_texture);
+    }
+}
+
+impl Drop for Frame<'_> {
+    fn drop(&mut self) {
+        // A frame dropped while unwinding is discarded rather than drawn half-queued
+        if !std::thread::panicking() {
+            self.finish();
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Development-time shader hot reloading.
+//!
+//! The GLSL sources in `res/shaders` are polled for changes, recompiled to WGSL through naga and
+//! the pipelines built from them are recreated. Errors leave the previous pipeline in place.
+//! Sources go through the same preprocessor as build.rs, so a change to an included `.glsl` file
+//! reloads every shader.
+
+use std::collections::{ BTreeSet, HashMap };
+use std::path::{ Path, PathBuf };
+use std::time::SystemTime;
+
+use naga::back::wgsl;
+use naga::front::glsl::{ Frontend, Options };
+use wgpu::{ Device, ShaderModule };
+
+use crate::error::RenderError;
+use crate::init::validated;
+use crate::preprocess;
+
+/// Directory the built-in shaders are compiled from.
+pub(crate) const SHADER_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/res/shaders");
+
+pub(crate) struct ShaderWatcher {
+    directory: PathBuf,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl ShaderWatcher {
+    pub(crate) fn new(directory: impl Into<PathBuf>) -> Self {
+        let mut watcher = Self {
+            directory: directory.into(),
+            modified: HashMap::new(),
+        };
+        watcher.changed_shaders();
+        watcher
+    }
+
+    pub(crate) fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns the names of the shaders, without their stage extension, whose sources or
+    /// includes changed since the last call.
+    pub(crate) fn changed_shaders(&mut self) -> BTreeSet<String> {
+        let mut changed = BTreeSet::new();
+        let mut all = BTreeSet::new();
+        let mut include_changed = false;
+        let Ok(entries) = std::fs::read_dir(&self.directory) else {
+            log::warn!("Cannot read shader directory {}", self.directory.display());
+            return changed;
+        };
+
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let is_include = path.extension().is_some_and(|extension| extension == "glsl");
+            if shader_stage(&path).is_none() && !is_include {
+                continue;
+            }
+            let Ok(modified) = std::fs::metadata(&path).and_then(|m| m.modified()) else {
+                continue;
+            };
+            let modified = self.modified.insert(path.clone(), modified) != Some(modified);
+
+            if is_include {
+                include_changed |= modified;
+            } else if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                all.insert(name.to_string());
+                if modified {
+                    changed.insert(name.to_string());
+                }
+            }
+        }
+
+        if include_changed { all } else { changed }
+    }
+}
+
+fn shader_stage(path: &Path) -> Option<naga::ShaderStage> {
+    match path.extension()?.to_str()? {
+        "vert" => Some(naga::ShaderStage::Vertex),
+        "frag" => Some(naga::ShaderStage::Fragment),
+        "comp" => Some(naga::ShaderStage::Compute),
+        _ => None,
+    }
+}
+
+/// Compiles a GLSL shader, or one of the variants it declares, to WGSL. Includes are resolved
+/// against the shader's own directory. Errors are reported with their file, line and column.
+pub(crate) fn compile_glsl(path: &Path, variant: Option<&str>) -> Result<String, RenderError> {
+    let stage = shader_stage(path).ok_or_else(|| {
+        RenderError::ShaderError(format!("Unsupported shader: {}", path.display()))
+    })?;
+    let include_directory = path.parent().unwrap_or(Path::new("."));
+    let source = preprocess::expand(include_directory, path).map_err(RenderError::ShaderError)?;
+
+    let mut options = Options::from(stage);
+    if let Some(variant) = variant {
+        let variant = source.variants
+            .iter()
+            .find(|it| it.name == variant)
+            .ok_or_else(|| {
+                RenderError::ShaderError(
+                    format!("{} has no variant named `{}`", path.display(), variant)
+                )
+            })?;
+        options.defines.extend(variant.defines.iter().cloned());
+    }
+
+    // Maps a span of the preprocessed code back to the file and line it came from
+    let locate = |span: naga::Span| {
+        let location = span.location(&source.code);
+        let (file, line) = source.origin(location.line_number as usize);
+        format!("{}:{}:{}", file.display(), line, location.line_position)
+    };
+
+    let module = Frontend::default()
+        .parse(&options, &source.code)
+        .map_err(|errors| {
+            let diagnostics: Vec<String> = errors.errors
+                .iter()
+                .map(|error| format!("{}: {}", locate(error.meta), error.kind))
+                .collect();
+            RenderError::ShaderError(diagnostics.join("\n"))
+        })?;
+
+    let info = naga::valid::Validator
+        ::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+        .validate(&module)
+        .map_err(|error| {
+            let mut diagnostic = match error.spans().next() {
+                Some((span, _)) => format!("{}: {}", locate(*span), error.as_inner()),
+                None => format!("{}: {}", path.display(), error.as_inner()),
+            };
+            for (span, label) in error.spans().filter(|(_, label)| !label.is_empty()) {
+                diagnostic.push_str(&format!("\n  {}: {}", locate(*span), label));
+            }
+            RenderError::ShaderError(diagnostic)
+        })?;
+
+    wgsl::write_string(&module, &info, wgsl::WriterFlags::all()).map_err(|e| {
+        RenderError::ShaderError(format!("{}: {}", path.display(), e))
+    })
+}
+
+/// Compiles the `.vert` and `.frag` sources of `name` into shader modules.
+pub(crate) fn load_shader_pair(
+    device: &Device,
+    directory: &Path,
+    name: &str
+) -> Result<(ShaderModule, ShaderModule), RenderError> {
+    let vert_source = compile_glsl(&directory.join(format!("{}.vert", name)), None)?;
+    let frag_source = compile_glsl(&directory.join(format!("{}.frag", name)), None)?;
+
+    validated(device, || {
+        let create = |label: &str, source: String| create_module(device, label, source);
+        (create("vertex shader", vert_source), create("fragment shader", frag_source))
+    })
+}
+
+/// Compiles the shader at `path` into a shader module.
+pub(crate) fn load_shader(device: &Device, path: &Path) -> Result<ShaderModule, RenderError> {
+    let source = compile_glsl(path, None)?;
+    let label = path.file_name().and_then(|name| name.to_str()).unwrap_or("shader");
+    validated(device, || create_module(device, label, source))
+}
+
+/// Compiles one variant of the shader at `path` into a shader module.
+pub(crate) fn load_shader_variant(
+    device: &Device,
+    path: &Path,
+    variant: &str
+) -> Result<ShaderModule, RenderError> {
+    let source = compile_glsl(path, Some(variant))?;
+    validated(device, || create_module(device, variant, source))
+}
+
+fn create_module(device: &Device, label: &str, source: String) -> ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    })
+}
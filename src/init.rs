@@ -1,6 +1,8 @@
 //! Contains all initialization code. Makes renderer::new() much simpler to read.
 
+use std::path::{ Path, PathBuf };
 use std::sync::Arc;
+use std::task::{ Context, Poll, Waker };
 #[cfg(not(target_arch = "wasm32"))]
 use wgpu::Backends;
 use wgpu::{
@@ -15,6 +17,7 @@ use wgpu::{
     BlendComponent,
     BlendState,
     Buffer,
+    BufferAddress,
     BufferBindingType,
     BufferDescriptor,
     BufferUsages,
@@ -28,6 +31,8 @@ use wgpu::{
     Instance,
     InstanceDescriptor,
     MultisampleState,
+    PipelineCache,
+    PipelineCacheDescriptor,
     PipelineLayout,
     PipelineLayoutDescriptor,
     PolygonMode,
@@ -53,7 +58,8 @@ use wgpu::util::{ BufferInitDescriptor, DeviceExt };
 use wgpu_glyph::ab_glyph;
 use winit::{ dpi::PhysicalSize, window::Window };
 
-use crate::types::{ Vertex, U32_SIZE };
+use crate::error::RenderError;
+use crate::types::{ Globals, Vertex, U32_SIZE };
 
 const FONT_BYTES: &[u8] = include_bytes!("../res/fonts/PressStart2P-Regular.ttf");
 
@@ -94,7 +100,10 @@ pub(crate) async fn create_device_and_queue(adapter: &Adapter) -> (Device, Queue
         .request_device(
             &(DeviceDescriptor {
                 label: None,
-                required_features: Features::empty(),
+                // Pipeline caching and timestamps for the profiler are only available on some
+                // backends
+                required_features: adapter.features() &
+                (Features::PIPELINE_CACHE | Features::TIMESTAMP_QUERY),
                 required_limits: adapter.limits(),
                 ..Default::default()
             })
@@ -102,6 +111,52 @@ pub(crate) async fn create_device_and_queue(adapter: &Adapter) -> (Device, Queue
         .unwrap()
 }
 
+/// Creates a pipeline cache seeded from the file for this adapter in `directory`, if the device
+/// supports caching. Returns the cache and the file it should be saved to.
+pub(crate) fn create_pipeline_cache(
+    device: &Device,
+    adapter: &Adapter,
+    directory: &Path
+) -> Option<(PipelineCache, PathBuf)> {
+    if !device.features().contains(Features::PIPELINE_CACHE) {
+        log::info!("Pipeline caching is not supported by this adapter");
+        return None;
+    }
+
+    // Cache data is only valid for the adapter and driver that produced it
+    let path = directory.join(wgpu::util::pipeline_cache_key(&adapter.get_info())?);
+    let data = std::fs::read(&path).ok();
+
+    // SAFETY: the data was written by `save_pipeline_cache` for an adapter with the same cache
+    // key, and invalid data falls back to an empty cache
+    let cache = unsafe {
+        device.create_pipeline_cache(
+            &(PipelineCacheDescriptor {
+                label: Some("Pipeline Cache"),
+                data: data.as_deref(),
+                fallback: true,
+            })
+        )
+    };
+
+    Some((cache, path))
+}
+
+/// Writes the contents of `cache` to `path`, through a temporary file so an interrupted write
+/// never leaves a truncated cache behind.
+pub(crate) fn save_pipeline_cache(cache: &PipelineCache, path: &Path) -> std::io::Result<()> {
+    let Some(data) = cache.get_data() else {
+        return Ok(());
+    };
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, data)?;
+    std::fs::rename(&temp_path, path)
+}
+
 pub(crate) fn create_surface_config(
     surface: &Surface<'_>,
     adapter: &Adapter,
@@ -129,7 +184,7 @@ pub(crate) fn create_surface_config(
 pub(crate) fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
     device.create_bind_group_layout(
         &(BindGroupLayoutDescriptor {
-            label: Some("Screen Size BGL"),
+            label: Some("Globals BGL"),
             entries: &[
                 BindGroupLayoutEntry {
                     binding: 0,
@@ -177,11 +232,17 @@ pub(crate) fn create_shader_modules(device: &Device) -> (ShaderModule, ShaderMod
     (vert_shader, frag_shader)
 }
 
-pub(crate) fn create_screen_size_buffer(device: &Device, size: PhysicalSize<u32>) -> Buffer {
+pub(crate) fn create_globals_buffer(device: &Device, size: PhysicalSize<u32>) -> Buffer {
+    let globals = Globals {
+        screen_size: [size.width as f32, size.height as f32],
+        view: glam::Mat4::IDENTITY.to_cols_array_2d(),
+        ..bytemuck::Zeroable::zeroed()
+    };
+
     device.create_buffer_init(
         &(BufferInitDescriptor {
-            label: Some("Screen Size Buffer"),
-            contents: bytemuck::cast_slice(&[size.width as f32, size.height as f32]),
+            label: Some("Globals Buffer"),
+            contents: bytemuck::bytes_of(&globals),
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         })
     )
@@ -209,19 +270,43 @@ pub(crate) fn create_vertex_and_index_buffers(device: &Device) -> (Buffer, Buffe
     (vertex_buffer, index_buffer)
 }
 
+/// Recreates `buffer` with room for at least `required` bytes if it is too small.
+/// Returns true when the buffer was replaced, so bind groups referencing it must be rebuilt.
+pub(crate) fn grow_buffer(
+    device: &Device,
+    buffer: &mut Buffer,
+    label: Option<&str>,
+    required: BufferAddress
+) -> bool {
+    if buffer.size() >= required {
+        return false;
+    }
+
+    *buffer = device.create_buffer(
+        &(BufferDescriptor {
+            label,
+            size: required.next_power_of_two(),
+            usage: buffer.usage(),
+            mapped_at_creation: false,
+        })
+    );
+
+    true
+}
+
 pub(crate) fn create_bind_group(
     device: &Device,
     bind_group_layout: &BindGroupLayout,
-    screen_size_buffer: &Buffer
+    globals_buffer: &Buffer
 ) -> BindGroup {
     device.create_bind_group(
         &(BindGroupDescriptor {
-            label: Some("Screen Size BG"),
+            label: Some("Globals BG"),
             layout: bind_group_layout,
             entries: &[
                 BindGroupEntry {
                     binding: 0,
-                    resource: screen_size_buffer.as_entire_binding(),
+                    resource: globals_buffer.as_entire_binding(),
                 },
             ],
         })
@@ -234,7 +319,8 @@ pub(crate) fn create_render_pipeline(
     surface_format: TextureFormat,
     vertex_layouts: &[VertexBufferLayout],
     vert_shader: ShaderModule,
-    frag_shader: ShaderModule
+    frag_shader: ShaderModule,
+    cache: Option<&PipelineCache>
 ) -> RenderPipeline {
     device.create_render_pipeline(
         &(RenderPipelineDescriptor {
@@ -277,7 +363,7 @@ pub(crate) fn create_render_pipeline(
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
-            cache: None,
+            cache,
         })
     )
 }
@@ -289,3 +375,25 @@ pub(crate) fn create_glyph_brush(
     let font = ab_glyph::FontArc::try_from_slice(FONT_BYTES).unwrap();
     wgpu_glyph::GlyphBrushBuilder::using_font(font).build(device, surface_format)
 }
+
+/// Runs `f` inside a validation error scope, turning any error it raised into a
+/// `RenderError::ShaderError`.
+///
+/// The scope is checked without waiting on the device. wgpu-core, behind every backend this
+/// crate creates, reports errors by the time the call raising them returns, so the scope is
+/// resolved as soon as it is popped. Only the browser's WebGPU resolves it later, and then the
+/// result cannot be waited for.
+pub(crate) fn validated<T>(device: &Device, f: impl FnOnce() -> T) -> Result<T, RenderError> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = f();
+
+    let mut scope = std::pin::pin!(device.pop_error_scope());
+    match scope.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(Some(error)) => Err(RenderError::ShaderError(error.to_string())),
+        Poll::Ready(None) => Ok(value),
+        Poll::Pending => {
+            log::debug!("Validation errors are not available yet and were not checked");
+            Ok(value)
+        }
+    }
+}
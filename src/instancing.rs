@@ -0,0 +1,295 @@
+//! Instanced rectangles and circles.
+//!
+//! Each shape kind has one unit mesh, centered on the origin, uploaded once. Queued shapes only
+//! add a `ShapeInstance` (center, size, rotation and color) to a per-instance buffer, so a shape
+//! costs one small write instead of a full set of vertices and indices. Each call queueing
+//! instances is a `Primitive` ordered by layer with the other shapes, and every batch of
+//! instances is drawn with a single instanced call.
+
+use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+use wgpu::{
+    BindGroup,
+    BindGroupLayout,
+    Buffer,
+    BufferAddress,
+    BufferDescriptor,
+    BufferUsages,
+    Device,
+    PipelineCache,
+    RenderPipeline,
+    ShaderModule,
+    TextureFormat,
+};
+
+use crate::batching::{ sorted_items, DrawKind, Primitive };
+use crate::init::{ create_pipeline_layout, create_render_pipeline, grow_buffer };
+use crate::profiler::FrameCounters;
+use crate::shader_types::ShapeVertex;
+use crate::types::{ Rect, ShapeInstance };
+
+// Matches the segment count of `Renderer::queue_circle`
+const CIRCLE_SEGMENTS: u32 = 32;
+
+pub(crate) struct ShapeInstancer {
+    pipeline: RenderPipeline,
+    mesh_vertex_buffer: Buffer,
+    mesh_index_buffer: Buffer,
+    rectangle_indices: Range<u32>,
+    circle_indices: Range<u32>,
+    instance_buffer: Buffer,
+    rectangles: Vec<ShapeInstance>,
+    circles: Vec<ShapeInstance>,
+    // Where the circles start in the instance buffer, after the rectangles
+    circle_offset: BufferAddress,
+}
+
+impl ShapeInstancer {
+    pub(crate) fn new(
+        device: &Device,
+        globals_layout: &BindGroupLayout,
+        format: TextureFormat,
+        cache: Option<&PipelineCache>
+    ) -> Self {
+        let (vert_shader, frag_shader) = create_shape_shaders(device);
+        let pipeline = create_shape_pipeline(
+            device,
+            globals_layout,
+            format,
+            vert_shader,
+            frag_shader,
+            cache
+        );
+
+        // A unit quad followed by a unit circle fan
+        let mut vertices = vec![
+            ShapeVertex { position: [-0.5, -0.5] },
+            ShapeVertex { position: [0.5, -0.5] },
+            ShapeVertex { position: [0.5, 0.5] },
+            ShapeVertex { position: [-0.5, 0.5] }
+        ];
+        let mut indices = vec![2, 1, 0, 3, 2, 0];
+        let rectangle_indices = 0..indices.len() as u32;
+
+        let center = vertices.len() as u32;
+        vertices.push(ShapeVertex { position: [0.0, 0.0] });
+        for i in 0..CIRCLE_SEGMENTS {
+            let angle = (2.0 * std::f32::consts::PI * (i as f32)) / (CIRCLE_SEGMENTS as f32);
+            vertices.push(ShapeVertex { position: [0.5 * angle.cos(), 0.5 * angle.sin()] });
+        }
+        for i in 0..CIRCLE_SEGMENTS {
+            let next = if i == CIRCLE_SEGMENTS - 1 { 1 } else { i + 2 };
+            indices.extend_from_slice(&[center + next, center + i + 1, center]);
+        }
+        let circle_indices = rectangle_indices.end..indices.len() as u32;
+
+        let mesh_vertex_buffer = device.create_buffer_init(
+            &(wgpu::util::BufferInitDescriptor {
+                label: Some("Shape Mesh Vertex Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: BufferUsages::VERTEX,
+            })
+        );
+        let mesh_index_buffer = device.create_buffer_init(
+            &(wgpu::util::BufferInitDescriptor {
+                label: Some("Shape Mesh Index Buffer"),
+                contents: bytemuck::cast_slice::<u32, u8>(&indices),
+                usage: BufferUsages::INDEX,
+            })
+        );
+        let instance_buffer = device.create_buffer(
+            &(BufferDescriptor {
+                label: Some("Shape Instance Buffer"),
+                size: ShapeInstance::SIZE * 1024,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+
+        Self {
+            pipeline,
+            mesh_vertex_buffer,
+            mesh_index_buffer,
+            rectangle_indices,
+            circle_indices,
+            instance_buffer,
+            rectangles: Vec::new(),
+            circles: Vec::new(),
+            circle_offset: 0,
+        }
+    }
+
+    /// Rebuilds the pipeline from recompiled shaders, keeping the old one if they are invalid.
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn reload_shaders(
+        &mut self,
+        device: &Device,
+        globals_layout: &BindGroupLayout,
+        format: TextureFormat,
+        vert_shader: ShaderModule,
+        frag_shader: ShaderModule,
+        cache: Option<&PipelineCache>
+    ) -> Result<(), crate::RenderError> {
+        self.pipeline = crate::init::validated(device, || {
+            create_shape_pipeline(device, globals_layout, format, vert_shader, frag_shader, cache)
+        })?;
+        Ok(())
+    }
+
+    /// Queues rectangle instances, returning their range for the primitive drawing them.
+    pub(crate) fn queue_rectangles(&mut self, instances: &[ShapeInstance]) -> Range<u32> {
+        let start = self.rectangles.len() as u32;
+        self.rectangles.extend_from_slice(instances);
+        start..self.rectangles.len() as u32
+    }
+
+    /// Queues circle instances, returning their range for the primitive drawing them.
+    pub(crate) fn queue_circles(&mut self, instances: &[ShapeInstance]) -> Range<u32> {
+        let start = self.circles.len() as u32;
+        self.circles.extend_from_slice(instances);
+        start..self.circles.len() as u32
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rectangles.is_empty() && self.circles.is_empty()
+    }
+
+    /// Uploads the queued instances in the draw order of `order`, rectangles first. Must run
+    /// before `draw`.
+    pub(crate) fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &wgpu::Queue,
+        primitives: &[Primitive],
+        order: &[usize],
+        counters: &mut FrameCounters
+    ) {
+        if self.is_empty() {
+            return;
+        }
+
+        let count = (self.rectangles.len() + self.circles.len()) as BufferAddress;
+        grow_buffer(
+            device,
+            &mut self.instance_buffer,
+            Some("Shape Instance Buffer"),
+            ShapeInstance::SIZE * count
+        );
+        let rectangles = sorted_items(primitives, order, DrawKind::Rectangles, &self.rectangles);
+        let circles = sorted_items(primitives, order, DrawKind::Circles, &self.circles);
+        let rectangles: &[u8] = bytemuck::cast_slice(&rectangles);
+        let circles: &[u8] = bytemuck::cast_slice(&circles);
+        self.circle_offset = rectangles.len() as BufferAddress;
+        queue.write_buffer(&self.instance_buffer, 0, rectangles);
+        queue.write_buffer(&self.instance_buffer, self.circle_offset, circles);
+        counters.upload(rectangles.len() + circles.len());
+    }
+
+    /// Draws a range of the instances of `kind` uploaded by `prepare`, in draw order, into an
+    /// open render pass.
+    pub(crate) fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        globals_bind_group: &BindGroup,
+        kind: DrawKind,
+        instances: Range<u32>,
+        counters: &mut FrameCounters
+    ) {
+        let (indices, offset) = match kind {
+            DrawKind::Rectangles => (self.rectangle_indices.clone(), 0),
+            DrawKind::Circles => (self.circle_indices.clone(), self.circle_offset),
+            DrawKind::Shapes | DrawKind::Meshes => {
+                return;
+            }
+        };
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, globals_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.mesh_vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.mesh_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        // Bound from the first instance of the kind so instance indices start at zero
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(offset..));
+        render_pass.draw_indexed(indices.clone(), 0, instances.clone());
+        counters.draw(indices.len() as u32, instances.len() as u32);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.rectangles.clear();
+        self.circles.clear();
+    }
+}
+
+fn create_shape_shaders(device: &Device) -> (ShaderModule, ShaderModule) {
+    let vert_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("shape vertex shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            std::borrow::Cow::Borrowed(include_str!("../res/shaders/shape.vert.wgsl"))
+        ),
+    });
+    let frag_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("shape fragment shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            std::borrow::Cow::Borrowed(include_str!("../res/shaders/shape.frag.wgsl"))
+        ),
+    });
+
+    (vert_shader, frag_shader)
+}
+
+fn create_shape_pipeline(
+    device: &Device,
+    globals_layout: &BindGroupLayout,
+    format: TextureFormat,
+    vert_shader: ShaderModule,
+    frag_shader: ShaderModule,
+    cache: Option<&PipelineCache>
+) -> RenderPipeline {
+    create_render_pipeline(
+        device,
+        &create_pipeline_layout(device, globals_layout),
+        format,
+        &[ShapeVertex::DESC, ShapeInstance::DESC],
+        vert_shader,
+        frag_shader,
+        cache
+    )
+}
+
+/// Returns the screen bounds of rotated instances, or `None` without instances.
+pub(crate) fn instance_bounds(instances: &[ShapeInstance]) -> Option<Rect> {
+    instances
+        .iter()
+        .map(|instance| {
+            let (sin, cos) = instance.rotation.sin_cos();
+            let [width, height] = instance.size;
+            let half_width = (width * cos).abs() * 0.5 + (height * sin).abs() * 0.5;
+            let half_height = (width * sin).abs() * 0.5 + (height * cos).abs() * 0.5;
+            let [x, y] = instance.center;
+            Rect::new(x - half_width, y - half_height, half_width * 2.0, half_height * 2.0)
+        })
+        .reduce(|bounds, rect| bounds.union(&rect))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(center: [f32; 2], size: [f32; 2], rotation: f32) -> ShapeInstance {
+        ShapeInstance { center, size, rotation, color: [1.0; 4] }
+    }
+
+    #[test]
+    fn bounds_cover_rotated_instances() {
+        let quarter_turn = std::f32::consts::FRAC_PI_2;
+        let bounds = instance_bounds(&[instance([10.0, 10.0], [20.0, 4.0], quarter_turn)]).unwrap();
+        assert!((bounds.width - 4.0).abs() < 1e-4 && (bounds.height - 20.0).abs() < 1e-4);
+        assert!((bounds.x - 8.0).abs() < 1e-4 && (bounds.y - 0.0).abs() < 1e-4);
+
+        let both = instance_bounds(
+            &[instance([0.0, 0.0], [2.0, 2.0], 0.0), instance([10.0, 0.0], [2.0, 2.0], 0.0)]
+        );
+        assert_eq!(both, Some(Rect::new(-1.0, -1.0, 12.0, 2.0)));
+        assert_eq!(instance_bounds(&[]), None);
+    }
+}
@@ -1,8 +1,35 @@
+pub mod draw_list;
 pub mod error;
+pub mod frame;
 pub mod renderer;
+pub mod text_input;
 pub mod types;
+pub(crate) mod batching;
+pub(crate) mod bitmap_font;
+pub(crate) mod compute;
+#[cfg(feature = "hot-reload")]
+pub(crate) mod hot_reload;
+pub(crate) mod instancing;
+pub(crate) mod lighting;
+pub(crate) mod material;
+pub(crate) mod mesh;
+pub(crate) mod msdf;
+pub(crate) mod normal_map;
+pub(crate) mod post;
+// Also used by build.rs, which includes it by path. Built for tests so they run without the
+// feature.
+#[cfg(any(test, feature = "hot-reload"))]
+pub(crate) mod preprocess;
+pub(crate) mod profiler;
+pub(crate) mod render_graph;
+pub(crate) mod shader_types;
+pub(crate) mod text_effects;
 pub(crate) mod init;
 
+pub use compute::Readback;
+pub use draw_list::DrawList;
 pub use error::RenderError;
+pub use frame::Frame;
 pub use renderer::Renderer;
+pub use text_input::TextInput;
 pub use types::*;
@@ -0,0 +1,532 @@
+//! 2D lighting with an ambient color, point lights and shadows.
+//!
+//! When lighting is in use, shapes are drawn into an offscreen target and a full-screen pass
+//! multiplies them by the ambient color plus the contribution of every point light. Text is drawn
+//! afterwards and stays unlit. Shapes are lit as flat surfaces, except where a sprite's normal map
+//! covers them: there each light is also scaled by the cosine of the angle between the normal and
+//! the direction to the light, which sits a quarter of its radius above the screen.
+//!
+//! Shadows come from a 1D shadow map: before the lighting pass, a compute shader casts one ray
+//! per direction around each light against the edges of the queued occluders and stores the
+//! distance to the second nearest hit, where the ray leaves the first occluder. The lighting pass
+//! then compares each fragment's distance to the light against the map, sampling neighbouring
+//! directions for soft shadows. Occluders are therefore lit on the side facing the light and
+//! only shadow what is behind them. Overlapping occluders count as separate ones.
+
+use glam::Vec2;
+use wgpu::{
+    BindGroup,
+    BindGroupLayout,
+    Buffer,
+    BufferAddress,
+    BufferUsages,
+    ComputePipeline,
+    Device,
+    RenderPipeline,
+    ShaderStages,
+};
+
+use crate::init::grow_buffer;
+use crate::post::{ create_fullscreen_shader, create_post_pipeline };
+use crate::profiler::{ FrameCounters, PassTimestamps };
+use crate::shader_types::{ Light, LightParams };
+use crate::types::{ LightId, PointLight, Shadows };
+
+// Must match the length of `lights` in lights.glsl
+const MAX_LIGHTS: usize = 64;
+// Must match `SHADOW_RESOLUTION` in lights.glsl
+const SHADOW_RESOLUTION: u32 = 720;
+// Must match `local_size_x` in shadow_map.comp
+const SHADOW_WORKGROUP_SIZE: u32 = 64;
+// The occluder buffer starts with the segment count, padded to the alignment of a segment
+const OCCLUDER_HEADER_SIZE: BufferAddress = 16;
+const SEGMENT_SIZE: BufferAddress = 16;
+// Circles are outlined with this many edges when casting shadows
+const CIRCLE_OCCLUDER_SEGMENTS: usize = 16;
+
+pub(crate) struct Lighting {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    shadow_pipeline: ComputePipeline,
+    shadow_bind_group_layout: BindGroupLayout,
+    shadow_bind_group: BindGroup,
+    sampler: wgpu::Sampler,
+    params_buffer: Buffer,
+    occluder_buffer: Buffer,
+    shadow_map: Buffer,
+    ambient: [f32; 3],
+    // Removed lights leave a hole so the ids of the others stay valid
+    lights: Vec<Option<PointLight>>,
+    // Occluder edges queued this frame, as start and end points
+    segments: Vec<[f32; 4]>,
+}
+
+impl Lighting {
+    pub(crate) fn new(
+        device: &Device,
+        config: &wgpu::SurfaceConfiguration,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Self {
+        let bind_group_layout = create_light_bind_group_layout(device);
+        let frag_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("light fragment shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                std::borrow::Cow::Borrowed(include_str!("../res/shaders/light.frag.wgsl"))
+            ),
+        });
+        let pipeline = create_post_pipeline(
+            device,
+            &[&bind_group_layout],
+            config.format,
+            &create_fullscreen_shader(device),
+            &frag_shader,
+            cache
+        );
+        let shadow_bind_group_layout = create_shadow_bind_group_layout(device);
+        let shadow_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shadow map compute shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                std::borrow::Cow::Borrowed(include_str!("../res/shaders/shadow_map.comp.wgsl"))
+            ),
+        });
+        let shadow_pipeline = create_shadow_pipeline(
+            device,
+            &shadow_bind_group_layout,
+            &shadow_shader,
+            cache
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+        let params_buffer = device.create_buffer(
+            &(wgpu::BufferDescriptor {
+                label: Some("Light Params Buffer"),
+                size: std::mem::size_of::<LightParams>() as BufferAddress,
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+        let occluder_buffer = device.create_buffer(
+            &(wgpu::BufferDescriptor {
+                label: Some("Occluder Buffer"),
+                size: OCCLUDER_HEADER_SIZE + SEGMENT_SIZE * 256,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+        let shadow_map = device.create_buffer(
+            &(wgpu::BufferDescriptor {
+                label: Some("Shadow Map Buffer"),
+                size: 4 * (SHADOW_RESOLUTION as BufferAddress) * (MAX_LIGHTS as BufferAddress),
+                usage: BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            })
+        );
+
+        let shadow_bind_group = create_shadow_bind_group(
+            device,
+            &shadow_bind_group_layout,
+            &occluder_buffer,
+            &shadow_map,
+            &params_buffer
+        );
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            shadow_pipeline,
+            shadow_bind_group_layout,
+            shadow_bind_group,
+            sampler,
+            params_buffer,
+            occluder_buffer,
+            shadow_map,
+            ambient: [1.0, 1.0, 1.0],
+            lights: Vec::new(),
+            segments: Vec::new(),
+        }
+    }
+
+    /// Recompiles `fullscreen.vert`, `light.frag` and `shadow_map.comp` from `directory` and
+    /// rebuilds the lighting and shadow map pipelines.
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn reload_shaders(
+        &mut self,
+        device: &Device,
+        directory: &std::path::Path,
+        format: wgpu::TextureFormat,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Result<(), crate::error::RenderError> {
+        use crate::hot_reload::load_shader;
+        use crate::init::validated;
+
+        let vert_shader = load_shader(device, &directory.join("fullscreen.vert"))?;
+        let frag_shader = load_shader(device, &directory.join("light.frag"))?;
+        let shadow_shader = load_shader(device, &directory.join("shadow_map.comp"))?;
+
+        let (pipeline, shadow_pipeline) = validated(device, || {
+            (
+                create_post_pipeline(
+                    device,
+                    &[&self.bind_group_layout],
+                    format,
+                    &vert_shader,
+                    &frag_shader,
+                    cache
+                ),
+                create_shadow_pipeline(
+                    device,
+                    &self.shadow_bind_group_layout,
+                    &shadow_shader,
+                    cache
+                ),
+            )
+        })?;
+        self.pipeline = pipeline;
+        self.shadow_pipeline = shadow_pipeline;
+        Ok(())
+    }
+
+    /// Whether anything would change the scene: a light, or an ambient color other than white.
+    pub(crate) fn is_active(&self) -> bool {
+        self.ambient != [1.0, 1.0, 1.0] || self.lights.iter().any(Option::is_some)
+    }
+
+    pub(crate) fn set_ambient(&mut self, color: [f32; 3]) {
+        self.ambient = color;
+    }
+
+    pub(crate) fn ambient(&self) -> [f32; 3] {
+        self.ambient
+    }
+
+    pub(crate) fn add_light(&mut self, light: PointLight) -> LightId {
+        if self.lights.iter().flatten().count() == MAX_LIGHTS {
+            log::warn!("More than {} point lights, the extra ones are not drawn", MAX_LIGHTS);
+        }
+        self.lights.push(Some(light));
+        LightId(self.lights.len() - 1)
+    }
+
+    pub(crate) fn light_mut(&mut self, id: LightId) -> Option<&mut PointLight> {
+        self.lights.get_mut(id.0)?.as_mut()
+    }
+
+    pub(crate) fn remove_light(&mut self, id: LightId) -> Option<PointLight> {
+        self.lights.get_mut(id.0)?.take()
+    }
+
+    /// Queues the outline of a closed polygon as an occluder for this frame.
+    pub(crate) fn queue_occluder(&mut self, polygon: &[Vec2]) {
+        if polygon.len() < 2 {
+            return;
+        }
+        let edges = polygon.iter().zip(polygon.iter().cycle().skip(1));
+        self.segments.extend(edges.map(|(start, end)| [start.x, start.y, end.x, end.y]));
+    }
+
+    /// Queues a rectangle given by its center, size and rotation in radians as an occluder.
+    pub(crate) fn queue_rectangle_occluder(&mut self, center: Vec2, size: Vec2, rotation: f32) {
+        let rotation = Vec2::from_angle(rotation);
+        let half = size * 0.5;
+        let corners = [
+            Vec2::new(-half.x, -half.y),
+            Vec2::new(half.x, -half.y),
+            Vec2::new(half.x, half.y),
+            Vec2::new(-half.x, half.y),
+        ];
+        self.queue_occluder(&corners.map(|corner| center + rotation.rotate(corner)));
+    }
+
+    pub(crate) fn queue_circle_occluder(&mut self, center: Vec2, radius: f32) {
+        let outline: Vec<Vec2> = (0..CIRCLE_OCCLUDER_SEGMENTS)
+            .map(|i| {
+                let turn = (i as f32) / (CIRCLE_OCCLUDER_SEGMENTS as f32);
+                center + Vec2::from_angle(std::f32::consts::TAU * turn) * radius
+            })
+            .collect();
+        self.queue_occluder(&outline);
+    }
+
+    /// Builds the shadow map and lights the shapes drawn into `scene`, with the normal maps drawn
+    /// into `normals`, writing to `output`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn apply(
+        &mut self,
+        device: &Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        scene: &wgpu::TextureView,
+        normals: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+        timestamps: Option<PassTimestamps>,
+        counters: &mut FrameCounters
+    ) {
+        let mut params: LightParams = bytemuck::Zeroable::zeroed();
+        params.ambient = self.ambient;
+
+        let lights: Vec<&PointLight> = self.lights.iter().flatten().collect();
+        for (slot, light) in params.lights.iter_mut().zip(&lights) {
+            *slot = Light {
+                position: light.position.into(),
+                radius: light.radius,
+                intensity: light.intensity,
+                color: light.color,
+                softness: match light.shadows {
+                    Shadows::None => -1.0,
+                    Shadows::Hard => 0.0,
+                    Shadows::Soft { softness } => softness.max(0.0),
+                },
+            };
+        }
+        params.light_count = lights.len().min(MAX_LIGHTS) as u32;
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+        counters.upload(std::mem::size_of::<LightParams>());
+
+        // Rays travel the full light radius when nothing is queued, so lights stay unshadowed
+        let occluders_size = OCCLUDER_HEADER_SIZE + SEGMENT_SIZE * (self.segments.len() as u64);
+        if grow_buffer(device, &mut self.occluder_buffer, Some("Occluder Buffer"), occluders_size) {
+            self.shadow_bind_group = create_shadow_bind_group(
+                device,
+                &self.shadow_bind_group_layout,
+                &self.occluder_buffer,
+                &self.shadow_map,
+                &self.params_buffer
+            );
+        }
+        queue.write_buffer(
+            &self.occluder_buffer,
+            0,
+            bytemuck::bytes_of(&[self.segments.len() as u32, 0, 0, 0])
+        );
+        let segments: &[u8] = bytemuck::cast_slice(&self.segments);
+        queue.write_buffer(&self.occluder_buffer, OCCLUDER_HEADER_SIZE, segments);
+        counters.upload((OCCLUDER_HEADER_SIZE as usize) + segments.len());
+
+        if params.light_count > 0 {
+            let mut compute_pass = encoder.begin_compute_pass(
+                &(wgpu::ComputePassDescriptor {
+                    label: Some("Shadow Map Pass"),
+                    timestamp_writes: None,
+                })
+            );
+            compute_pass.set_pipeline(&self.shadow_pipeline);
+            compute_pass.set_bind_group(0, &self.shadow_bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                SHADOW_RESOLUTION.div_ceil(SHADOW_WORKGROUP_SIZE),
+                params.light_count,
+                1
+            );
+        }
+
+        // The scene and normals come from the frame's render graph, so they are bound per frame
+        let bind_group = create_light_bind_group(
+            device,
+            &self.bind_group_layout,
+            scene,
+            normals,
+            &self.sampler,
+            &self.params_buffer,
+            &self.shadow_map
+        );
+        let mut render_pass = encoder.begin_render_pass(
+            &(wgpu::RenderPassDescriptor {
+                label: Some("Lighting Pass"),
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: output,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    }),
+                ],
+                depth_stencil_attachment: None,
+                timestamp_writes: timestamps
+                    .as_ref()
+                    .map(|timestamps| timestamps.writes(true, true)),
+                occlusion_query_set: None,
+            })
+        );
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        counters.draw(3, 1);
+    }
+
+    /// Drops the occluders queued this frame.
+    pub(crate) fn clear_occluders(&mut self) {
+        self.segments.clear();
+    }
+}
+
+fn storage_entry(
+    binding: u32,
+    visibility: ShaderStages,
+    read_only: bool
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn params_entry(visibility: ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: 2,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<LightParams>() as u64),
+        },
+        count: None,
+    }
+}
+
+fn texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn create_light_bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(
+        &(wgpu::BindGroupLayoutDescriptor {
+            label: Some("Light BGL"),
+            entries: &[
+                texture_entry(0),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                params_entry(ShaderStages::FRAGMENT),
+                storage_entry(3, ShaderStages::FRAGMENT, true),
+                texture_entry(4),
+            ],
+        })
+    )
+}
+
+fn create_light_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    scene_view: &wgpu::TextureView,
+    normals_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    params_buffer: &Buffer,
+    shadow_map: &Buffer
+) -> BindGroup {
+    device.create_bind_group(
+        &(wgpu::BindGroupDescriptor {
+            label: Some("Light BG"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(scene_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: shadow_map.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(normals_view),
+                },
+            ],
+        })
+    )
+}
+
+fn create_shadow_bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(
+        &(wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Map BGL"),
+            entries: &[
+                storage_entry(0, ShaderStages::COMPUTE, true),
+                storage_entry(1, ShaderStages::COMPUTE, false),
+                params_entry(ShaderStages::COMPUTE),
+            ],
+        })
+    )
+}
+
+fn create_shadow_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    occluder_buffer: &Buffer,
+    shadow_map: &Buffer,
+    params_buffer: &Buffer
+) -> BindGroup {
+    device.create_bind_group(
+        &(wgpu::BindGroupDescriptor {
+            label: Some("Shadow Map BG"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: occluder_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: shadow_map.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    )
+}
+
+fn create_shadow_pipeline(
+    device: &Device,
+    layout: &BindGroupLayout,
+    shader: &wgpu::ShaderModule,
+    cache: Option<&wgpu::PipelineCache>
+) -> ComputePipeline {
+    let pipeline_layout = device.create_pipeline_layout(
+        &(wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Map Pipeline Layout"),
+            bind_group_layouts: &[layout],
+            push_constant_ranges: &[],
+        })
+    );
+
+    device.create_compute_pipeline(
+        &(wgpu::ComputePipelineDescriptor {
+            label: Some("Shadow Map Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache,
+        })
+    )
+}
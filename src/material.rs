@@ -0,0 +1,174 @@
+//! User-defined shader materials for shapes.
+//!
+//! A material is a WGSL module drawn with the shape `Vertex` layout and the globals bind group at
+//! group 0. Materials with a uniform block get their own buffer at group 1, binding 0.
+
+use wgpu::{
+    BindGroup,
+    BindGroupLayout,
+    BlendState,
+    Buffer,
+    BufferAddress,
+    BufferUsages,
+    Device,
+    Queue,
+};
+
+use crate::error::RenderError;
+use crate::init::validated;
+use crate::types::{ UniformLayout, Vertex };
+
+pub(crate) struct Material {
+    pub(crate) pipeline: wgpu::RenderPipeline,
+    pub(crate) layout: UniformLayout,
+    pub(crate) uniforms: Option<MaterialUniforms>,
+}
+
+pub(crate) struct MaterialUniforms {
+    pub(crate) buffer: Buffer,
+    pub(crate) bind_group: BindGroup,
+}
+
+impl Material {
+    /// Compiles `source` and builds its pipeline. Shader and pipeline validation errors are
+    /// returned as `RenderError::ShaderError` instead of reaching the device's error handler.
+    pub(crate) fn new(
+        device: &Device,
+        globals_layout: &BindGroupLayout,
+        format: wgpu::TextureFormat,
+        source: &str,
+        layout: UniformLayout,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Result<Self, RenderError> {
+        let (uniform_layout, pipeline) = validated(device, || {
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("material shader"),
+                source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(source)),
+            });
+
+            let uniform_layout = (layout.size > 0).then(|| create_uniform_layout(device));
+
+            let mut bind_group_layouts = vec![globals_layout];
+            bind_group_layouts.extend(uniform_layout.as_ref());
+            let pipeline_layout = device.create_pipeline_layout(
+                &(wgpu::PipelineLayoutDescriptor {
+                    label: Some("Material Pipeline Layout"),
+                    bind_group_layouts: &bind_group_layouts,
+                    push_constant_ranges: &[],
+                })
+            );
+
+            // Entry points are left to wgpu, so the module needs exactly one of each stage
+            let pipeline = device.create_render_pipeline(
+                &(wgpu::RenderPipelineDescriptor {
+                    label: Some("Material Pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: None,
+                        buffers: &[Vertex::DESC],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: None,
+                        targets: &[
+                            Some(wgpu::ColorTargetState {
+                                format,
+                                blend: Some(BlendState::ALPHA_BLENDING),
+                                write_mask: wgpu::ColorWrites::ALL,
+                            }),
+                        ],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache,
+                })
+            );
+            (uniform_layout, pipeline)
+        })?;
+
+        let uniforms = uniform_layout.map(|uniform_layout| {
+            MaterialUniforms::new(device, &uniform_layout, layout.size)
+        });
+
+        Ok(Self { pipeline, layout, uniforms })
+    }
+}
+
+impl MaterialUniforms {
+    pub(crate) fn new(device: &Device, layout: &BindGroupLayout, size: BufferAddress) -> Self {
+        // Uniform buffer sizes must be a multiple of 16
+        let buffer = device.create_buffer(
+            &(wgpu::BufferDescriptor {
+                label: Some("Material Uniform Buffer"),
+                size: size.next_multiple_of(16),
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+        let bind_group = device.create_bind_group(
+            &(wgpu::BindGroupDescriptor {
+                label: Some("Material BG"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        );
+
+        Self { buffer, bind_group }
+    }
+
+    /// Writes a uniform block, which must be exactly `layout.size` bytes.
+    pub(crate) fn write(
+        uniforms: Option<&Self>,
+        queue: &Queue,
+        layout: UniformLayout,
+        bytes: &[u8]
+    ) -> Result<(), RenderError> {
+        match uniforms {
+            Some(uniforms) if (bytes.len() as BufferAddress) == layout.size => {
+                queue.write_buffer(&uniforms.buffer, 0, bytes);
+                Ok(())
+            }
+            _ =>
+                Err(
+                    RenderError::ResourceError(
+                        format!(
+                            "Uniforms are {} bytes but the shader expects {}",
+                            bytes.len(),
+                            layout.size
+                        )
+                    )
+                ),
+        }
+    }
+}
+
+/// Layout of a user uniform block at binding 0 of its group.
+pub(crate) fn create_uniform_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(
+        &(wgpu::BindGroupLayoutDescriptor {
+            label: Some("Material BGL"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        })
+    )
+}
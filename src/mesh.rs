@@ -0,0 +1,304 @@
+//! Retained meshes.
+//!
+//! A mesh's vertices and indices are uploaded once, into buffers of its own, when it is created.
+//! Drawing it only queues a `MeshInstance` (transform and tint), so static geometry such as
+//! backgrounds and level layouts costs one small write per draw instead of being rebuilt and
+//! re-uploaded every frame with the queued shapes. Each draw is a `Primitive` ordered by layer
+//! with the other shapes, and consecutive draws of the same mesh share one instanced call.
+
+use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+use wgpu::{
+    BindGroup,
+    BindGroupLayout,
+    Buffer,
+    BufferAddress,
+    BufferDescriptor,
+    BufferUsages,
+    Device,
+    PipelineCache,
+    RenderPipeline,
+    ShaderModule,
+    TextureFormat,
+};
+
+use crate::batching::{ sorted_items, DrawKind, Primitive };
+use crate::init::{ create_pipeline_layout, create_render_pipeline, grow_buffer };
+use crate::profiler::FrameCounters;
+use crate::shader_types::{ MeshInstance, MeshVertex };
+use crate::types::{ MeshId, Rect, Vertex };
+
+// Meshes are built from the public `Vertex` but drawn with the layout reflected from mesh.vert
+const _: () = assert!(std::mem::size_of::<Vertex>() == std::mem::size_of::<MeshVertex>());
+
+struct Mesh {
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+    // Bounds of the vertices before any transform
+    bounds: Rect,
+}
+
+pub(crate) struct MeshRenderer {
+    pipeline: RenderPipeline,
+    // Removed meshes leave a hole so the ids of the others stay valid
+    meshes: Vec<Option<Mesh>>,
+    instance_buffer: Buffer,
+    instances: Vec<MeshInstance>,
+}
+
+impl MeshRenderer {
+    pub(crate) fn new(
+        device: &Device,
+        globals_layout: &BindGroupLayout,
+        format: TextureFormat,
+        cache: Option<&PipelineCache>
+    ) -> Self {
+        let (vert_shader, frag_shader) = create_mesh_shaders(device);
+        let pipeline = create_mesh_pipeline(
+            device,
+            globals_layout,
+            format,
+            vert_shader,
+            frag_shader,
+            cache
+        );
+        let instance_buffer = device.create_buffer(
+            &(BufferDescriptor {
+                label: Some("Mesh Instance Buffer"),
+                size: MeshInstance::SIZE * 64,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+
+        Self {
+            pipeline,
+            meshes: Vec::new(),
+            instance_buffer,
+            instances: Vec::new(),
+        }
+    }
+
+    /// Rebuilds the pipeline from recompiled shaders, keeping the old one if they are invalid.
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn reload_shaders(
+        &mut self,
+        device: &Device,
+        globals_layout: &BindGroupLayout,
+        format: TextureFormat,
+        vert_shader: ShaderModule,
+        frag_shader: ShaderModule,
+        cache: Option<&PipelineCache>
+    ) -> Result<(), crate::RenderError> {
+        self.pipeline = crate::init::validated(device, || {
+            create_mesh_pipeline(device, globals_layout, format, vert_shader, frag_shader, cache)
+        })?;
+        Ok(())
+    }
+
+    pub(crate) fn create_mesh(
+        &mut self,
+        device: &Device,
+        vertices: &[Vertex],
+        indices: &[u32]
+    ) -> MeshId {
+        let vertex_buffer = device.create_buffer_init(
+            &(wgpu::util::BufferInitDescriptor {
+                label: Some("Mesh Vertex Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: BufferUsages::VERTEX,
+            })
+        );
+        let index_buffer = device.create_buffer_init(
+            &(wgpu::util::BufferInitDescriptor {
+                label: Some("Mesh Index Buffer"),
+                contents: bytemuck::cast_slice::<u32, u8>(indices),
+                usage: BufferUsages::INDEX,
+            })
+        );
+
+        self.meshes.push(
+            Some(Mesh {
+                vertex_buffer,
+                index_buffer,
+                index_count: indices.len() as u32,
+                bounds: vertex_bounds(vertices),
+            })
+        );
+        MeshId(self.meshes.len() - 1)
+    }
+
+    /// Frees the mesh's buffers. Returns whether it existed.
+    pub(crate) fn remove_mesh(&mut self, mesh: MeshId) -> bool {
+        self.meshes.get_mut(mesh.0).and_then(Option::take).is_some()
+    }
+
+    /// Queues an instance of the mesh. Returns its index in the queued instances and the screen
+    /// bounds of the transformed mesh, or `None` if the mesh does not exist.
+    pub(crate) fn queue_draw(
+        &mut self,
+        mesh: MeshId,
+        transform: glam::Affine2,
+        tint: [f32; 4]
+    ) -> Option<(Range<u32>, Rect)> {
+        let Some(Some(queued)) = self.meshes.get(mesh.0) else {
+            log::warn!("Drawing {:?}, which does not exist", mesh);
+            return None;
+        };
+        let bounds = transformed_bounds(queued.bounds, transform);
+
+        let index = self.instances.len() as u32;
+        self.instances.push(MeshInstance {
+            axis_x: transform.matrix2.x_axis.into(),
+            axis_y: transform.matrix2.y_axis.into(),
+            translation: transform.translation.into(),
+            tint,
+        });
+        Some((index..index + 1, bounds))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Uploads the queued instances in the draw order of `primitives`, which `order` comes from
+    /// `build_batches`. Must run before `draw`.
+    pub(crate) fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &wgpu::Queue,
+        primitives: &[Primitive],
+        order: &[usize],
+        counters: &mut FrameCounters
+    ) {
+        if self.is_empty() {
+            return;
+        }
+
+        grow_buffer(
+            device,
+            &mut self.instance_buffer,
+            Some("Mesh Instance Buffer"),
+            MeshInstance::SIZE * (self.instances.len() as BufferAddress)
+        );
+        let instances = sorted_items(primitives, order, DrawKind::Meshes, &self.instances);
+        let instances: &[u8] = bytemuck::cast_slice(&instances);
+        queue.write_buffer(&self.instance_buffer, 0, instances);
+        counters.upload(instances.len());
+    }
+
+    /// Draws a range of the instances uploaded by `prepare`, in draw order, of `mesh` into an
+    /// open render pass.
+    pub(crate) fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        globals_bind_group: &BindGroup,
+        mesh: MeshId,
+        instances: Range<u32>,
+        counters: &mut FrameCounters
+    ) {
+        // Removed after it was queued
+        let Some(Some(mesh)) = self.meshes.get(mesh.0) else {
+            return;
+        };
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, globals_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..mesh.index_count, 0, instances.clone());
+        counters.draw(mesh.index_count, instances.len() as u32);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.instances.clear();
+    }
+}
+
+fn vertex_bounds(vertices: &[Vertex]) -> Rect {
+    let Some(first) = vertices.first() else {
+        return Rect::default();
+    };
+    let start = glam::Vec2::from(first.position);
+    let (min, max) = vertices
+        .iter()
+        .map(|vertex| glam::Vec2::from(vertex.position))
+        .fold((start, start), |(min, max), position| (min.min(position), max.max(position)));
+    Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+}
+
+/// Bounds of `bounds` moved by `transform`, which may rotate, scale or skew it.
+fn transformed_bounds(bounds: Rect, transform: glam::Affine2) -> Rect {
+    let corners = [
+        glam::vec2(bounds.x, bounds.y),
+        glam::vec2(bounds.right(), bounds.y),
+        glam::vec2(bounds.x, bounds.bottom()),
+        glam::vec2(bounds.right(), bounds.bottom()),
+    ].map(|corner| transform.transform_point2(corner));
+    let min = corners.iter().copied().reduce(glam::Vec2::min).unwrap();
+    let max = corners.iter().copied().reduce(glam::Vec2::max).unwrap();
+    Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+}
+
+fn create_mesh_shaders(device: &Device) -> (ShaderModule, ShaderModule) {
+    let vert_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("mesh vertex shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            std::borrow::Cow::Borrowed(include_str!("../res/shaders/mesh.vert.wgsl"))
+        ),
+    });
+    let frag_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("mesh fragment shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            std::borrow::Cow::Borrowed(include_str!("../res/shaders/mesh.frag.wgsl"))
+        ),
+    });
+
+    (vert_shader, frag_shader)
+}
+
+fn create_mesh_pipeline(
+    device: &Device,
+    globals_layout: &BindGroupLayout,
+    format: TextureFormat,
+    vert_shader: ShaderModule,
+    frag_shader: ShaderModule,
+    cache: Option<&PipelineCache>
+) -> RenderPipeline {
+    create_render_pipeline(
+        device,
+        &create_pipeline_layout(device, globals_layout),
+        format,
+        &[MeshVertex::DESC, MeshInstance::DESC],
+        vert_shader,
+        frag_shader,
+        cache
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_follow_the_transform() {
+        let vertices = [
+            Vertex::with_color(0.0, 0.0, [1.0; 4]),
+            Vertex::with_color(4.0, -2.0, [1.0; 4]),
+            Vertex::with_color(2.0, 6.0, [1.0; 4]),
+        ];
+        let bounds = vertex_bounds(&vertices);
+        assert_eq!(bounds, Rect::new(0.0, -2.0, 4.0, 8.0));
+
+        let quarter_turn = glam::Affine2::from_angle_translation(
+            std::f32::consts::FRAC_PI_2,
+            glam::vec2(10.0, 0.0)
+        );
+        let moved = transformed_bounds(bounds, quarter_turn);
+        assert!((moved.x - 4.0).abs() < 1e-4 && (moved.y - 0.0).abs() < 1e-4);
+        assert!((moved.width - 8.0).abs() < 1e-4 && (moved.height - 4.0).abs() < 1e-4);
+    }
+}
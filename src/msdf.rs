@@ -0,0 +1,1040 @@
+//! Multi-channel signed distance field (MSDF) text.
+//!
+//! Glyph outlines from the loaded fonts are turned into distance fields on the CPU and packed
+//! into a shared atlas. Because the atlas stores distances rather than coverage, the same glyph
+//! can be drawn at any size, rotation or camera zoom without being rasterized again.
+//!
+//! Glyphs can be generated up front with `preload`. Otherwise they are generated the first time
+//! they are drawn, at most `GLYPHS_PER_FRAME` per frame so a new string never stalls a frame;
+//! glyphs over the budget are left out until a later frame has generated them.
+
+use std::collections::HashMap;
+
+use glam::{ Affine2, Vec2 };
+use wgpu::{
+    BindGroup,
+    BindGroupLayout,
+    BlendState,
+    Buffer,
+    BufferAddress,
+    BufferDescriptor,
+    BufferUsages,
+    Device,
+    ShaderModule,
+};
+use wgpu_glyph::ab_glyph::{ Font, FontArc, GlyphId, OutlineCurve, PxScale, ScaleFont };
+
+use crate::init::grow_buffer;
+use crate::profiler::FrameCounters;
+use crate::shader_types::MsdfVertex;
+use crate::types::{ HorizontalAlign, MsdfText, VerticalAlign, U32_SIZE };
+
+const ATLAS_SIZE: u32 = 1024;
+// Pixel scale glyphs are generated at
+const GLYPH_SCALE: f32 = 48.0;
+// Width of the encoded distance band in atlas pixels. Must match msdf.frag
+const DISTANCE_RANGE: f32 = 6.0;
+const PADDING: u32 = (DISTANCE_RANGE as u32) / 2 + 1;
+// Curves are flattened into this many line segments
+const QUAD_SEGMENTS: usize = 8;
+const CUBIC_SEGMENTS: usize = 12;
+// Tangents turning by more than this (sine of roughly 8 degrees) mark a corner
+const CORNER_CROSS_THRESHOLD: f32 = 0.141;
+// Glyphs generated while preparing a frame, the rest wait for later frames
+const GLYPHS_PER_FRAME: usize = 8;
+
+const RED: u8 = 0b001;
+const GREEN: u8 = 0b010;
+const BLUE: u8 = 0b100;
+const CYAN: u8 = GREEN | BLUE;
+const MAGENTA: u8 = RED | BLUE;
+const YELLOW: u8 = RED | GREEN;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+/// A glyph's place in the atlas.
+#[derive(Copy, Clone)]
+struct AtlasGlyph {
+    // Normalized texture coordinates
+    uv_min: Vec2,
+    uv_max: Vec2,
+    // Quad relative to the glyph origin, in pixels at `GLYPH_SCALE`
+    plane_min: Vec2,
+    plane_max: Vec2,
+}
+
+struct QueuedGlyph {
+    font: usize,
+    id: GlyphId,
+    // Maps the glyph plane at `GLYPH_SCALE` to its final position
+    transform: Affine2,
+    color: [f32; 4],
+    world: bool,
+}
+
+pub(crate) struct MsdfRenderer {
+    pipeline: wgpu::RenderPipeline,
+    atlas: wgpu::Texture,
+    // Kept to rebuild the pipeline when shaders are reloaded
+    #[cfg(feature = "hot-reload")]
+    atlas_layout: BindGroupLayout,
+    bind_group: BindGroup,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    // `None` for glyphs without an outline, like spaces
+    glyphs: HashMap<(usize, GlyphId), Option<AtlasGlyph>>,
+    packer: ShelfPacker,
+    queued: Vec<QueuedGlyph>,
+    index_count: u32,
+}
+
+impl MsdfRenderer {
+    pub(crate) fn new(
+        device: &Device,
+        globals_layout: &BindGroupLayout,
+        format: wgpu::TextureFormat,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Self {
+        let atlas = device.create_texture(
+            &(wgpu::TextureDescriptor {
+                label: Some("MSDF Atlas"),
+                size: wgpu::Extent3d {
+                    width: ATLAS_SIZE,
+                    height: ATLAS_SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                // Distances are linear data, so no sRGB conversion
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            })
+        );
+        let atlas_view = atlas.create_view(&Default::default());
+        let sampler = device.create_sampler(
+            &(wgpu::SamplerDescriptor {
+                label: Some("MSDF Sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            })
+        );
+
+        let atlas_layout = create_texture_bind_group_layout(device, "MSDF Atlas BGL");
+        let bind_group = device.create_bind_group(
+            &(wgpu::BindGroupDescriptor {
+                label: Some("MSDF Atlas BG"),
+                layout: &atlas_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&atlas_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            })
+        );
+
+        let (vert_shader, frag_shader) = create_msdf_shaders(device);
+        let pipeline = create_msdf_pipeline(
+            device,
+            globals_layout,
+            &atlas_layout,
+            format,
+            &vert_shader,
+            &frag_shader,
+            cache
+        );
+
+        let vertex_buffer = device.create_buffer(
+            &(BufferDescriptor {
+                label: Some("MSDF Vertex Buffer"),
+                size: MsdfVertex::SIZE * 256,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+        let index_buffer = device.create_buffer(
+            &(BufferDescriptor {
+                label: Some("MSDF Index Buffer"),
+                size: U32_SIZE * 384,
+                usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+
+        Self {
+            pipeline,
+            atlas,
+            #[cfg(feature = "hot-reload")]
+            atlas_layout,
+            bind_group,
+            vertex_buffer,
+            index_buffer,
+            glyphs: HashMap::new(),
+            packer: ShelfPacker::new(ATLAS_SIZE, ATLAS_SIZE),
+            queued: Vec::new(),
+            index_count: 0,
+        }
+    }
+
+    /// Rebuilds the pipeline from recompiled shaders, keeping the old one if they are invalid.
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn reload_shaders(
+        &mut self,
+        device: &Device,
+        globals_layout: &BindGroupLayout,
+        format: wgpu::TextureFormat,
+        vert_shader: &ShaderModule,
+        frag_shader: &ShaderModule,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Result<(), crate::RenderError> {
+        self.pipeline = crate::init::validated(device, || {
+            create_msdf_pipeline(
+                device,
+                globals_layout,
+                &self.atlas_layout,
+                format,
+                vert_shader,
+                frag_shader,
+                cache
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Lays out `text` with the metrics of its font and queues one quad per glyph.
+    pub(crate) fn queue(&mut self, fonts: &[FontArc], text: &MsdfText) {
+        let Some(font) = fonts.get(text.font.0) else {
+            log::warn!("MSDF text uses unknown font {:?}", text.font);
+            return;
+        };
+        let scaled = font.as_scaled(PxScale::from(text.size));
+        let line_height = scaled.ascent() - scaled.descent() + scaled.line_gap();
+        let glyph_scale = text.size / GLYPH_SCALE;
+
+        // Pen positions of every glyph, relative to the top-left of the block
+        let mut lines: Vec<(f32, Vec<(GlyphId, f32)>)> = Vec::new();
+        for line in text.text.split('\n') {
+            let mut pen = 0.0;
+            let mut previous = None;
+            let mut glyphs = Vec::new();
+            for c in line.chars() {
+                let id = font.glyph_id(c);
+                if let Some(previous) = previous {
+                    pen += scaled.kern(previous, id);
+                }
+                glyphs.push((id, pen));
+                pen += scaled.h_advance(id);
+                previous = Some(id);
+            }
+            lines.push((pen, glyphs));
+        }
+
+        let block_height = line_height * (lines.len() as f32);
+        let block_y = match text.v_align {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Middle => -block_height * 0.5,
+            VerticalAlign::Bottom => -block_height,
+        };
+        let anchor = Affine2::from_angle_translation(text.rotation, text.position);
+
+        for (line_index, (line_width, glyphs)) in lines.into_iter().enumerate() {
+            let line_x = match text.h_align {
+                HorizontalAlign::Left => 0.0,
+                HorizontalAlign::Center => -line_width * 0.5,
+                HorizontalAlign::Right => -line_width,
+            };
+            let baseline = block_y + line_height * (line_index as f32) + scaled.ascent();
+
+            for (id, pen) in glyphs {
+                let origin = Vec2::new(line_x + pen, baseline);
+                self.queued.push(QueuedGlyph {
+                    font: text.font.0,
+                    id,
+                    transform: anchor *
+                    Affine2::from_scale_angle_translation(Vec2::splat(glyph_scale), 0.0, origin),
+                    color: text.color,
+                    world: text.world_space,
+                });
+            }
+        }
+    }
+
+    /// Generates every glyph of `text` in `font` that is not in the atlas yet, regardless of the
+    /// per-frame budget.
+    pub(crate) fn preload(
+        &mut self,
+        queue: &wgpu::Queue,
+        fonts: &[FontArc],
+        font: usize,
+        text: &str,
+        counters: &mut FrameCounters
+    ) {
+        let Some(font_arc) = fonts.get(font) else {
+            log::warn!("Preloading MSDF glyphs of unknown font {}", font);
+            return;
+        };
+        let keys: Vec<_> = text
+            .chars()
+            .filter(|c| !c.is_control())
+            .map(|c| (font, font_arc.glyph_id(c)))
+            .collect();
+        if self.generate_glyphs(queue, fonts, &keys, usize::MAX, counters) {
+            log::warn!("MSDF atlas is full, not every glyph was preloaded");
+        }
+    }
+
+    /// Generates missing glyphs, up to the per-frame budget, and uploads this frame's geometry.
+    pub(crate) fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &wgpu::Queue,
+        fonts: &[FontArc],
+        counters: &mut FrameCounters
+    ) {
+        self.index_count = 0;
+        if self.queued.is_empty() {
+            return;
+        }
+
+        // If the atlas fills up, start over once with only the glyphs this frame needs
+        let keys: Vec<_> = self.queued
+            .iter()
+            .map(|queued| (queued.font, queued.id))
+            .collect();
+        if self.generate_glyphs(queue, fonts, &keys, GLYPHS_PER_FRAME, counters) {
+            self.glyphs.clear();
+            self.packer = ShelfPacker::new(ATLAS_SIZE, ATLAS_SIZE);
+            if self.generate_glyphs(queue, fonts, &keys, GLYPHS_PER_FRAME, counters) {
+                log::warn!("MSDF atlas is full, some glyphs will not be drawn");
+            }
+        }
+
+        let mut vertices = Vec::with_capacity(self.queued.len() * 4);
+        let mut indices = Vec::with_capacity(self.queued.len() * 6);
+        for queued in self.queued.drain(..) {
+            let Some(Some(glyph)) = self.glyphs.get(&(queued.font, queued.id)) else {
+                continue;
+            };
+
+            let vertex_offset = vertices.len() as u32;
+            let world = if queued.world { 1.0 } else { 0.0 };
+            let corners = [
+                (glyph.plane_min, glyph.uv_min),
+                (Vec2::new(glyph.plane_max.x, glyph.plane_min.y), Vec2::new(glyph.uv_max.x, glyph.uv_min.y)),
+                (glyph.plane_max, glyph.uv_max),
+                (Vec2::new(glyph.plane_min.x, glyph.plane_max.y), Vec2::new(glyph.uv_min.x, glyph.uv_max.y)),
+            ];
+            for (plane, uv) in corners {
+                vertices.push(MsdfVertex {
+                    position: queued.transform.transform_point2(plane).to_array(),
+                    uv: uv.to_array(),
+                    color: queued.color,
+                    world,
+                });
+            }
+            indices.extend_from_slice(
+                &[
+                    vertex_offset,
+                    vertex_offset + 1,
+                    vertex_offset + 2,
+                    vertex_offset,
+                    vertex_offset + 2,
+                    vertex_offset + 3,
+                ]
+            );
+        }
+
+        if indices.is_empty() {
+            return;
+        }
+
+        grow_buffer(
+            device,
+            &mut self.vertex_buffer,
+            Some("MSDF Vertex Buffer"),
+            MsdfVertex::SIZE * (vertices.len() as BufferAddress)
+        );
+        grow_buffer(
+            device,
+            &mut self.index_buffer,
+            Some("MSDF Index Buffer"),
+            U32_SIZE * (indices.len() as BufferAddress)
+        );
+        let vertex_bytes: &[u8] = bytemuck::cast_slice(&vertices);
+        let index_bytes: &[u8] = bytemuck::cast_slice(&indices);
+        queue.write_buffer(&self.vertex_buffer, 0, vertex_bytes);
+        queue.write_buffer(&self.index_buffer, 0, index_bytes);
+        counters.upload(vertex_bytes.len() + index_bytes.len());
+        self.index_count = indices.len() as u32;
+    }
+
+    pub(crate) fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        globals_bind_group: &BindGroup,
+        counters: &mut FrameCounters
+    ) {
+        if self.index_count == 0 {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(
+            &(wgpu::RenderPassDescriptor {
+                label: Some("MSDF Text Render Pass"),
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    }),
+                ],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            })
+        );
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, globals_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+        counters.draw(self.index_count, 1);
+    }
+
+    /// Generates the glyphs of `keys` missing from the atlas, at most `limit` of them. Returns
+    /// whether the atlas ran out of room.
+    fn generate_glyphs(
+        &mut self,
+        queue: &wgpu::Queue,
+        fonts: &[FontArc],
+        keys: &[(usize, GlyphId)],
+        limit: usize,
+        counters: &mut FrameCounters
+    ) -> bool {
+        let mut generated = 0;
+        for &key in keys {
+            if generated == limit {
+                break;
+            }
+            if self.glyphs.contains_key(&key) {
+                continue;
+            }
+            let Some(font) = fonts.get(key.0) else {
+                continue;
+            };
+            match self.insert_glyph(queue, font, key, counters) {
+                Some(glyph) => {
+                    self.glyphs.insert(key, glyph);
+                    generated += 1;
+                }
+                None => {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Generates and uploads a glyph. Returns `None` when the atlas has no room left.
+    fn insert_glyph(
+        &mut self,
+        queue: &wgpu::Queue,
+        font: &FontArc,
+        key: (usize, GlyphId),
+        counters: &mut FrameCounters
+    ) -> Option<Option<AtlasGlyph>> {
+        let Some(bitmap) = generate_msdf(font, key.1) else {
+            return Some(None);
+        };
+        let (x, y) = self.packer.allocate(bitmap.width, bitmap.height)?;
+
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.atlas,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &bitmap.pixels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(bitmap.width * 4),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: bitmap.width,
+                height: bitmap.height,
+                depth_or_array_layers: 1,
+            }
+        );
+        counters.upload(bitmap.pixels.len());
+
+        let atlas_size = ATLAS_SIZE as f32;
+        Some(
+            Some(AtlasGlyph {
+                uv_min: Vec2::new(x as f32, y as f32) / atlas_size,
+                uv_max: Vec2::new((x + bitmap.width) as f32, (y + bitmap.height) as f32) /
+                atlas_size,
+                plane_min: bitmap.plane_min,
+                plane_max: bitmap.plane_min +
+                Vec2::new(bitmap.width as f32, bitmap.height as f32),
+            })
+        )
+    }
+}
+
+/// Packs rectangles left to right into rows of increasing height.
+struct ShelfPacker {
+    width: u32,
+    height: u32,
+    cursor_x: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+}
+
+impl ShelfPacker {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            cursor_x: 0,
+            shelf_y: 0,
+            shelf_height: 0,
+        }
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width > self.width {
+            return None;
+        }
+        // A failed allocation leaves the current shelf open for smaller glyphs
+        let new_shelf = self.cursor_x + width > self.width;
+        let (x, y) = if new_shelf {
+            (0, self.shelf_y + self.shelf_height)
+        } else {
+            (self.cursor_x, self.shelf_y)
+        };
+        if y + height > self.height {
+            return None;
+        }
+        if new_shelf {
+            self.shelf_y = y;
+            self.shelf_height = 0;
+        }
+
+        let position = (x, y);
+        self.cursor_x = x;
+        // Leave a gap so linear filtering never bleeds between glyphs
+        self.cursor_x += width + 1;
+        self.shelf_height = self.shelf_height.max(height + 1);
+        Some(position)
+    }
+}
+
+struct GlyphBitmap {
+    width: u32,
+    height: u32,
+    // RGBA8, the alpha channel is unused
+    pixels: Vec<u8>,
+    // Top-left of the bitmap relative to the glyph origin, in pixels at `GLYPH_SCALE`
+    plane_min: Vec2,
+}
+
+/// A straight piece of a flattened outline.
+#[derive(Copy, Clone)]
+struct Edge {
+    a: Vec2,
+    b: Vec2,
+    color: u8,
+    // Only the ends of the original curve get pseudo-distance extension, interior joints of a
+    // flattened curve are smooth
+    extend_start: bool,
+    extend_end: bool,
+}
+
+/// A curve of the outline, flattened into edges.
+struct Curve {
+    edges: Vec<Edge>,
+    start_tangent: Vec2,
+    end_tangent: Vec2,
+}
+
+fn generate_msdf(font: &FontArc, id: GlyphId) -> Option<GlyphBitmap> {
+    let outline = font.outline(id)?;
+    let scale = font.as_scaled(PxScale::from(GLYPH_SCALE)).h_scale_factor();
+
+    // Font units are y-up, the atlas is y-down
+    let to_px = |p: wgpu_glyph::ab_glyph::Point| Vec2::new(p.x * scale, -p.y * scale);
+    let mut contours = split_contours(&outline.curves, to_px);
+    if contours.is_empty() {
+        return None;
+    }
+    for contour in &mut contours {
+        color_edges(contour);
+    }
+    let edges: Vec<Edge> = contours
+        .iter()
+        .flatten()
+        .flat_map(|curve| curve.edges.iter().copied())
+        .collect();
+
+    // Filled regions lie on the same side of every edge. Outer contours dominate the total
+    // signed area, which tells which side that is.
+    let area: f32 = edges
+        .iter()
+        .map(|e| e.a.perp_dot(e.b))
+        .sum();
+    let inside_sign = if area > 0.0 { 1.0 } else { -1.0 };
+
+    let min = to_px(outline.bounds.min).min(to_px(outline.bounds.max));
+    let max = to_px(outline.bounds.min).max(to_px(outline.bounds.max));
+    let plane_min = min.floor() - Vec2::splat(PADDING as f32);
+    let width = ((max.x.ceil() - min.x.floor()) as u32) + PADDING * 2;
+    let height = ((max.y.ceil() - min.y.floor()) as u32) + PADDING * 2;
+
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let p = plane_min + Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let mut channels = [0.0; 3];
+            for (i, channel) in [RED, GREEN, BLUE].into_iter().enumerate() {
+                channels[i] = channel_distance(&edges, p, channel) * inside_sign;
+            }
+
+            // Where the channels disagree with the true distance about being inside, fall back to
+            // a plain SDF for this texel
+            let true_distance = true_signed_distance(&edges, p);
+            let median = median(channels[0], channels[1], channels[2]);
+            if median.signum() != true_distance.signum() && true_distance.abs() > 0.5 {
+                channels = [true_distance; 3];
+            }
+
+            for distance in channels {
+                let encoded = (distance / DISTANCE_RANGE + 0.5).clamp(0.0, 1.0);
+                pixels.push((encoded * 255.0).round() as u8);
+            }
+            pixels.push(255);
+        }
+    }
+
+    Some(GlyphBitmap {
+        width,
+        height,
+        pixels,
+        plane_min,
+    })
+}
+
+/// Splits consecutive curves into closed contours and flattens them.
+fn split_contours(
+    curves: &[OutlineCurve],
+    to_px: impl Fn(wgpu_glyph::ab_glyph::Point) -> Vec2
+) -> Vec<Vec<Curve>> {
+    let mut contours: Vec<Vec<Curve>> = Vec::new();
+    let mut last_end: Option<Vec2> = None;
+
+    for curve in curves {
+        let points: Vec<Vec2> = match *curve {
+            OutlineCurve::Line(p0, p1) => vec![to_px(p0), to_px(p1)],
+            OutlineCurve::Quad(p0, p1, p2) => vec![to_px(p0), to_px(p1), to_px(p2)],
+            OutlineCurve::Cubic(p0, p1, p2, p3) =>
+                vec![to_px(p0), to_px(p1), to_px(p2), to_px(p3)],
+        };
+        let start = points[0];
+        let end = points[points.len() - 1];
+
+        if last_end.is_none_or(|last| last.distance_squared(start) > 1e-6) {
+            contours.push(Vec::new());
+        }
+        last_end = Some(end);
+
+        let flattened: Vec<Vec2> = match points.len() {
+            2 => points.clone(),
+            3 =>
+                (0..=QUAD_SEGMENTS)
+                    .map(|i| {
+                        let t = (i as f32) / (QUAD_SEGMENTS as f32);
+                        points[0].lerp(points[1], t).lerp(points[1].lerp(points[2], t), t)
+                    })
+                    .collect(),
+            _ =>
+                (0..=CUBIC_SEGMENTS)
+                    .map(|i| {
+                        let t = (i as f32) / (CUBIC_SEGMENTS as f32);
+                        let a = points[0].lerp(points[1], t);
+                        let b = points[1].lerp(points[2], t);
+                        let c = points[2].lerp(points[3], t);
+                        a.lerp(b, t).lerp(b.lerp(c, t), t)
+                    })
+                    .collect(),
+        };
+
+        let mut edges: Vec<Edge> = flattened
+            .windows(2)
+            .filter(|w| w[0].distance_squared(w[1]) > 1e-8)
+            .map(|w| Edge {
+                a: w[0],
+                b: w[1],
+                color: WHITE,
+                extend_start: false,
+                extend_end: false,
+            })
+            .collect();
+        let (Some(first), Some(last)) = (edges.first().copied(), edges.last().copied()) else {
+            continue;
+        };
+        edges[0].extend_start = true;
+        let last_index = edges.len() - 1;
+        edges[last_index].extend_end = true;
+
+        // Control points may coincide with the end points, so fall back to the flattened edges
+        let start_tangent = points
+            .iter()
+            .skip(1)
+            .map(|p| *p - start)
+            .find(|d| d.length_squared() > 1e-8)
+            .unwrap_or(first.b - first.a)
+            .normalize();
+        let end_tangent = points
+            .iter()
+            .rev()
+            .skip(1)
+            .map(|p| end - *p)
+            .find(|d| d.length_squared() > 1e-8)
+            .unwrap_or(last.b - last.a)
+            .normalize();
+
+        contours.last_mut().unwrap().push(Curve {
+            edges,
+            start_tangent,
+            end_tangent,
+        });
+    }
+
+    contours.retain(|contour| !contour.is_empty());
+    contours
+}
+
+/// Assigns channel colors so that the two sides of every sharp corner differ in at least two
+/// channels, following the simple edge coloring strategy of msdfgen.
+fn color_edges(contour: &mut [Curve]) {
+    let curve_count = contour.len();
+    // Edge index (across the whole contour) at which each corner starts
+    let mut corners = Vec::new();
+    let mut edge_index = 0;
+    for i in 0..curve_count {
+        let previous = &contour[(i + curve_count - 1) % curve_count];
+        let a = previous.end_tangent;
+        let b = contour[i].start_tangent;
+        if a.dot(b) <= 0.0 || a.perp_dot(b).abs() > CORNER_CROSS_THRESHOLD {
+            corners.push(edge_index);
+        }
+        edge_index += contour[i].edges.len();
+    }
+
+    let mut edges: Vec<&mut Edge> = contour
+        .iter_mut()
+        .flat_map(|curve| curve.edges.iter_mut())
+        .collect();
+    let edge_count = edges.len();
+
+    match corners.len() {
+        // Smooth contour, every channel agrees
+        0 => {}
+        // Teardrop: split the contour into three color regions around the single corner
+        1 if edge_count >= 3 => {
+            let colors = [MAGENTA, WHITE, YELLOW];
+            for i in 0..edge_count {
+                let third = (((i as f32) * 3.0) / (edge_count as f32)) as usize;
+                edges[(corners[0] + i) % edge_count].color = colors[third.min(2)];
+            }
+        }
+        1 => {}
+        _ => {
+            let colors = [CYAN, MAGENTA, YELLOW];
+            let mut color_index = 0;
+            let mut corner = 0;
+            for i in 0..edge_count {
+                let index = (corners[0] + i) % edge_count;
+                if corner + 1 < corners.len() && corners[corner + 1] == index {
+                    corner += 1;
+                    color_index = (color_index + 1) % 3;
+                    // The last run borders the first one, so it must differ from both neighbours
+                    if corner == corners.len() - 1 && color_index == 0 {
+                        color_index = 1;
+                    }
+                }
+                edges[index].color = colors[color_index];
+            }
+        }
+    }
+}
+
+/// Signed pseudo-distance from `p` to the closest edge carrying `channel`.
+fn channel_distance(edges: &[Edge], p: Vec2, channel: u8) -> f32 {
+    let mut closest: Option<(&Edge, f32, f32)> = None;
+    for edge in edges.iter().filter(|e| e.color & channel != 0) {
+        let (distance, orthogonality) = edge_distance(edge, p);
+        let closer = match closest {
+            None => true,
+            Some((_, best, best_orthogonality)) =>
+                distance < best - 1e-4 ||
+                    ((distance - best).abs() <= 1e-4 && orthogonality > best_orthogonality),
+        };
+        if closer {
+            closest = Some((edge, distance, orthogonality));
+        }
+    }
+
+    match closest {
+        Some((edge, _, _)) => pseudo_distance(edge, p),
+        None => -DISTANCE_RANGE,
+    }
+}
+
+/// Unsigned distance to the segment, and how perpendicular the approach is.
+fn edge_distance(edge: &Edge, p: Vec2) -> (f32, f32) {
+    let ab = edge.b - edge.a;
+    let t = ((p - edge.a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0);
+    let to_point = p - (edge.a + ab * t);
+    let distance = to_point.length();
+    let orthogonality = if distance > 0.0 {
+        ab.normalize().perp_dot(to_point / distance).abs()
+    } else {
+        1.0
+    };
+    (distance, orthogonality)
+}
+
+/// Distance to the segment, measured perpendicular to its line past the ends of a curve, and
+/// positive on the segment's left side.
+fn pseudo_distance(edge: &Edge, p: Vec2) -> f32 {
+    let ab = edge.b - edge.a;
+    let direction = ab.normalize();
+    let t = (p - edge.a).dot(ab) / ab.length_squared();
+
+    if (t < 0.0 && edge.extend_start) || (t > 1.0 && edge.extend_end) {
+        return direction.perp_dot(p - edge.a);
+    }
+
+    let to_point = p - (edge.a + ab * t.clamp(0.0, 1.0));
+    let side = direction.perp_dot(to_point).signum();
+    to_point.length() * side
+}
+
+/// Plain signed distance, positive inside the glyph under the non-zero fill rule.
+fn true_signed_distance(edges: &[Edge], p: Vec2) -> f32 {
+    let mut distance = f32::INFINITY;
+    let mut winding = 0;
+    for edge in edges {
+        distance = distance.min(edge_distance(edge, p).0);
+
+        if edge.a.y <= p.y {
+            if edge.b.y > p.y && (edge.b - edge.a).perp_dot(p - edge.a) > 0.0 {
+                winding += 1;
+            }
+        } else if edge.b.y <= p.y && (edge.b - edge.a).perp_dot(p - edge.a) < 0.0 {
+            winding -= 1;
+        }
+    }
+
+    if winding != 0 { distance } else { -distance }
+}
+
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
+
+fn create_texture_bind_group_layout(device: &Device, label: &str) -> BindGroupLayout {
+    device.create_bind_group_layout(
+        &(wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    )
+}
+
+fn create_msdf_shaders(device: &Device) -> (ShaderModule, ShaderModule) {
+    let vert_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("msdf vertex shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            std::borrow::Cow::Borrowed(include_str!("../res/shaders/msdf.vert.wgsl"))
+        ),
+    });
+    let frag_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("msdf fragment shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            std::borrow::Cow::Borrowed(include_str!("../res/shaders/msdf.frag.wgsl"))
+        ),
+    });
+
+    (vert_shader, frag_shader)
+}
+
+fn create_msdf_pipeline(
+    device: &Device,
+    globals_layout: &BindGroupLayout,
+    atlas_layout: &BindGroupLayout,
+    format: wgpu::TextureFormat,
+    vert_shader: &ShaderModule,
+    frag_shader: &ShaderModule,
+    cache: Option<&wgpu::PipelineCache>
+) -> wgpu::RenderPipeline {
+
+    let pipeline_layout = device.create_pipeline_layout(
+        &(wgpu::PipelineLayoutDescriptor {
+            label: Some("MSDF Pipeline Layout"),
+            bind_group_layouts: &[globals_layout, atlas_layout],
+            push_constant_ranges: &[],
+        })
+    );
+
+    device.create_render_pipeline(
+        &(wgpu::RenderPipelineDescriptor {
+            label: Some("MSDF Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: vert_shader,
+                entry_point: Some("main"),
+                buffers: &[MsdfVertex::DESC],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: frag_shader,
+                entry_point: Some("main"),
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache,
+        })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu_glyph::ab_glyph::point;
+
+    use super::*;
+
+    const FONT: &[u8] = include_bytes!("../res/fonts/PressStart2P-Regular.ttf");
+
+    fn square_contour(size: f32) -> Vec<Curve> {
+        let corners = [(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)];
+        let curves: Vec<OutlineCurve> = (0..4)
+            .map(|i| {
+                let (a, b) = (corners[i], corners[(i + 1) % 4]);
+                OutlineCurve::Line(point(a.0, a.1), point(b.0, b.1))
+            })
+            .collect();
+        let mut contours = split_contours(&curves, |p| Vec2::new(p.x, p.y));
+        assert_eq!(contours.len(), 1);
+        contours.remove(0)
+    }
+
+    /// Asserts that the edges on both sides of every corner differ in at least two channels.
+    fn assert_corners_colored(contour: &[Curve]) {
+        let count = contour.len();
+        for i in 0..count {
+            let previous = contour[(i + count - 1) % count].edges.last().unwrap().color;
+            let next = contour[i].edges[0].color;
+            let a = contour[(i + count - 1) % count].end_tangent;
+            let b = contour[i].start_tangent;
+            let corner = a.dot(b) <= 0.0 || a.perp_dot(b).abs() > CORNER_CROSS_THRESHOLD;
+            if corner {
+                assert!((previous & next).count_ones() <= 1, "corner {} shares channels", i);
+            }
+            assert!(previous.count_ones() >= 2 && next.count_ones() >= 2);
+        }
+    }
+
+    #[test]
+    fn shelf_packer_fills_rows_then_fails() {
+        let mut packer = ShelfPacker::new(32, 20);
+
+        assert_eq!(packer.allocate(10, 8), Some((0, 0)));
+        assert_eq!(packer.allocate(10, 5), Some((11, 0)));
+        // 22 + 11 overflows the row, so a new shelf starts under the tallest glyph plus the gap
+        assert_eq!(packer.allocate(11, 4), Some((0, 9)));
+        assert_eq!(packer.allocate(40, 1), None);
+        assert_eq!(packer.allocate(32, 15), None);
+        assert_eq!(packer.allocate(20, 10), Some((12, 9)));
+    }
+
+    #[test]
+    fn square_corners_get_distinct_colors() {
+        let mut contour = square_contour(10.0);
+        color_edges(&mut contour);
+        assert_corners_colored(&contour);
+    }
+
+    #[test]
+    fn font_glyph_corners_get_distinct_colors() {
+        let font = FontArc::try_from_slice(FONT).unwrap();
+        let outline = font.outline(font.glyph_id('L')).unwrap();
+        let contours = split_contours(&outline.curves, |p| Vec2::new(p.x, -p.y));
+
+        assert!(!contours.is_empty());
+        for mut contour in contours {
+            color_edges(&mut contour);
+            assert_corners_colored(&contour);
+        }
+    }
+
+    #[test]
+    fn generated_glyph_is_inside_at_its_stem() {
+        let font = FontArc::try_from_slice(FONT).unwrap();
+        assert!(generate_msdf(&font, font.glyph_id(' ')).is_none());
+
+        let bitmap = generate_msdf(&font, font.glyph_id('I')).unwrap();
+        let texel = |x: u32, y: u32| {
+            let index = ((y * bitmap.width + x) * 4) as usize;
+            let [r, g, b] = [0, 1, 2].map(|channel| bitmap.pixels[index + channel] as f32);
+            median(r, g, b) / 255.0
+        };
+
+        assert_eq!(bitmap.pixels.len(), (bitmap.width * bitmap.height * 4) as usize);
+        // Encoded distances above one half are inside the glyph
+        assert!(texel(bitmap.width / 2, bitmap.height / 2) > 0.5);
+        assert!(texel(0, 0) < 0.5);
+    }
+}
@@ -0,0 +1,497 @@
+//! Normal maps for lit sprites.
+//!
+//! A normal map is a tangent-space normal texture, stored the usual way: red, green and blue
+//! hold the x, y and z of the normal mapped from -1..1 to 0..1, with green pointing up, and alpha
+//! is its coverage. Normal maps queued for a frame are stretched over their sprite's rectangle
+//! into a normal target that the lighting pass samples. The target starts out with no coverage,
+//! so shapes without a normal map stay lit as flat surfaces.
+//!
+//! Normal maps are only ordered by layer among themselves: a shape on a higher layer drawn over a
+//! normal-mapped sprite is lit with the sprite's normals unless it is given a normal map too.
+
+use std::ops::Range;
+
+use wgpu::{
+    BindGroup,
+    BindGroupLayout,
+    BlendComponent,
+    BlendState,
+    Buffer,
+    BufferAddress,
+    BufferDescriptor,
+    BufferUsages,
+    Device,
+    Sampler,
+    ShaderModule,
+    TextureFormat,
+};
+
+use crate::error::RenderError;
+use crate::init::grow_buffer;
+use crate::profiler::FrameCounters;
+use crate::shader_types::NormalMapVertex;
+use crate::types::{ NormalMapId, Rect, U32_SIZE };
+
+/// Format of the normal target. Normals are data, so they are never sRGB encoded.
+pub(crate) const NORMAL_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+// A flat normal with no coverage
+const CLEAR_NORMAL: wgpu::Color = wgpu::Color { r: 0.5, g: 0.5, b: 1.0, a: 0.0 };
+
+struct QueuedNormalMap {
+    layer: i32,
+    normal_map: usize,
+    rect: Rect,
+}
+
+/// A run of quads drawn with the same normal map.
+struct NormalMapDraw {
+    normal_map: usize,
+    indices: Range<u32>,
+}
+
+pub(crate) struct NormalMapRenderer {
+    pipeline: wgpu::RenderPipeline,
+    texture_layout: BindGroupLayout,
+    sampler: Sampler,
+    // Indexed by `NormalMapId`
+    normal_maps: Vec<BindGroup>,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    queued: Vec<QueuedNormalMap>,
+    draws: Vec<NormalMapDraw>,
+}
+
+impl NormalMapRenderer {
+    pub(crate) fn new(
+        device: &Device,
+        globals_layout: &BindGroupLayout,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Self {
+        let texture_layout = create_texture_bind_group_layout(device);
+        let (vert_shader, frag_shader) = create_normal_map_shaders(device);
+        let pipeline = create_normal_map_pipeline(
+            device,
+            globals_layout,
+            &texture_layout,
+            &vert_shader,
+            &frag_shader,
+            cache
+        );
+        let sampler = device.create_sampler(
+            &(wgpu::SamplerDescriptor {
+                label: Some("Normal Map Sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            })
+        );
+
+        let vertex_buffer = device.create_buffer(
+            &(BufferDescriptor {
+                label: Some("Normal Map Vertex Buffer"),
+                size: NormalMapVertex::SIZE * 64,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+        let index_buffer = device.create_buffer(
+            &(BufferDescriptor {
+                label: Some("Normal Map Index Buffer"),
+                size: U32_SIZE * 96,
+                usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+
+        Self {
+            pipeline,
+            texture_layout,
+            sampler,
+            normal_maps: Vec::new(),
+            vertex_buffer,
+            index_buffer,
+            queued: Vec::new(),
+            draws: Vec::new(),
+        }
+    }
+
+    /// Rebuilds the pipeline from recompiled shaders, keeping the old one if they are invalid.
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn reload_shaders(
+        &mut self,
+        device: &Device,
+        globals_layout: &BindGroupLayout,
+        vert_shader: &ShaderModule,
+        frag_shader: &ShaderModule,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Result<(), RenderError> {
+        self.pipeline = crate::init::validated(device, || {
+            create_normal_map_pipeline(
+                device,
+                globals_layout,
+                &self.texture_layout,
+                vert_shader,
+                frag_shader,
+                cache
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Uploads a normal map image, see the module docs for its layout.
+    pub(crate) fn load(
+        &mut self,
+        device: &Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8]
+    ) -> Result<NormalMapId, RenderError> {
+        let pixels = image
+            ::load_from_memory(bytes)
+            .map_err(|e| RenderError::ResourceError(format!("Invalid normal map: {}", e)))?
+            .to_rgba8();
+        let size = wgpu::Extent3d {
+            width: pixels.width(),
+            height: pixels.height(),
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(
+            &(wgpu::TextureDescriptor {
+                label: Some("Normal Map"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: NORMAL_FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            })
+        );
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &pixels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(pixels.width() * 4),
+                rows_per_image: None,
+            },
+            size
+        );
+
+        let view = texture.create_view(&Default::default());
+        self.normal_maps.push(
+            device.create_bind_group(
+                &(wgpu::BindGroupDescriptor {
+                    label: Some("Normal Map BG"),
+                    layout: &self.texture_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&self.sampler),
+                        },
+                    ],
+                })
+            )
+        );
+        Ok(NormalMapId(self.normal_maps.len() - 1))
+    }
+
+    /// Queues a normal map stretched over `rect` on `layer` for this frame.
+    pub(crate) fn queue(&mut self, normal_map: NormalMapId, layer: i32, rect: Rect) {
+        if normal_map.0 >= self.normal_maps.len() {
+            log::warn!("Drawing {:?}, which does not exist", normal_map);
+            return;
+        }
+        self.queued.push(QueuedNormalMap { layer, normal_map: normal_map.0, rect });
+    }
+
+    /// Uploads this frame's quads in layer order. Must run before `draw`.
+    pub(crate) fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &wgpu::Queue,
+        counters: &mut FrameCounters
+    ) {
+        self.draws.clear();
+        if self.queued.is_empty() {
+            return;
+        }
+
+        let (vertices, indices, draws) = build_quads(&mut self.queued);
+        self.draws = draws;
+
+        grow_buffer(
+            device,
+            &mut self.vertex_buffer,
+            Some("Normal Map Vertex Buffer"),
+            NormalMapVertex::SIZE * (vertices.len() as BufferAddress)
+        );
+        grow_buffer(
+            device,
+            &mut self.index_buffer,
+            Some("Normal Map Index Buffer"),
+            U32_SIZE * (indices.len() as BufferAddress)
+        );
+        let vertex_bytes: &[u8] = bytemuck::cast_slice(&vertices);
+        let index_bytes: &[u8] = bytemuck::cast_slice(&indices);
+        queue.write_buffer(&self.vertex_buffer, 0, vertex_bytes);
+        queue.write_buffer(&self.index_buffer, 0, index_bytes);
+        counters.upload(vertex_bytes.len() + index_bytes.len());
+    }
+
+    /// Clears `view`, a texture of `NORMAL_FORMAT`, to flat and draws the uploaded normal maps
+    /// into it.
+    pub(crate) fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        globals_bind_group: &BindGroup,
+        counters: &mut FrameCounters
+    ) {
+        let mut render_pass = encoder.begin_render_pass(
+            &(wgpu::RenderPassDescriptor {
+                label: Some("Normal Map Render Pass"),
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(CLEAR_NORMAL),
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    }),
+                ],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            })
+        );
+        if self.draws.is_empty() {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, globals_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+
+        for draw in &self.draws {
+            render_pass.set_bind_group(1, &self.normal_maps[draw.normal_map], &[]);
+            render_pass.draw_indexed(draw.indices.clone(), 0, 0..1);
+            counters.draw(draw.indices.len() as u32, 1);
+        }
+    }
+
+    /// Drops the normal maps queued this frame, for frames without lighting.
+    pub(crate) fn clear(&mut self) {
+        self.queued.clear();
+    }
+}
+
+/// Builds the quads of `queued` in layer order, draining it. Returns their vertices and indices,
+/// and the runs of indices drawn with each normal map.
+fn build_quads(
+    queued: &mut Vec<QueuedNormalMap>
+) -> (Vec<NormalMapVertex>, Vec<u32>, Vec<NormalMapDraw>) {
+    // Stable, so normal maps on the same layer keep their queue order
+    queued.sort_by_key(|quad| quad.layer);
+
+    let mut vertices = Vec::with_capacity(queued.len() * 4);
+    let mut indices = Vec::with_capacity(queued.len() * 6);
+    let mut draws: Vec<NormalMapDraw> = Vec::new();
+    for quad in queued.drain(..) {
+        let vertex_offset = vertices.len() as u32;
+        let index_offset = indices.len() as u32;
+        let rect = quad.rect;
+        let vertex = |x: f32, y: f32, u: f32, v: f32| NormalMapVertex {
+            position: [x, y],
+            uv: [u, v],
+        };
+        vertices.extend_from_slice(
+            &[
+                vertex(rect.x, rect.y, 0.0, 0.0),
+                vertex(rect.right(), rect.y, 1.0, 0.0),
+                vertex(rect.right(), rect.bottom(), 1.0, 1.0),
+                vertex(rect.x, rect.bottom(), 0.0, 1.0),
+            ]
+        );
+        indices.extend_from_slice(
+            &[
+                vertex_offset,
+                vertex_offset + 1,
+                vertex_offset + 2,
+                vertex_offset,
+                vertex_offset + 2,
+                vertex_offset + 3,
+            ]
+        );
+
+        match draws.last_mut() {
+            Some(draw) if draw.normal_map == quad.normal_map => {
+                draw.indices.end += 6;
+            }
+            _ =>
+                draws.push(NormalMapDraw {
+                    normal_map: quad.normal_map,
+                    indices: index_offset..index_offset + 6,
+                }),
+        }
+    }
+
+    (vertices, indices, draws)
+}
+
+fn create_texture_bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(
+        &(wgpu::BindGroupLayoutDescriptor {
+            label: Some("Normal Map BGL"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    )
+}
+
+fn create_normal_map_shaders(device: &Device) -> (ShaderModule, ShaderModule) {
+    let vert_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("normal map vertex shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            std::borrow::Cow::Borrowed(include_str!("../res/shaders/normal_map.vert.wgsl"))
+        ),
+    });
+    let frag_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("normal map fragment shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            std::borrow::Cow::Borrowed(include_str!("../res/shaders/normal_map.frag.wgsl"))
+        ),
+    });
+
+    (vert_shader, frag_shader)
+}
+
+fn create_normal_map_pipeline(
+    device: &Device,
+    globals_layout: &BindGroupLayout,
+    texture_layout: &BindGroupLayout,
+    vert_shader: &ShaderModule,
+    frag_shader: &ShaderModule,
+    cache: Option<&wgpu::PipelineCache>
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(
+        &(wgpu::PipelineLayoutDescriptor {
+            label: Some("Normal Map Pipeline Layout"),
+            bind_group_layouts: &[globals_layout, texture_layout],
+            push_constant_ranges: &[],
+        })
+    );
+
+    device.create_render_pipeline(
+        &(wgpu::RenderPipelineDescriptor {
+            label: Some("Normal Map Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: vert_shader,
+                entry_point: Some("main"),
+                buffers: &[NormalMapVertex::DESC],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: frag_shader,
+                entry_point: Some("main"),
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format: NORMAL_FORMAT,
+                        // Normals blend by coverage, which accumulates like alpha
+                        blend: Some(BlendState {
+                            color: BlendState::ALPHA_BLENDING.color,
+                            alpha: BlendComponent::OVER,
+                        }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache,
+        })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad(layer: i32, normal_map: usize, x: f32) -> QueuedNormalMap {
+        QueuedNormalMap { layer, normal_map, rect: Rect::new(x, 0.0, 10.0, 20.0) }
+    }
+
+    #[test]
+    fn quads_are_drawn_in_layer_order() {
+        let mut queued = vec![
+            quad(1, 0, 0.0),
+            quad(0, 1, 10.0),
+            quad(1, 0, 20.0),
+            quad(0, 0, 30.0)
+        ];
+        let (vertices, indices, draws) = build_quads(&mut queued);
+
+        assert!(queued.is_empty());
+        let lefts: Vec<f32> = vertices
+            .chunks(4)
+            .map(|quad| quad[0].position[0])
+            .collect();
+        assert_eq!(lefts, [10.0, 30.0, 0.0, 20.0]);
+        assert_eq!(&indices[6..12], [4, 5, 6, 4, 6, 7]);
+        // The last three quads share a normal map and one draw
+        let draws: Vec<_> = draws
+            .iter()
+            .map(|draw| (draw.normal_map, draw.indices.clone()))
+            .collect();
+        assert_eq!(draws, [(1, 0..6), (0, 6..24)]);
+    }
+
+    #[test]
+    fn quads_span_the_whole_normal_map() {
+        let (vertices, _, _) = build_quads(&mut vec![quad(0, 0, 5.0)]);
+
+        let corners: Vec<_> = vertices
+            .iter()
+            .map(|vertex| (vertex.position, vertex.uv))
+            .collect();
+        assert_eq!(corners, [
+            ([5.0, 0.0], [0.0, 0.0]),
+            ([15.0, 0.0], [1.0, 0.0]),
+            ([15.0, 20.0], [1.0, 1.0]),
+            ([5.0, 20.0], [0.0, 1.0]),
+        ]);
+    }
+}
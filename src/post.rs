@@ -0,0 +1,886 @@
+//! Post-processing effect chain.
+//!
+//! While effects are set, the scene is drawn into an offscreen target instead of the frame. Each
+//! effect then runs as one or more full-screen passes that ping-pong between three targets, the
+//! scene and two transient textures of the frame's render graph, and the last pass writes to the
+//! frame. Every pass binds its input texture, a linear sampler, the
+//! `PostParams` block and an auxiliary texture (the bloom or LUT texture) at group 0.
+
+use wgpu::{
+    BindGroup,
+    BindGroupLayout,
+    BindingResource,
+    Buffer,
+    BufferAddress,
+    BufferUsages,
+    Device,
+    Queue,
+    RenderPipeline,
+    ShaderModule,
+    ShaderStages,
+    TextureView,
+};
+
+use crate::error::RenderError;
+use crate::init::{ grow_buffer, validated };
+use crate::material::{ create_uniform_layout, MaterialUniforms };
+use crate::profiler::{ FrameCounters, PassTimestamps };
+use crate::shader_types::PostParams;
+use crate::types::{ ColorLutId, PostEffect, PostShaderId, UniformLayout };
+
+/// Declarations available to custom post shaders, prepended to their source.
+const CUSTOM_PRELUDE: &str =
+    r#"
+struct PostParams {
+    texel_size: vec2<f32>,
+    direction: vec2<f32>,
+    params: vec4<f32>,
+}
+
+struct PostInput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@group(0) @binding(0) var input_texture: texture_2d<f32>;
+@group(0) @binding(1) var input_sampler: sampler;
+@group(0) @binding(2) var<uniform> post: PostParams;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> PostInput {
+    let position = vec2(f32((index << 1u) & 2u), f32(index & 2u));
+    return PostInput(vec4(position * 2.0 - 1.0, 0.0, 1.0), vec2(position.x, 1.0 - position.y));
+}
+"#;
+
+pub(crate) const TARGET_COUNT: usize = 3;
+
+struct BuiltinPipelines {
+    blur: RenderPipeline,
+    bloom_extract: RenderPipeline,
+    bloom_composite: RenderPipeline,
+    crt: RenderPipeline,
+    vignette: RenderPipeline,
+    color_grade: RenderPipeline,
+}
+
+impl BuiltinPipelines {
+    /// Takes the blur, bloom extract, bloom composite, CRT, vignette and color grade fragment
+    /// shaders, in that order.
+    fn new(
+        device: &Device,
+        bind_group_layout: &BindGroupLayout,
+        format: wgpu::TextureFormat,
+        vert_shader: &ShaderModule,
+        frag_shaders: &[ShaderModule; 6],
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Self {
+        let pipeline = |frag_shader| {
+            create_post_pipeline(
+                device,
+                &[bind_group_layout],
+                format,
+                vert_shader,
+                frag_shader,
+                cache
+            )
+        };
+
+        Self {
+            blur: pipeline(&frag_shaders[0]),
+            bloom_extract: pipeline(&frag_shaders[1]),
+            bloom_composite: pipeline(&frag_shaders[2]),
+            crt: pipeline(&frag_shaders[3]),
+            vignette: pipeline(&frag_shaders[4]),
+            color_grade: pipeline(&frag_shaders[5]),
+        }
+    }
+}
+
+struct PostShader {
+    pipeline: RenderPipeline,
+    layout: UniformLayout,
+    uniforms: Option<MaterialUniforms>,
+}
+
+struct ColorLut {
+    view: TextureView,
+    size: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Source {
+    Target(usize),
+    Lut(usize),
+    None,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum PassPipeline {
+    Blur,
+    BloomExtract,
+    BloomComposite,
+    Crt,
+    Vignette,
+    ColorGrade,
+    Custom(usize),
+}
+
+struct Pass {
+    pipeline: PassPipeline,
+    input: usize,
+    aux: Source,
+    output: usize,
+    direction: [f32; 2],
+    params: [f32; 4],
+}
+
+impl Pass {
+    fn new(pipeline: PassPipeline, input: usize, params: [f32; 4]) -> Self {
+        Self {
+            pipeline,
+            input,
+            aux: Source::None,
+            output: 0,
+            direction: [0.0, 0.0],
+            params,
+        }
+    }
+
+    fn with_aux(mut self, aux: Source) -> Self {
+        self.aux = aux;
+        self
+    }
+
+    fn with_direction(mut self, direction: [f32; 2]) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+/// Appends `pass` writing to a target that neither its inputs nor `keep` live in, and returns
+/// that target.
+fn push_pass(passes: &mut Vec<Pass>, mut pass: Pass, keep: Option<usize>) -> usize {
+    pass.output = (0..TARGET_COUNT)
+        .find(|&target| {
+            target != pass.input && pass.aux != Source::Target(target) && keep != Some(target)
+        })
+        .unwrap_or(0);
+    passes.push(pass);
+    passes[passes.len() - 1].output
+}
+
+/// Appends a horizontal and a vertical blur pass.
+fn push_blur(passes: &mut Vec<Pass>, input: usize, radius: f32, keep: Option<usize>) -> usize {
+    let params = [radius, 0.0, 0.0, 0.0];
+    let horizontal = push_pass(
+        passes,
+        Pass::new(PassPipeline::Blur, input, params).with_direction([1.0, 0.0]),
+        keep
+    );
+    push_pass(
+        passes,
+        Pass::new(PassPipeline::Blur, horizontal, params).with_direction([0.0, 1.0]),
+        keep
+    )
+}
+
+/// Expands `effects` into passes, ping-ponging between the post targets. The scene starts in
+/// target 0. `lut_size` returns the size of a color grading LUT.
+fn plan(effects: &[PostEffect], srgb: bool, lut_size: impl Fn(usize) -> u32) -> Vec<Pass> {
+    let mut passes = Vec::new();
+    let mut current = 0;
+
+    for effect in effects {
+        current = match *effect {
+            PostEffect::Bloom { threshold, intensity, radius } => {
+                // The scene is kept for the composite
+                let scene = Some(current);
+                let bright = push_pass(
+                    &mut passes,
+                    Pass::new(PassPipeline::BloomExtract, current, [threshold, 0.0, 0.0, 0.0]),
+                    scene
+                );
+                let blurred = push_blur(&mut passes, bright, radius, scene);
+                push_pass(
+                    &mut passes,
+                    Pass::new(
+                        PassPipeline::BloomComposite,
+                        current,
+                        [intensity, 0.0, 0.0, 0.0]
+                    ).with_aux(Source::Target(blurred)),
+                    None
+                )
+            }
+            PostEffect::Blur { radius } => push_blur(&mut passes, current, radius, None),
+            PostEffect::Crt { scanline_intensity, curvature } =>
+                push_pass(
+                    &mut passes,
+                    Pass::new(
+                        PassPipeline::Crt,
+                        current,
+                        [scanline_intensity, curvature, 0.0, 0.0]
+                    ),
+                    None
+                ),
+            PostEffect::Vignette { intensity, radius, softness } =>
+                push_pass(
+                    &mut passes,
+                    Pass::new(
+                        PassPipeline::Vignette,
+                        current,
+                        [intensity, radius, softness, 0.0]
+                    ),
+                    None
+                ),
+            PostEffect::ColorGrading { lut, intensity } => {
+                let size = lut_size(lut.0) as f32;
+                let srgb = if srgb { 1.0 } else { 0.0 };
+                push_pass(
+                    &mut passes,
+                    Pass::new(
+                        PassPipeline::ColorGrade,
+                        current,
+                        [intensity, size, srgb, 0.0]
+                    ).with_aux(Source::Lut(lut.0)),
+                    None
+                )
+            }
+            PostEffect::Custom(shader) =>
+                push_pass(
+                    &mut passes,
+                    Pass::new(PassPipeline::Custom(shader.0), current, [0.0; 4]),
+                    None
+                ),
+        };
+    }
+
+    passes
+}
+
+pub(crate) struct PostProcessor {
+    effects: Vec<PostEffect>,
+    format: wgpu::TextureFormat,
+    bind_group_layout: BindGroupLayout,
+    uniform_layout: BindGroupLayout,
+    pipelines: BuiltinPipelines,
+    shaders: Vec<PostShader>,
+    luts: Vec<ColorLut>,
+    sampler: wgpu::Sampler,
+    // Bound as the auxiliary texture by passes that do not use one
+    blank_view: TextureView,
+    size: (u32, u32),
+    params_buffer: Buffer,
+    params_stride: BufferAddress,
+}
+
+impl PostProcessor {
+    pub(crate) fn new(
+        device: &Device,
+        config: &wgpu::SurfaceConfiguration,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Self {
+        let bind_group_layout = create_post_bind_group_layout(device);
+        let vert_shader = create_fullscreen_shader(device);
+        let frag_shader = |label: &str, source: &'static str| {
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(source)),
+            })
+        };
+        let frag_shaders = [
+            frag_shader("blur shader", include_str!("../res/shaders/blur.frag.wgsl")),
+            frag_shader(
+                "bloom extract shader",
+                include_str!("../res/shaders/bloom_extract.frag.wgsl")
+            ),
+            frag_shader(
+                "bloom composite shader",
+                include_str!("../res/shaders/bloom_composite.frag.wgsl")
+            ),
+            frag_shader("crt shader", include_str!("../res/shaders/crt.frag.wgsl")),
+            frag_shader("vignette shader", include_str!("../res/shaders/vignette.frag.wgsl")),
+            frag_shader(
+                "color grade shader",
+                include_str!("../res/shaders/color_grade.frag.wgsl")
+            ),
+        ];
+        let pipelines = BuiltinPipelines::new(
+            device,
+            &bind_group_layout,
+            config.format,
+            &vert_shader,
+            &frag_shaders,
+            cache
+        );
+
+        let sampler = device.create_sampler(
+            &(wgpu::SamplerDescriptor {
+                label: Some("Post Sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            })
+        );
+        let blank_view = create_texture(
+            device,
+            "Post Blank Texture",
+            (1, 1),
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureUsages::TEXTURE_BINDING
+        ).create_view(&Default::default());
+
+        let params_stride = device
+            .limits()
+            .min_uniform_buffer_offset_alignment.max(
+                std::mem::size_of::<PostParams>() as u32
+            ) as BufferAddress;
+        let params_buffer = device.create_buffer(
+            &(wgpu::BufferDescriptor {
+                label: Some("Post Params Buffer"),
+                size: params_stride * 8,
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+
+        let mut post = Self {
+            effects: Vec::new(),
+            format: config.format,
+            bind_group_layout,
+            uniform_layout: create_uniform_layout(device),
+            pipelines,
+            shaders: Vec::new(),
+            luts: Vec::new(),
+            sampler,
+            blank_view,
+            size: (config.width, config.height),
+            params_buffer,
+            params_stride,
+        };
+        post.resize(config.width, config.height);
+        post
+    }
+
+    /// Recompiles `fullscreen.vert` and the built-in effect shaders from `directory` and rebuilds
+    /// their pipelines. Custom shaders are compiled from the application's WGSL and are kept.
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn reload_shaders(
+        &mut self,
+        device: &Device,
+        directory: &std::path::Path,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Result<(), RenderError> {
+        use crate::hot_reload::load_shader;
+
+        let vert_shader = load_shader(device, &directory.join("fullscreen.vert"))?;
+        let frag_shader = |name| load_shader(device, &directory.join(format!("{}.frag", name)));
+        let frag_shaders = [
+            frag_shader("blur")?,
+            frag_shader("bloom_extract")?,
+            frag_shader("bloom_composite")?,
+            frag_shader("crt")?,
+            frag_shader("vignette")?,
+            frag_shader("color_grade")?,
+        ];
+
+        self.pipelines = validated(device, || {
+            BuiltinPipelines::new(
+                device,
+                &self.bind_group_layout,
+                self.format,
+                &vert_shader,
+                &frag_shaders,
+                cache
+            )
+        })?;
+        Ok(())
+    }
+
+    pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        self.size = (width.max(1), height.max(1));
+    }
+
+    /// Replaces the effect chain. Effects referring to unknown LUTs or shaders are dropped.
+    pub(crate) fn set_effects(&mut self, effects: &[PostEffect]) {
+        self.effects = effects
+            .iter()
+            .filter(|effect| {
+                let known = match effect {
+                    PostEffect::ColorGrading { lut, .. } => lut.0 < self.luts.len(),
+                    PostEffect::Custom(shader) => shader.0 < self.shaders.len(),
+                    _ => true,
+                };
+                if !known {
+                    log::warn!("Dropping post effect {:?} with an unknown handle", effect);
+                }
+                known
+            })
+            .copied()
+            .collect();
+    }
+
+    pub(crate) fn effects(&self) -> &[PostEffect] {
+        &self.effects
+    }
+
+    /// Whether the scene has to be drawn into an offscreen target this frame.
+    pub(crate) fn is_active(&self) -> bool {
+        !self.effects.is_empty()
+    }
+
+    /// Loads a LUT strip image: `size` square slices of `size` by `size` texels side by side,
+    /// one per blue level.
+    pub(crate) fn load_lut(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        bytes: &[u8]
+    ) -> Result<ColorLutId, RenderError> {
+        let image = image
+            ::load_from_memory(bytes)
+            .map_err(|e| RenderError::ResourceError(format!("Invalid LUT image: {}", e)))?
+            .to_rgba8();
+        let (width, height) = image.dimensions();
+        if width != height * height {
+            return Err(
+                RenderError::ResourceError(
+                    format!(
+                        "A LUT of size {} must be {} pixels wide, got {}",
+                        height,
+                        height * height,
+                        width
+                    )
+                )
+            );
+        }
+
+        // Grading happens on encoded colors when the target is sRGB
+        let format = if self.format.is_srgb() {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        };
+        let texture = create_texture(
+            device,
+            "Color LUT",
+            (width, height),
+            format,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+        );
+        queue.write_texture(
+            texture.as_image_copy(),
+            &image,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: None,
+            },
+            texture.size()
+        );
+
+        self.luts.push(ColorLut {
+            view: texture.create_view(&Default::default()),
+            size: height,
+        });
+        Ok(ColorLutId(self.luts.len() - 1))
+    }
+
+    /// Compiles a custom post shader: a WGSL fragment entry point written against
+    /// `CUSTOM_PRELUDE`, with its uniform block at group 1.
+    pub(crate) fn create_shader(
+        &mut self,
+        device: &Device,
+        source: &str,
+        layout: UniformLayout,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Result<PostShaderId, RenderError> {
+        let source = format!("{}{}", CUSTOM_PRELUDE, source);
+        let pipeline = validated(device, || {
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("custom post shader"),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+            let mut bind_group_layouts = vec![&self.bind_group_layout];
+            if layout.size > 0 {
+                bind_group_layouts.push(&self.uniform_layout);
+            }
+            create_post_pipeline(
+                device,
+                &bind_group_layouts,
+                self.format,
+                &shader,
+                &shader,
+                cache
+            )
+        })?;
+
+        let uniforms = (layout.size > 0).then(|| {
+            MaterialUniforms::new(device, &self.uniform_layout, layout.size)
+        });
+        self.shaders.push(PostShader { pipeline, layout, uniforms });
+        Ok(PostShaderId(self.shaders.len() - 1))
+    }
+
+    pub(crate) fn set_shader_uniforms(
+        &self,
+        queue: &Queue,
+        shader: PostShaderId,
+        bytes: &[u8]
+    ) -> Result<(), RenderError> {
+        let shader = self.shaders
+            .get(shader.0)
+            .ok_or_else(|| RenderError::ResourceError(format!("Unknown {:?}", shader)))?;
+
+        MaterialUniforms::write(shader.uniforms.as_ref(), queue, shader.layout, bytes)
+    }
+
+    /// Runs the effect chain on the scene in `targets[0]`, using the other targets for
+    /// intermediate results, and writes the result to `output`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn apply(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        targets: [&TextureView; TARGET_COUNT],
+        output: &TextureView,
+        timestamps: Option<PassTimestamps>,
+        counters: &mut FrameCounters
+    ) {
+        let passes = self.plan();
+        if passes.is_empty() {
+            return;
+        }
+
+        let texel_size = [1.0 / (self.size.0 as f32), 1.0 / (self.size.1 as f32)];
+        let mut params = vec![0u8; (self.params_stride as usize) * passes.len()];
+        for (i, pass) in passes.iter().enumerate() {
+            let block = PostParams {
+                texel_size,
+                direction: pass.direction,
+                params: pass.params,
+            };
+            let offset = (self.params_stride as usize) * i;
+            params[offset..offset + std::mem::size_of::<PostParams>()].copy_from_slice(
+                bytemuck::bytes_of(&block)
+            );
+        }
+        grow_buffer(
+            device,
+            &mut self.params_buffer,
+            Some("Post Params Buffer"),
+            params.len() as BufferAddress
+        );
+        queue.write_buffer(&self.params_buffer, 0, &params);
+        counters.upload(params.len());
+
+        for (i, pass) in passes.iter().enumerate() {
+            let aux = match pass.aux {
+                Source::Target(target) => targets[target],
+                Source::Lut(lut) => &self.luts[lut].view,
+                Source::None => &self.blank_view,
+            };
+            let bind_group = self.create_bind_group(device, targets[pass.input], aux);
+            let target = if i == passes.len() - 1 { output } else { targets[pass.output] };
+
+            let mut render_pass = encoder.begin_render_pass(
+                &(wgpu::RenderPassDescriptor {
+                    label: Some("Post Render Pass"),
+                    color_attachments: &[
+                        Some(wgpu::RenderPassColorAttachment {
+                            view: target,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                                store: wgpu::StoreOp::Store,
+                            },
+                            depth_slice: None,
+                        }),
+                    ],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: timestamps
+                        .as_ref()
+                        .map(|timestamps| timestamps.writes(i == 0, i == passes.len() - 1)),
+                    occlusion_query_set: None,
+                })
+            );
+
+            let pipeline = match pass.pipeline {
+                PassPipeline::Blur => &self.pipelines.blur,
+                PassPipeline::BloomExtract => &self.pipelines.bloom_extract,
+                PassPipeline::BloomComposite => &self.pipelines.bloom_composite,
+                PassPipeline::Crt => &self.pipelines.crt,
+                PassPipeline::Vignette => &self.pipelines.vignette,
+                PassPipeline::ColorGrade => &self.pipelines.color_grade,
+                PassPipeline::Custom(shader) => {
+                    let shader = &self.shaders[shader];
+                    if let Some(uniforms) = &shader.uniforms {
+                        render_pass.set_bind_group(1, &uniforms.bind_group, &[]);
+                    }
+                    &shader.pipeline
+                }
+            };
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(
+                0,
+                &bind_group,
+                &[(self.params_stride * (i as BufferAddress)) as u32]
+            );
+            render_pass.draw(0..3, 0..1);
+            counters.draw(3, 1);
+        }
+    }
+
+    /// Expands the effects into passes. The scene starts in target 0.
+    fn plan(&self) -> Vec<Pass> {
+        plan(&self.effects, self.format.is_srgb(), |lut| self.luts[lut].size)
+    }
+
+    fn create_bind_group(
+        &self,
+        device: &Device,
+        input: &TextureView,
+        aux: &TextureView
+    ) -> BindGroup {
+        device.create_bind_group(
+            &(wgpu::BindGroupDescriptor {
+                label: Some("Post BG"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(input),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &self.params_buffer,
+                            offset: 0,
+                            size: wgpu::BufferSize::new(std::mem::size_of::<PostParams>() as u64),
+                        }),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: BindingResource::TextureView(aux),
+                    },
+                ],
+            })
+        )
+    }
+}
+
+pub(crate) fn create_texture(
+    device: &Device,
+    label: &str,
+    (width, height): (u32, u32),
+    format: wgpu::TextureFormat,
+    usage: wgpu::TextureUsages
+) -> wgpu::Texture {
+    device.create_texture(
+        &(wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        })
+    )
+}
+
+fn create_post_bind_group_layout(device: &Device) -> BindGroupLayout {
+    let texture = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    };
+
+    device.create_bind_group_layout(
+        &(wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post BGL"),
+            entries: &[
+                texture(0),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<PostParams>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                texture(3),
+            ],
+        })
+    )
+}
+
+pub(crate) fn create_fullscreen_shader(device: &Device) -> ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("fullscreen vertex shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            std::borrow::Cow::Borrowed(include_str!("../res/shaders/fullscreen.vert.wgsl"))
+        ),
+    })
+}
+
+pub(crate) fn create_post_pipeline(
+    device: &Device,
+    bind_group_layouts: &[&BindGroupLayout],
+    format: wgpu::TextureFormat,
+    vert_shader: &ShaderModule,
+    frag_shader: &ShaderModule,
+    cache: Option<&wgpu::PipelineCache>
+) -> RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(
+        &(wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Pipeline Layout"),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        })
+    );
+
+    // Built-in shaders use `main` and custom ones `vs_main` plus their own fragment entry point,
+    // so each module has a single entry point per stage
+    device.create_render_pipeline(
+        &(wgpu::RenderPipelineDescriptor {
+            label: Some("Post Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: vert_shader,
+                entry_point: None,
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: frag_shader,
+                entry_point: None,
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache,
+        })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan_effects(effects: &[PostEffect]) -> Vec<Pass> {
+        plan(effects, false, |_| 16)
+    }
+
+    fn pipelines(passes: &[Pass]) -> Vec<PassPipeline> {
+        passes.iter().map(|pass| pass.pipeline).collect()
+    }
+
+    #[test]
+    fn effects_expand_into_chained_passes() {
+        let passes = plan_effects(
+            &[
+                PostEffect::Bloom { threshold: 0.8, intensity: 1.0, radius: 2.0 },
+                PostEffect::Blur { radius: 1.0 },
+                PostEffect::Custom(PostShaderId(0)),
+            ]
+        );
+
+        assert_eq!(pipelines(&passes), [
+            PassPipeline::BloomExtract,
+            PassPipeline::Blur,
+            PassPipeline::Blur,
+            PassPipeline::BloomComposite,
+            PassPipeline::Blur,
+            PassPipeline::Blur,
+            PassPipeline::Custom(0),
+        ]);
+        assert_eq!(passes[0].input, 0);
+        assert_eq!([passes[1].direction, passes[2].direction], [[1.0, 0.0], [0.0, 1.0]]);
+
+        // Apart from the composite, which reads the scene, each pass reads the previous output
+        for (index, pass) in passes.iter().enumerate().skip(1) {
+            if pass.pipeline != PassPipeline::BloomComposite {
+                assert_eq!(pass.input, passes[index - 1].output, "pass {}", index);
+            }
+        }
+    }
+
+    #[test]
+    fn bloom_composite_reads_the_kept_scene() {
+        let passes = plan_effects(
+            &[
+                PostEffect::Blur { radius: 1.0 },
+                PostEffect::Bloom { threshold: 0.8, intensity: 1.0, radius: 2.0 },
+            ]
+        );
+        let scene = passes[1].output;
+        let (extract, composite) = (&passes[2], &passes[5]);
+
+        assert_eq!(extract.input, scene);
+        assert_eq!(composite.input, scene);
+        assert_eq!(composite.aux, Source::Target(passes[4].output));
+        // Nothing between the extract and the composite overwrites the scene
+        assert!(passes[2..5].iter().all(|pass| pass.output != scene));
+    }
+
+    #[test]
+    fn no_pass_reads_its_own_output() {
+        let effects = [
+            PostEffect::Bloom { threshold: 0.8, intensity: 1.0, radius: 2.0 },
+            PostEffect::Blur { radius: 1.0 },
+            PostEffect::Custom(PostShaderId(0)),
+            PostEffect::Bloom { threshold: 0.5, intensity: 0.5, radius: 4.0 },
+            PostEffect::ColorGrading { lut: ColorLutId(0), intensity: 1.0 },
+        ];
+
+        for pass in plan_effects(&effects) {
+            assert!(pass.output < TARGET_COUNT);
+            assert_ne!(pass.input, pass.output, "{:?}", pass.pipeline);
+            assert_ne!(pass.aux, Source::Target(pass.output), "{:?}", pass.pipeline);
+        }
+    }
+
+    #[test]
+    fn color_grading_takes_the_lut_size_and_surface_encoding() {
+        let effects = [PostEffect::ColorGrading { lut: ColorLutId(0), intensity: 0.5 }];
+        let passes = plan(&effects, true, |_| 32);
+
+        assert_eq!(passes[0].aux, Source::Lut(0));
+        assert_eq!(passes[0].params, [0.5, 32.0, 1.0, 0.0]);
+    }
+}
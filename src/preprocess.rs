@@ -0,0 +1,229 @@
+//! GLSL preprocessing shared by build.rs and shader hot reloading.
+//!
+//! `#include "name.glsl"` splices in a file from the shader directory. Each file is included at
+//! most once per shader, so includes need no guards. `#pragma variant <name> [DEFINE[=value]]...`
+//! declares an extra compilation of the shader with those defines set, written next to the base
+//! one as `<stem>.<name>.<stage>.wgsl`. Every other directive, `#ifdef` included, is left to
+//! naga.
+
+use std::path::{ Path, PathBuf };
+
+/// A shader with its includes spliced in.
+pub struct Source {
+    pub code: String,
+    pub variants: Vec<Variant>,
+    // File and 1-based line each line of `code` came from
+    lines: Vec<(PathBuf, usize)>,
+}
+
+pub struct Variant {
+    pub name: String,
+    pub defines: Vec<(String, String)>,
+}
+
+impl Source {
+    /// Returns the file and line that a 1-based line of the expanded code came from.
+    pub fn origin(&self, line: usize) -> (&Path, usize) {
+        match self.lines.get(line.saturating_sub(1)).or(self.lines.last()) {
+            Some((path, line)) => (path, *line),
+            None => (Path::new(""), line),
+        }
+    }
+}
+
+/// Reads `path` and resolves its includes relative to `include_directory`.
+pub fn expand(include_directory: &Path, path: &Path) -> Result<Source, String> {
+    let mut source = Source {
+        code: String::new(),
+        variants: Vec::new(),
+        lines: Vec::new(),
+    };
+    let mut included = Vec::new();
+    expand_file(include_directory, path, &mut source, &mut included)?;
+    Ok(source)
+}
+
+fn expand_file(
+    include_directory: &Path,
+    path: &Path,
+    source: &mut Source,
+    included: &mut Vec<PathBuf>
+) -> Result<(), String> {
+    let code = std::fs
+        ::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    for (index, line) in code.lines().enumerate() {
+        let line_number = index + 1;
+        let directive = line.trim_start();
+        let error = |message: String| format!("{}:{}: {}", path.display(), line_number, message);
+
+        if let Some(rest) = directive.strip_prefix("#include") {
+            let name = rest
+                .trim()
+                .strip_prefix('"')
+                .and_then(|rest| rest.strip_suffix('"'))
+                .ok_or_else(|| error(format!("Expected `#include \"file\"`, found `{}`", line)))?;
+            let include_path = include_directory.join(name);
+
+            if included.contains(&include_path) {
+                push_line(source, "", path, line_number);
+            } else {
+                if !include_path.is_file() {
+                    let directory = include_directory.display();
+                    return Err(error(format!("Cannot include {}: not in {}", name, directory)));
+                }
+                included.push(include_path.clone());
+                expand_file(include_directory, &include_path, source, included)?;
+            }
+            continue;
+        }
+
+        if let Some(rest) = directive.strip_prefix("#pragma variant") {
+            let mut words = rest.split_whitespace();
+            let name = words
+                .next()
+                .ok_or_else(|| error("Expected a name after `#pragma variant`".to_string()))?;
+            if source.variants.iter().any(|variant| variant.name == name) {
+                return Err(error(format!("Variant `{}` is declared twice", name)));
+            }
+
+            let defines = words
+                .map(|define| {
+                    match define.split_once('=') {
+                        Some((key, value)) => (key.to_string(), value.to_string()),
+                        None => (define.to_string(), String::new()),
+                    }
+                })
+                .collect();
+            source.variants.push(Variant {
+                name: name.to_string(),
+                defines,
+            });
+
+            // Kept as an empty line so the line map stays one entry per line
+            push_line(source, "", path, line_number);
+            continue;
+        }
+
+        push_line(source, line, path, line_number);
+    }
+
+    Ok(())
+}
+
+fn push_line(source: &mut Source, line: &str, path: &Path, line_number: usize) {
+    source.code.push_str(line);
+    source.code.push('\n');
+    source.lines.push((path.to_path_buf(), line_number));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `files` into a fresh directory under the system temp directory.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env
+            ::temp_dir()
+            .join(format!("wgpu-renderer-preprocess-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        for (name, code) in files {
+            std::fs::write(directory.join(name), code).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn includes_are_spliced_in_once() {
+        let directory = write_files("includes", &[
+            ("common.glsl", "float common() { return 1.0; }\n"),
+            ("light.glsl", "#include \"common.glsl\"\nfloat light() { return common(); }\n"),
+            (
+                "main.frag",
+                "#version 450\n#include \"common.glsl\"\n#include \"light.glsl\"\nvoid main() {}\n",
+            ),
+        ]);
+
+        let source = expand(&directory, &directory.join("main.frag")).unwrap();
+        assert_eq!(
+            source.code,
+            "#version 450\n\
+            float common() { return 1.0; }\n\
+            \n\
+            float light() { return common(); }\n\
+            void main() {}\n"
+        );
+    }
+
+    #[test]
+    fn origin_maps_lines_back_through_includes() {
+        let directory = write_files("origin", &[
+            ("common.glsl", "// common\nfloat common() { return 1.0; }\n"),
+            ("main.frag", "#version 450\n#include \"common.glsl\"\nvoid main() {}\n"),
+        ]);
+        let main = directory.join("main.frag");
+        let common = directory.join("common.glsl");
+
+        let source = expand(&directory, &main).unwrap();
+        assert_eq!(source.origin(1), (main.as_path(), 1));
+        assert_eq!(source.origin(2), (common.as_path(), 1));
+        assert_eq!(source.origin(3), (common.as_path(), 2));
+        assert_eq!(source.origin(4), (main.as_path(), 3));
+        // Lines past the end, such as errors at the end of the input, map to the last line
+        assert_eq!(source.origin(10), (main.as_path(), 3));
+    }
+
+    #[test]
+    fn variants_are_parsed_with_their_defines() {
+        let directory = write_files("variants", &[
+            (
+                "main.frag",
+                "#version 450\n#pragma variant outline OUTLINE RADIUS=4\n#pragma variant plain\n",
+            ),
+        ]);
+
+        let source = expand(&directory, &directory.join("main.frag")).unwrap();
+        assert_eq!(source.variants.len(), 2);
+        assert_eq!(source.variants[0].name, "outline");
+        assert_eq!(
+            source.variants[0].defines,
+            [
+                ("OUTLINE".to_string(), String::new()),
+                ("RADIUS".to_string(), "4".to_string()),
+            ]
+        );
+        assert_eq!(source.variants[1].name, "plain");
+        assert!(source.variants[1].defines.is_empty());
+        // The pragmas are blanked so naga does not see them and lines keep their numbers
+        assert_eq!(source.code, "#version 450\n\n\n");
+    }
+
+    #[test]
+    fn duplicate_variants_are_rejected() {
+        let directory = write_files("duplicate", &[
+            ("main.frag", "#version 450\n#pragma variant outline A\n#pragma variant outline B\n"),
+        ]);
+        let main = directory.join("main.frag");
+
+        let error = expand(&directory, &main).err().unwrap();
+        assert_eq!(
+            error,
+            format!("{}:3: Variant `outline` is declared twice", main.display())
+        );
+    }
+
+    #[test]
+    fn bad_includes_are_rejected() {
+        let directory = write_files("bad_includes", &[
+            ("missing.frag", "#version 450\n#include \"missing.glsl\"\n"),
+            ("unquoted.frag", "#include common.glsl\n"),
+        ]);
+
+        let error = expand(&directory, &directory.join("missing.frag")).err().unwrap();
+        assert!(error.contains("missing.frag:2: Cannot include missing.glsl"), "{}", error);
+        let error = expand(&directory, &directory.join("unquoted.frag")).err().unwrap();
+        assert!(error.contains("unquoted.frag:1: Expected `#include \"file\"`"), "{}", error);
+    }
+}
@@ -0,0 +1,311 @@
+//! Per-frame render statistics and GPU timing.
+//!
+//! Draw calls, vertices, triangles and uploaded bytes are counted by the code that records them,
+//! every frame. GPU timing is opt-in: while enabled, the render passes that start and end the
+//! shape, text and post sections of the frame write timestamps, which are resolved into a buffer
+//! and read back once the GPU is done with it. Only one readback is in flight at a time, so
+//! frames recorded while the previous one is still mapping are not timed.
+
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+
+use wgpu::{ Buffer, BufferAddress, BufferUsages, Device, Features, QuerySet, Queue };
+
+use crate::types::FrameStats;
+
+const TIMESTAMP_COUNT: u32 = 4;
+const TIMESTAMPS_SIZE: BufferAddress = (TIMESTAMP_COUNT as BufferAddress) * 8;
+// wgpu_glyph draws each glyph as an instance of position, texture coordinates and color, and
+// uploads its transform matrix with every draw
+const GLYPH_INSTANCE_SIZE: u64 = 13 * 4;
+const GLYPH_TRANSFORM_SIZE: u64 = 16 * 4;
+
+/// Points of the frame where a timestamp is written, in the order they are recorded.
+#[derive(Copy, Clone)]
+pub(crate) enum Mark {
+    FrameStart = 0,
+    ShapesEnd = 1,
+    TextEnd = 2,
+    PostEnd = 3,
+}
+
+/// Draw and upload counts of the frame being recorded.
+#[derive(Default)]
+pub(crate) struct FrameCounters {
+    draw_calls: u32,
+    vertices: u64,
+    triangles: u64,
+    bytes_uploaded: u64,
+}
+
+impl FrameCounters {
+    /// Counts one draw of a triangle list.
+    pub(crate) fn draw(&mut self, vertex_count: u32, instance_count: u32) {
+        let vertices = (vertex_count as u64) * (instance_count as u64);
+        self.draw_calls += 1;
+        self.vertices += vertices;
+        self.triangles += vertices / 3;
+    }
+
+    pub(crate) fn upload(&mut self, bytes: usize) {
+        self.bytes_uploaded += bytes as u64;
+    }
+
+    /// Counts one `draw_queued` of a wgpu_glyph brush holding `glyphs` glyphs, each drawn as a
+    /// quad. The brush writes its instances and transform through the staging belt, skipping the
+    /// instances when the queued text did not change, so the uploaded bytes are an upper bound.
+    pub(crate) fn glyph_draw(&mut self, glyphs: u32) {
+        if glyphs == 0 {
+            return;
+        }
+        self.draw_calls += 1;
+        self.vertices += 4 * (glyphs as u64);
+        self.triangles += 2 * (glyphs as u64);
+        self.bytes_uploaded += GLYPH_INSTANCE_SIZE * (glyphs as u64) + GLYPH_TRANSFORM_SIZE;
+    }
+}
+
+/// Glyphs wgpu_glyph draws for `text`, one per character that is not whitespace.
+pub(crate) fn glyph_count(text: &str) -> u32 {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .count() as u32
+}
+
+/// Timestamps written by the render passes of one section of the frame: `beginning` at the start
+/// of its first pass and `end` at the end of its last.
+pub(crate) struct PassTimestamps {
+    query_set: QuerySet,
+    beginning: Option<Mark>,
+    end: Option<Mark>,
+}
+
+impl PassTimestamps {
+    pub(crate) fn writes(&self, first: bool, last: bool) -> wgpu::RenderPassTimestampWrites<'_> {
+        wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: self.beginning.filter(|_| first).map(|mark| mark as u32),
+            end_of_pass_write_index: self.end.filter(|_| last).map(|mark| mark as u32),
+        }
+    }
+}
+
+struct Timestamps {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    // Nanoseconds per timestamp tick
+    period: f32,
+    mapped: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+    // Whether the readback buffer is mapping or mapped
+    in_flight: bool,
+    // Whether the frame being recorded writes timestamps
+    recording: bool,
+}
+
+pub(crate) struct Profiler {
+    // `None` while GPU timing is off
+    timestamps: Option<Timestamps>,
+    counters: FrameCounters,
+    encode_start: Instant,
+    stats: FrameStats,
+}
+
+impl Profiler {
+    pub(crate) fn new() -> Self {
+        Self {
+            timestamps: None,
+            counters: FrameCounters::default(),
+            encode_start: Instant::now(),
+            stats: FrameStats::default(),
+        }
+    }
+
+    /// Turns GPU timing on or off. Returns `false` if the device does not support timestamp
+    /// queries, in which case timing stays off.
+    pub(crate) fn set_enabled(&mut self, device: &Device, queue: &Queue, enabled: bool) -> bool {
+        if !enabled {
+            self.timestamps = None;
+            self.stats.shape_gpu_time = None;
+            self.stats.text_gpu_time = None;
+            self.stats.post_gpu_time = None;
+            return true;
+        }
+        if self.timestamps.is_some() {
+            return true;
+        }
+
+        if !device.features().contains(Features::TIMESTAMP_QUERY) {
+            log::warn!("GPU timing is not supported by this adapter");
+            return false;
+        }
+
+        let query_set = device.create_query_set(
+            &(wgpu::QuerySetDescriptor {
+                label: Some("Profiler Timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: TIMESTAMP_COUNT,
+            })
+        );
+        let resolve_buffer = device.create_buffer(
+            &(wgpu::BufferDescriptor {
+                label: Some("Profiler Resolve Buffer"),
+                size: TIMESTAMPS_SIZE,
+                usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        );
+        let readback_buffer = device.create_buffer(
+            &(wgpu::BufferDescriptor {
+                label: Some("Profiler Readback Buffer"),
+                size: TIMESTAMPS_SIZE,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+
+        self.timestamps = Some(Timestamps {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            mapped: Arc::default(),
+            in_flight: false,
+            recording: false,
+        });
+        true
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.timestamps.is_some()
+    }
+
+    pub(crate) fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    pub(crate) fn counters(&mut self) -> &mut FrameCounters {
+        &mut self.counters
+    }
+
+    /// Starts timing the CPU side of a frame and collects the GPU times of an earlier one, if
+    /// they have arrived.
+    pub(crate) fn begin_frame(&mut self, device: &Device) {
+        self.encode_start = Instant::now();
+
+        let Some(timestamps) = &mut self.timestamps else {
+            return;
+        };
+        if timestamps.in_flight {
+            if let Err(e) = device.poll(wgpu::PollType::Poll) {
+                log::warn!("Failed to poll device: {}", e);
+            }
+            let mapped = timestamps.mapped
+                .lock()
+                .ok()
+                .and_then(|mut mapped| mapped.take());
+            match mapped {
+                Some(Ok(())) => {
+                    let ticks: [u64; TIMESTAMP_COUNT as usize] = {
+                        let data = timestamps.readback_buffer.slice(..).get_mapped_range();
+                        bytemuck::pod_read_unaligned(&data)
+                    };
+                    timestamps.readback_buffer.unmap();
+                    timestamps.in_flight = false;
+
+                    let period = timestamps.period as f64;
+                    let elapsed = |from: Mark, to: Mark| {
+                        let ticks = ticks[to as usize].saturating_sub(ticks[from as usize]);
+                        Some(Duration::from_nanos(((ticks as f64) * period) as u64))
+                    };
+                    self.stats.shape_gpu_time = elapsed(Mark::FrameStart, Mark::ShapesEnd);
+                    self.stats.text_gpu_time = elapsed(Mark::ShapesEnd, Mark::TextEnd);
+                    self.stats.post_gpu_time = elapsed(Mark::TextEnd, Mark::PostEnd);
+                }
+                Some(Err(e)) => {
+                    log::warn!("Failed to read GPU timestamps: {}", e);
+                    timestamps.in_flight = false;
+                }
+                None => {}
+            }
+        }
+        timestamps.recording = !timestamps.in_flight;
+    }
+
+    /// Timestamps for the passes of a frame section, or `None` while the frame is not timed.
+    pub(crate) fn pass_timestamps(
+        &self,
+        beginning: Option<Mark>,
+        end: Option<Mark>
+    ) -> Option<PassTimestamps> {
+        let timestamps = self.timestamps.as_ref().filter(|timestamps| timestamps.recording)?;
+        Some(PassTimestamps {
+            query_set: timestamps.query_set.clone(),
+            beginning,
+            end,
+        })
+    }
+
+    /// Writes timestamps at a point of the frame that no pass of the renderer starts or ends,
+    /// through an empty compute pass.
+    pub(crate) fn mark(&self, encoder: &mut wgpu::CommandEncoder, beginning: Mark, end: Mark) {
+        if let Some(timestamps) = &self.timestamps && timestamps.recording {
+            encoder.begin_compute_pass(
+                &(wgpu::ComputePassDescriptor {
+                    label: Some("Profiler Timestamp Pass"),
+                    timestamp_writes: Some(wgpu::ComputePassTimestampWrites {
+                        query_set: &timestamps.query_set,
+                        beginning_of_pass_write_index: Some(beginning as u32),
+                        end_of_pass_write_index: Some(end as u32),
+                    }),
+                })
+            );
+        }
+    }
+
+    /// Resolves the frame's timestamps and publishes its counts. Must run before the encoder is
+    /// finished.
+    pub(crate) fn end_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(timestamps) = &self.timestamps && timestamps.recording {
+            encoder.resolve_query_set(
+                &timestamps.query_set,
+                0..TIMESTAMP_COUNT,
+                &timestamps.resolve_buffer,
+                0
+            );
+            encoder.copy_buffer_to_buffer(
+                &timestamps.resolve_buffer,
+                0,
+                &timestamps.readback_buffer,
+                0,
+                TIMESTAMPS_SIZE
+            );
+        }
+
+        let counters = std::mem::take(&mut self.counters);
+        self.stats.cpu_encode_time = self.encode_start.elapsed();
+        self.stats.draw_calls = counters.draw_calls;
+        self.stats.vertices = counters.vertices;
+        self.stats.triangles = counters.triangles;
+        self.stats.bytes_uploaded = counters.bytes_uploaded;
+    }
+
+    /// Starts reading back the timestamps of the submitted frame.
+    pub(crate) fn after_submit(&mut self) {
+        let Some(timestamps) = &mut self.timestamps else {
+            return;
+        };
+        if !timestamps.recording {
+            return;
+        }
+
+        let mapped = timestamps.mapped.clone();
+        timestamps.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            if let Ok(mut mapped) = mapped.lock() {
+                *mapped = Some(result);
+            }
+        });
+        timestamps.in_flight = true;
+        timestamps.recording = false;
+    }
+}
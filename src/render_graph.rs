@@ -0,0 +1,425 @@
+//! Render graph for the passes of a frame.
+//!
+//! Each frame is described as passes that declare the textures they read and write, instead of
+//! being hand-ordered in one function. Passes writing a texture run before the passes reading it,
+//! and passes writing the same texture keep the order they were added in. Textures are either
+//! imported, like the frame's surface view, or transient: created by the graph for this frame
+//! only. Transient textures come from a `TexturePool` kept across frames, and two with the same
+//! size and format share one texture when their uses in the ordered passes do not overlap.
+//! A final pass, if set, is recorded after all the others whatever textures they use.
+//! Everything is recorded into a single command encoder.
+
+use wgpu::{ CommandEncoder, Device, TextureFormat, TextureView };
+
+use crate::post::create_texture;
+
+/// Handle to a texture of a `RenderGraph`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct GraphTexture(usize);
+
+/// Description of a transient texture. Transient textures can be rendered to and sampled.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct TransientTexture {
+    pub(crate) label: &'static str,
+    pub(crate) size: (u32, u32),
+    pub(crate) format: TextureFormat,
+}
+
+impl TransientTexture {
+    // Labels only name the texture, so they do not prevent sharing
+    fn matches(&self, other: &Self) -> bool {
+        self.size == other.size && self.format == other.format
+    }
+}
+
+enum TextureSource {
+    Imported(TextureView),
+    Transient(TransientTexture),
+}
+
+type RecordPass<'a, C> = Box<dyn FnOnce(&mut C, &mut CommandEncoder, &GraphTextures) + 'a>;
+
+struct Pass<'a, C> {
+    name: &'static str,
+    reads: Vec<GraphTexture>,
+    writes: Vec<GraphTexture>,
+    record: RecordPass<'a, C>,
+}
+
+/// The passes of one frame. `C` is the state every pass records from, handed to `execute`.
+pub(crate) struct RenderGraph<'a, C> {
+    textures: Vec<TextureSource>,
+    passes: Vec<Pass<'a, C>>,
+    final_pass: Option<Pass<'a, C>>,
+}
+
+/// Views of a graph's textures while its passes are recorded.
+pub(crate) struct GraphTextures {
+    views: Vec<TextureView>,
+}
+
+impl GraphTextures {
+    pub(crate) fn view(&self, texture: GraphTexture) -> &TextureView {
+        &self.views[texture.0]
+    }
+}
+
+struct PooledTexture<T> {
+    desc: TransientTexture,
+    texture: T,
+    // Position of the last pass using it in the frame being compiled
+    busy_until: Option<usize>,
+}
+
+/// Transient textures kept between frames. Textures no frame used are dropped, so a resize frees
+/// the textures of the old size once the next frame is compiled. `T` is what a texture is handed
+/// out as, a view for rendering.
+pub(crate) struct TexturePool<T = TextureView> {
+    textures: Vec<PooledTexture<T>>,
+}
+
+impl<T> Default for TexturePool<T> {
+    fn default() -> Self {
+        Self { textures: Vec::new() }
+    }
+}
+
+impl<T: Clone> TexturePool<T> {
+    /// Starts compiling a frame, during which every texture is free until acquired.
+    fn begin_frame(&mut self) {
+        for texture in &mut self.textures {
+            texture.busy_until = None;
+        }
+    }
+
+    /// Returns a texture matching `desc` that is free from pass `first_use` on, creating one with
+    /// `create` if none is, and keeps it busy until `last_use`.
+    fn acquire(
+        &mut self,
+        desc: TransientTexture,
+        first_use: usize,
+        last_use: usize,
+        create: impl FnOnce(&TransientTexture) -> T
+    ) -> T {
+        let free = self.textures
+            .iter()
+            .position(|texture| {
+                texture.desc.matches(&desc) &&
+                    texture.busy_until.is_none_or(|busy_until| busy_until < first_use)
+            });
+        let index = free.unwrap_or_else(|| {
+            let texture = create(&desc);
+            self.textures.push(PooledTexture { desc, texture, busy_until: None });
+            self.textures.len() - 1
+        });
+
+        let texture = &mut self.textures[index];
+        texture.busy_until = Some(last_use);
+        texture.texture.clone()
+    }
+
+    /// Drops the textures the frame did not acquire.
+    fn end_frame(&mut self) {
+        self.textures.retain(|texture| texture.busy_until.is_some());
+    }
+}
+
+fn create_transient_view(device: &Device, desc: &TransientTexture) -> TextureView {
+    create_texture(
+        device,
+        desc.label,
+        (desc.size.0.max(1), desc.size.1.max(1)),
+        desc.format,
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+    ).create_view(&Default::default())
+}
+
+impl<'a, C> RenderGraph<'a, C> {
+    pub(crate) fn new() -> Self {
+        Self {
+            textures: Vec::new(),
+            passes: Vec::new(),
+            final_pass: None,
+        }
+    }
+
+    /// Adds a texture that lives outside the graph, such as the frame's surface view.
+    pub(crate) fn import_texture(&mut self, view: TextureView) -> GraphTexture {
+        self.textures.push(TextureSource::Imported(view));
+        GraphTexture(self.textures.len() - 1)
+    }
+
+    /// Adds a texture that only exists while this graph is recorded.
+    pub(crate) fn create_texture(&mut self, desc: TransientTexture) -> GraphTexture {
+        self.textures.push(TextureSource::Transient(desc));
+        GraphTexture(self.textures.len() - 1)
+    }
+
+    /// Adds a pass. `record` runs once the passes it depends on have been recorded, with the views
+    /// of the graph's textures.
+    pub(crate) fn add_pass(
+        &mut self,
+        name: &'static str,
+        reads: &[GraphTexture],
+        writes: &[GraphTexture],
+        record: impl FnOnce(&mut C, &mut CommandEncoder, &GraphTextures) + 'a
+    ) {
+        self.passes.push(Pass {
+            name,
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            record: Box::new(record),
+        });
+    }
+
+    /// Sets the pass recorded after every other one, for work that must see the whole frame
+    /// recorded, like resolving queries. It uses no graph textures.
+    pub(crate) fn set_final_pass(
+        &mut self,
+        name: &'static str,
+        record: impl FnOnce(&mut C, &mut CommandEncoder, &GraphTextures) + 'a
+    ) {
+        self.final_pass = Some(Pass {
+            name,
+            reads: Vec::new(),
+            writes: Vec::new(),
+            record: Box::new(record),
+        });
+    }
+
+    /// Orders the passes, allocates their transient textures from `pool` and records them into
+    /// `encoder`.
+    pub(crate) fn execute(
+        self,
+        device: &Device,
+        pool: &mut TexturePool,
+        encoder: &mut CommandEncoder,
+        context: &mut C
+    ) {
+        let order = self.order();
+        let textures = self.allocate(device, pool, &order);
+
+        let mut passes: Vec<Option<Pass<'a, C>>> = self.passes.into_iter().map(Some).collect();
+        let ordered = order.into_iter().filter_map(|index| passes[index].take());
+        for pass in ordered.chain(self.final_pass) {
+            encoder.push_debug_group(pass.name);
+            (pass.record)(context, encoder, &textures);
+            encoder.pop_debug_group();
+        }
+    }
+
+    /// Returns the pass indices in recording order. Among the passes whose dependencies have
+    /// been recorded, the one added first goes next.
+    fn order(&self) -> Vec<usize> {
+        let count = self.passes.len();
+        let mut dependencies = vec![Vec::new(); count];
+        for (index, pass) in self.passes.iter().enumerate() {
+            for (other, other_pass) in self.passes.iter().enumerate() {
+                if other == index {
+                    continue;
+                }
+                // Readers wait for every writer, and writers keep the order they were added in
+                let reads_output = pass.reads
+                    .iter()
+                    .any(|texture| other_pass.writes.contains(texture));
+                let writes_before = other < index &&
+                    pass.writes.iter().any(|texture| other_pass.writes.contains(texture));
+                if reads_output || writes_before {
+                    dependencies[index].push(other);
+                }
+            }
+        }
+
+        let mut recorded = vec![false; count];
+        let mut order = Vec::with_capacity(count);
+        while order.len() < count {
+            let next = (0..count).find(|&index| {
+                !recorded[index] && dependencies[index].iter().all(|&other| recorded[other])
+            });
+            let next = next.unwrap_or_else(|| {
+                // A cycle: fall back to the order the remaining passes were added in
+                let index = (0..count).find(|&index| !recorded[index]).unwrap_or_default();
+                let name = self.passes[index].name;
+                log::warn!("Render pass `{}` is part of a cycle, ordering it as added", name);
+                index
+            });
+            recorded[next] = true;
+            order.push(next);
+        }
+        order
+    }
+
+    fn allocate(&self, device: &Device, pool: &mut TexturePool, order: &[usize]) -> GraphTextures {
+        let mut views: Vec<Option<TextureView>> = self.textures
+            .iter()
+            .map(|source| {
+                match source {
+                    TextureSource::Imported(view) => Some(view.clone()),
+                    TextureSource::Transient(_) => None,
+                }
+            })
+            .collect();
+
+        pool.begin_frame();
+        for (texture, desc, first, last) in self.transient_uses(order) {
+            let create = |desc: &TransientTexture| create_transient_view(device, desc);
+            views[texture] = Some(pool.acquire(desc, first, last, create));
+        }
+        pool.end_frame();
+
+        GraphTextures {
+            views: views.into_iter().flatten().collect(),
+        }
+    }
+
+    /// Returns each transient texture with the first and last position in `order` of the passes
+    /// using it, sorted by first use. Textures no pass uses come last and still get a texture, so
+    /// lookups cannot fail.
+    fn transient_uses(&self, order: &[usize]) -> Vec<(usize, TransientTexture, usize, usize)> {
+        let mut uses: Vec<Option<(usize, usize)>> = vec![None; self.textures.len()];
+        for (position, &index) in order.iter().enumerate() {
+            let pass = &self.passes[index];
+            for texture in pass.reads.iter().chain(&pass.writes) {
+                let range = uses[texture.0].get_or_insert((position, position));
+                range.1 = position;
+            }
+        }
+
+        let mut transients: Vec<_> = self.textures
+            .iter()
+            .enumerate()
+            .filter_map(|(texture, source)| {
+                let TextureSource::Transient(desc) = source else {
+                    return None;
+                };
+                let (first, last) = uses[texture].unwrap_or((order.len(), order.len()));
+                Some((texture, *desc, first, last))
+            })
+            .collect();
+        transients.sort_by_key(|&(_, _, first, _)| first);
+        transients
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(size: (u32, u32)) -> TransientTexture {
+        TransientTexture { label: "Target", size, format: TextureFormat::Rgba8Unorm }
+    }
+
+    fn add(graph: &mut RenderGraph<'_, ()>, reads: &[GraphTexture], writes: &[GraphTexture]) {
+        graph.add_pass("Pass", reads, writes, |_, _, _| {});
+    }
+
+    /// Acquires a texture for each transient texture of `graph` from `pool`, numbering new ones
+    /// after those already pooled.
+    fn allocate(graph: &RenderGraph<'_, ()>, pool: &mut TexturePool<usize>) -> Vec<usize> {
+        let mut created = pool.textures.iter().map(|texture| texture.texture + 1).max();
+        let mut textures = vec![usize::MAX; graph.textures.len()];
+
+        pool.begin_frame();
+        for (texture, desc, first, last) in graph.transient_uses(&graph.order()) {
+            textures[texture] = pool.acquire(desc, first, last, |_| {
+                let next = created.unwrap_or_default();
+                created = Some(next + 1);
+                next
+            });
+        }
+        pool.end_frame();
+        textures
+    }
+
+    #[test]
+    fn readers_run_after_writers() {
+        let mut graph = RenderGraph::new();
+        let scene = graph.create_texture(target((4, 4)));
+        let bloom = graph.create_texture(target((4, 4)));
+        add(&mut graph, &[bloom], &[]);
+        add(&mut graph, &[scene], &[bloom]);
+        add(&mut graph, &[], &[scene]);
+
+        assert_eq!(graph.order(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn writers_keep_the_order_they_were_added_in() {
+        let mut graph = RenderGraph::new();
+        let scene = graph.create_texture(target((4, 4)));
+        add(&mut graph, &[scene], &[]);
+        add(&mut graph, &[], &[scene]);
+        add(&mut graph, &[], &[]);
+        add(&mut graph, &[], &[scene]);
+
+        // Independent passes go in as added, but the reader waits for both writers
+        assert_eq!(graph.order(), [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn reads_without_a_writer_do_not_block() {
+        let mut graph = RenderGraph::new();
+        let unwritten = graph.create_texture(target((4, 4)));
+        add(&mut graph, &[unwritten], &[]);
+        add(&mut graph, &[], &[]);
+
+        assert_eq!(graph.order(), [0, 1]);
+    }
+
+    #[test]
+    fn cycles_fall_back_to_the_order_added() {
+        let mut graph = RenderGraph::new();
+        let a = graph.create_texture(target((4, 4)));
+        let b = graph.create_texture(target((4, 4)));
+        add(&mut graph, &[], &[]);
+        add(&mut graph, &[b], &[a]);
+        add(&mut graph, &[a], &[b]);
+
+        assert_eq!(graph.order(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn textures_are_shared_when_their_uses_do_not_overlap() {
+        let mut graph = RenderGraph::new();
+        let scene = graph.create_texture(target((4, 4)));
+        let ping = graph.create_texture(target((4, 4)));
+        let pong = graph.create_texture(target((4, 4)));
+        let small = graph.create_texture(target((2, 2)));
+        add(&mut graph, &[], &[scene]);
+        add(&mut graph, &[scene], &[ping]);
+        add(&mut graph, &[ping], &[pong]);
+        add(&mut graph, &[pong], &[small]);
+
+        let mut pool = TexturePool::default();
+        let textures = allocate(&graph, &mut pool);
+
+        // Scene is last used before pong is first written, and a different size never shares
+        assert_eq!(textures[scene.0], textures[pong.0]);
+        assert_ne!(textures[scene.0], textures[ping.0]);
+        assert_ne!(textures[ping.0], textures[pong.0]);
+        assert_eq!(pool.textures.len(), 3);
+        assert!(!textures[..3].contains(&textures[small.0]));
+    }
+
+    #[test]
+    fn pooled_textures_are_reused_across_frames() {
+        let build = |size| {
+            let mut graph = RenderGraph::new();
+            let scene = graph.create_texture(target(size));
+            let bloom = graph.create_texture(target(size));
+            add(&mut graph, &[], &[scene]);
+            add(&mut graph, &[scene], &[bloom]);
+            graph
+        };
+
+        let mut pool = TexturePool::default();
+        let first = allocate(&build((4, 4)), &mut pool);
+        let second = allocate(&build((4, 4)), &mut pool);
+        assert_eq!(first, second);
+
+        // A resize creates new textures and drops the ones of the old size
+        let resized = allocate(&build((8, 8)), &mut pool);
+        assert!(resized.iter().all(|texture| !first.contains(texture)));
+        assert_eq!(pool.textures.len(), 2);
+    }
+}
@@ -1,12 +1,47 @@
-use std::{ iter, sync::Arc };
+use std::{ iter, path::{ Path, PathBuf }, sync::Arc };
 
-use wgpu::{ BindGroup, Buffer };
-use wgpu_glyph::{ Section, Text };
+use wgpu::{ BindGroup, BindGroupLayout, Buffer, BufferAddress };
+use wgpu_glyph::{ ab_glyph::{ self, Font, ScaleFont }, GlyphCruncher, Section };
 use winit::{ dpi::PhysicalSize, window::Window };
 
+use crate::batching::{ build_batches, sorted_items, Batch, BatchKey, DrawKind, Primitive };
+use crate::bitmap_font::{ BitmapFontRenderer, BitmapSection, FontDescriptor };
+use crate::compute::{ ComputeRunner, Readback };
+use crate::draw_list::{ replay_commands, DrawList, ListState };
+use crate::error::RenderError;
+use crate::frame::{ CustomPass, Frame };
 use crate::init::*;
+use crate::instancing::{ instance_bounds, ShapeInstancer };
+use crate::lighting::Lighting;
+use crate::material::{ Material, MaterialUniforms };
+use crate::mesh::MeshRenderer;
+use crate::msdf::MsdfRenderer;
+use crate::normal_map::{ NormalMapRenderer, NORMAL_FORMAT };
+use crate::post::PostProcessor;
+use crate::profiler::{ glyph_count, Mark, PassTimestamps, Profiler };
+use crate::render_graph::{ RenderGraph, TexturePool, TransientTexture };
+use crate::text_effects::{ EffectParams, TextEffects };
 use crate::types::*;
 
+/// Shaders whose pipelines are rebuilt together on reload, by group. The fullscreen vertex shader
+/// is shared by the post-processing effects and the lighting pass.
+#[cfg(feature = "hot-reload")]
+const SHADER_GROUPS: [(&str, &[&str]); 2] = [
+    (
+        "post",
+        &[
+            "fullscreen",
+            "blur",
+            "bloom_extract",
+            "bloom_composite",
+            "crt",
+            "vignette",
+            "color_grade",
+        ],
+    ),
+    ("lighting", &["fullscreen", "light", "shadow_map"]),
+];
+
 pub struct Renderer {
     surface: wgpu::Surface<'static>,
     config: wgpu::SurfaceConfiguration,
@@ -16,12 +51,38 @@ pub struct Renderer {
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     glyph_brush: wgpu_glyph::GlyphBrush<()>,
+    text_effects: TextEffects,
+    msdf: MsdfRenderer,
+    bitmap_fonts: BitmapFontRenderer,
+    instancer: ShapeInstancer,
+    meshes: MeshRenderer,
+    lighting: Lighting,
+    normal_maps: NormalMapRenderer,
+    compute: ComputeRunner,
+    post: PostProcessor,
+    profiler: Profiler,
+    texture_pool: TexturePool,
     staging_belt: wgpu::util::StagingBelt,
     queued_vertices: Vec<Vertex>,
     queued_indices: Vec<u32>,
+    primitives: Vec<Primitive>,
+    // Glyphs queued on `glyph_brush` this frame
+    queued_glyphs: u32,
+    materials: Vec<Material>,
+    material: Option<MaterialId>,
+    layer: i32,
+    casting_shadows: bool,
+    // Submitted since the last frame, replayed when it is recorded
+    draw_lists: Vec<DrawList>,
+    camera: Option<Camera2D>,
     // Passed into shaders
-    screen_size_buffer: Buffer,
+    globals_buffer: Buffer,
+    globals_layout: BindGroupLayout,
     bind_group: BindGroup,
+    pipeline_cache: Option<wgpu::PipelineCache>,
+    pipeline_cache_path: Option<PathBuf>,
+    #[cfg(feature = "hot-reload")]
+    shader_watcher: Option<crate::hot_reload::ShaderWatcher>,
 }
 
 impl Renderer {
@@ -33,7 +94,46 @@ impl Renderer {
         self.config.height as f32
     }
 
+    /// The device, for creating the pipelines and resources of custom passes.
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Format of the surface and of the view custom passes draw into.
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
     pub async fn new(window: Arc<Window>, size: PhysicalSize<u32>) -> Renderer {
+        Self::create(window, size, None).await
+    }
+
+    /// Creates a renderer whose pipelines go through a driver pipeline cache kept in
+    /// `cache_directory`. The cache is loaded here and written back when the renderer is dropped
+    /// or `save_pipeline_cache` is called, which makes later cold starts faster. Adapters without
+    /// cache support (everything but Vulkan) render without one.
+    ///
+    /// `cache_directory` is a directory, not a file, and is created on the first save. The cache
+    /// file inside it is named after `wgpu::util::pipeline_cache_key` for the adapter, since cache
+    /// data only works with the GPU and driver that wrote it, so several adapters can share the
+    /// directory. `pipeline_cache_path` returns the file that was chosen.
+    pub async fn with_pipeline_cache(
+        window: Arc<Window>,
+        size: PhysicalSize<u32>,
+        cache_directory: impl AsRef<Path>
+    ) -> Renderer {
+        Self::create(window, size, Some(cache_directory.as_ref())).await
+    }
+
+    async fn create(
+        window: Arc<Window>,
+        size: PhysicalSize<u32>,
+        cache_directory: Option<&Path>
+    ) -> Renderer {
         log::warn!("size: {:?}", size);
 
         // Create core wgpu components
@@ -47,16 +147,20 @@ impl Renderer {
         let (device, queue) = create_device_and_queue(&adapter).await;
 
         let config = create_surface_config(&surface, &adapter, size);
+        let (pipeline_cache, pipeline_cache_path) = cache_directory
+            .and_then(|directory| create_pipeline_cache(&device, &adapter, directory))
+            .unzip();
+        let cache = pipeline_cache.as_ref();
 
         let bind_group_layout = create_bind_group_layout(&device);
         let pipeline_layout = create_pipeline_layout(&device, &bind_group_layout);
 
         let (vert_shader, frag_shader) = create_shader_modules(&device);
 
-        let screen_size_buffer = create_screen_size_buffer(&device, size);
+        let globals_buffer = create_globals_buffer(&device, size);
         let (vertex_buffer, index_buffer) = create_vertex_and_index_buffers(&device);
 
-        let bind_group = create_bind_group(&device, &bind_group_layout, &screen_size_buffer);
+        let bind_group = create_bind_group(&device, &bind_group_layout, &globals_buffer);
 
         let pipeline = create_render_pipeline(
             &device,
@@ -64,10 +168,30 @@ impl Renderer {
             config.format,
             &[Vertex::DESC],
             vert_shader,
-            frag_shader
+            frag_shader,
+            cache
         );
 
         let glyph_brush = create_glyph_brush(&device, config.format);
+        let text_effects = TextEffects::new(
+            &device,
+            &config,
+            &bind_group_layout,
+            create_glyph_brush(&device, config.format),
+            cache
+        );
+        let msdf = MsdfRenderer::new(&device, &bind_group_layout, config.format, cache);
+        let bitmap_fonts = BitmapFontRenderer::new(
+            &device,
+            &bind_group_layout,
+            config.format,
+            cache
+        );
+        let instancer = ShapeInstancer::new(&device, &bind_group_layout, config.format, cache);
+        let meshes = MeshRenderer::new(&device, &bind_group_layout, config.format, cache);
+        let lighting = Lighting::new(&device, &config, cache);
+        let normal_maps = NormalMapRenderer::new(&device, &bind_group_layout, cache);
+        let post = PostProcessor::new(&device, &config, cache);
         let staging_belt = wgpu::util::StagingBelt::new(1024);
 
         surface.configure(&device, &config);
@@ -81,11 +205,35 @@ impl Renderer {
             vertex_buffer,
             index_buffer,
             glyph_brush,
+            text_effects,
+            msdf,
+            bitmap_fonts,
+            instancer,
+            meshes,
+            lighting,
+            normal_maps,
+            compute: ComputeRunner::new(),
+            post,
+            profiler: Profiler::new(),
+            texture_pool: TexturePool::default(),
             staging_belt,
             queued_vertices: Vec::new(),
             queued_indices: Vec::new(),
-            screen_size_buffer,
+            primitives: Vec::new(),
+            queued_glyphs: 0,
+            materials: Vec::new(),
+            material: None,
+            layer: 0,
+            casting_shadows: false,
+            draw_lists: Vec::new(),
+            camera: None,
+            globals_buffer,
+            globals_layout: bind_group_layout,
             bind_group,
+            pipeline_cache,
+            pipeline_cache_path,
+            #[cfg(feature = "hot-reload")]
+            shader_watcher: None,
         }
     }
 
@@ -95,55 +243,965 @@ impl Renderer {
         self.config.width = size.width.min(max_texture_size);
         self.config.height = size.height.min(max_texture_size);
 
+        let screen_size = [size.width as f32, size.height as f32];
         self.queue.write_buffer(
-            &self.screen_size_buffer,
-            0,
-            bytemuck::cast_slice(&[size.width as f32, size.height as f32])
+            &self.globals_buffer,
+            Globals::SCREEN_SIZE_OFFSET,
+            bytemuck::cast_slice(&screen_size)
         );
+        self.profiler.counters().upload(std::mem::size_of_val(&screen_size));
         self.surface.configure(&self.device, &self.config);
+        // The view matrix centers the camera on screen
+        self.set_camera(self.camera);
+        self.text_effects.resize(
+            &self.device,
+            self.config.format,
+            self.config.width,
+            self.config.height
+        );
+        self.post.resize(self.config.width, self.config.height);
+    }
+
+    /// Sets the camera used by world-space rendering. `None` maps world space 1:1 to screen
+    /// pixels.
+    pub fn set_camera(&mut self, camera: Option<Camera2D>) {
+        self.camera = camera;
+
+        let view = match camera {
+            Some(camera) => camera.view_matrix(self.width(), self.height()),
+            None => glam::Mat4::IDENTITY,
+        };
+        self.queue.write_buffer(
+            &self.globals_buffer,
+            Globals::VIEW_OFFSET,
+            bytemuck::cast_slice(&view.to_cols_array())
+        );
+        self.profiler.counters().upload(std::mem::size_of::<glam::Mat4>());
+    }
+
+    pub fn camera(&self) -> Option<Camera2D> {
+        self.camera
+    }
+
+    /// The file the pipeline cache is loaded from and saved to, inside the directory given to
+    /// `with_pipeline_cache`. `None` if the renderer has no cache.
+    pub fn pipeline_cache_path(&self) -> Option<&Path> {
+        self.pipeline_cache_path.as_deref()
+    }
+
+    /// Writes the pipeline cache to its file now instead of when the renderer is dropped. Does
+    /// nothing for renderers without a cache.
+    pub fn save_pipeline_cache(&self) -> Result<(), RenderError> {
+        let (Some(cache), Some(path)) = (&self.pipeline_cache, &self.pipeline_cache_path) else {
+            return Ok(());
+        };
+
+        save_pipeline_cache(cache, path).map_err(|e| {
+            RenderError::ResourceError(
+                format!("Failed to save pipeline cache to {}: {}", path.display(), e)
+            )
+        })
+    }
+
+    /// Starts watching the GLSL sources in `res/shaders` so `poll_shader_changes` can rebuild the
+    /// built-in pipelines while the application runs.
+    #[cfg(feature = "hot-reload")]
+    pub fn watch_shaders(&mut self) {
+        self.shader_watcher = Some(
+            crate::hot_reload::ShaderWatcher::new(crate::hot_reload::SHADER_DIRECTORY)
+        );
+    }
+
+    /// Recompiles shaders whose sources changed since the last call and rebuilds their pipelines.
+    /// Returns whether any pipeline was rebuilt. Shaders that fail to compile keep their previous
+    /// pipeline and are reported as a `RenderError::ShaderError` with diagnostics.
+    #[cfg(feature = "hot-reload")]
+    pub fn poll_shader_changes(&mut self) -> Result<bool, RenderError> {
+        let Some(watcher) = &mut self.shader_watcher else {
+            return Ok(false);
+        };
+        let directory = watcher.directory().to_path_buf();
+        let mut changed = std::collections::BTreeSet::new();
+        for name in watcher.changed_shaders() {
+            // Shaders rebuilt as a group are reloaded once however many of them changed
+            let groups: Vec<_> = SHADER_GROUPS.iter()
+                .filter(|(_, members)| members.contains(&name.as_str()))
+                .map(|(group, _)| group.to_string())
+                .collect();
+            if groups.is_empty() {
+                changed.insert(name);
+            } else {
+                changed.extend(groups);
+            }
+        }
+
+        let mut rebuilt = false;
+        let mut errors = Vec::new();
+        for name in changed {
+            match self.reload_shader(&directory, &name) {
+                Ok(true) => {
+                    log::info!("Reloaded {} shaders", name);
+                    rebuilt = true;
+                }
+                Ok(false) => {}
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(rebuilt)
+        } else {
+            Err(RenderError::ShaderError(errors.join("\n")))
+        }
+    }
+
+    #[cfg(feature = "hot-reload")]
+    fn reload_shader(&mut self, directory: &Path, name: &str) -> Result<bool, RenderError> {
+        use crate::hot_reload::load_shader_pair;
+        use crate::init::validated;
+
+        let format = self.config.format;
+        match name {
+            "post" => {
+                self.post.reload_shaders(&self.device, directory, self.pipeline_cache.as_ref())?;
+                return Ok(true);
+            }
+            "lighting" => {
+                self.lighting.reload_shaders(
+                    &self.device,
+                    directory,
+                    format,
+                    self.pipeline_cache.as_ref()
+                )?;
+                return Ok(true);
+            }
+            "textured" |
+            "shape" |
+            "mesh" |
+            "text_effect" |
+            "msdf" |
+            "bitmap_text" |
+            "normal_map" => {}
+            _ => {
+                log::warn!("{} shaders changed but no built-in pipeline uses them", name);
+                return Ok(false);
+            }
+        }
+
+        let (vert_shader, frag_shader) = load_shader_pair(&self.device, directory, name)?;
+        match name {
+            "textured" => {
+                self.pipeline = validated(&self.device, || {
+                    create_render_pipeline(
+                        &self.device,
+                        &create_pipeline_layout(&self.device, &self.globals_layout),
+                        format,
+                        &[Vertex::DESC],
+                        vert_shader,
+                        frag_shader,
+                        self.pipeline_cache.as_ref()
+                    )
+                })?;
+            }
+            "shape" => {
+                self.instancer.reload_shaders(
+                    &self.device,
+                    &self.globals_layout,
+                    format,
+                    vert_shader,
+                    frag_shader,
+                    self.pipeline_cache.as_ref()
+                )?;
+            }
+            "mesh" => {
+                self.meshes.reload_shaders(
+                    &self.device,
+                    &self.globals_layout,
+                    format,
+                    vert_shader,
+                    frag_shader,
+                    self.pipeline_cache.as_ref()
+                )?;
+            }
+            "text_effect" => {
+                let path = directory.join("text_effect.frag");
+                let variant = |name| {
+                    crate::hot_reload::load_shader_variant(&self.device, &path, name)
+                };
+                let shaders = crate::text_effects::EffectShaders {
+                    vert: vert_shader,
+                    shadow: frag_shader,
+                    shadow_horizontal: variant("horizontal")?,
+                    outline: variant("outline")?,
+                    outline_horizontal: variant("outline_horizontal")?,
+                };
+                self.text_effects.reload_shaders(
+                    &self.device,
+                    &self.globals_layout,
+                    format,
+                    &shaders,
+                    self.pipeline_cache.as_ref()
+                )?;
+            }
+            "msdf" => {
+                self.msdf.reload_shaders(
+                    &self.device,
+                    &self.globals_layout,
+                    format,
+                    &vert_shader,
+                    &frag_shader,
+                    self.pipeline_cache.as_ref()
+                )?;
+            }
+            "normal_map" => {
+                self.normal_maps.reload_shaders(
+                    &self.device,
+                    &self.globals_layout,
+                    &vert_shader,
+                    &frag_shader,
+                    self.pipeline_cache.as_ref()
+                )?;
+            }
+            _ => {
+                self.bitmap_fonts.reload_shaders(
+                    &self.device,
+                    &self.globals_layout,
+                    format,
+                    &vert_shader,
+                    &frag_shader,
+                    self.pipeline_cache.as_ref()
+                )?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Loads a TrueType/OpenType font so it can be selected through `TextStyle::font`.
+    pub fn load_font(&mut self, bytes: Vec<u8>) -> Result<FontId, RenderError> {
+        let font = ab_glyph::FontArc
+            ::try_from_vec(bytes)
+            .map_err(|e| RenderError::ResourceError(format!("Invalid font data: {}", e)))?;
+
+        self.text_effects.add_font(font.clone());
+        Ok(FontId(self.glyph_brush.add_font(font).0))
+    }
+
+    /// Loads an AngelCode BMFont from a text or XML `.fnt` descriptor and its page images (PNG or
+    /// any other format the `image` crate reads), given in page id order.
+    ///
+    /// Bitmap fonts work with `queue_text`, `queue_styled_text`, `queue_text_object`,
+    /// `queue_clipped_text` and the measurement functions. They are drawn with nearest-neighbor
+    /// sampling and without outline or shadow effects. Rich text and MSDF text need a TrueType
+    /// font.
+    pub fn load_bitmap_font(
+        &mut self,
+        descriptor: &str,
+        pages: &[&[u8]]
+    ) -> Result<FontId, RenderError> {
+        let descriptor = FontDescriptor::parse(descriptor)?;
+        self.add_bitmap_font(descriptor, pages)
+    }
+
+    /// Loads a BMFont `.fnt` file, reading its page images from paths relative to the file.
+    pub fn load_bitmap_font_file(&mut self, path: impl AsRef<Path>) -> Result<FontId, RenderError> {
+        let path = path.as_ref();
+        let read_error = |path: &Path, e: std::io::Error| {
+            RenderError::ResourceError(format!("Failed to read {}: {}", path.display(), e))
+        };
+
+        let source = std::fs::read_to_string(path).map_err(|e| read_error(path, e))?;
+        let descriptor = FontDescriptor::parse(&source)?;
+
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let pages = descriptor.pages
+            .iter()
+            .map(|file| {
+                let page_path = directory.join(file);
+                std::fs::read(&page_path).map_err(|e| read_error(&page_path, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let pages: Vec<&[u8]> = pages.iter().map(Vec::as_slice).collect();
+
+        self.add_bitmap_font(descriptor, &pages)
+    }
+
+    fn add_bitmap_font(
+        &mut self,
+        descriptor: FontDescriptor,
+        pages: &[&[u8]]
+    ) -> Result<FontId, RenderError> {
+        let id = FontId(self.glyph_brush.fonts().len());
+        self.bitmap_fonts.add_font(&self.device, &self.queue, id, descriptor, pages)?;
+
+        // Bitmap fonts are laid out separately, but take a slot in the glyph brushes so every
+        // font shares one id space. Paths that can only lay out through the glyph brush refuse
+        // them instead of drawing the placeholder.
+        let placeholder = self.glyph_brush.fonts()[0].clone();
+        self.text_effects.add_font(placeholder.clone());
+        self.glyph_brush.add_font(placeholder);
+
+        Ok(id)
     }
 
     pub fn queue_text(&mut self, text: &str, position: (f32, f32), size: f32, color: [f32; 4]) {
+        self.queue_styled_text(text, position, &TextStyle::new(size, color))
+    }
+
+    pub fn queue_styled_text(&mut self, text: &str, position: (f32, f32), style: &TextStyle) {
+        if self.bitmap_fonts.font(style.font).is_some() {
+            self.bitmap_fonts.queue(
+                &(BitmapSection {
+                    text,
+                    font: style.font,
+                    position,
+                    size: style.size,
+                    color: style.color,
+                    h_align: HorizontalAlign::Left,
+                    v_align: VerticalAlign::Top,
+                    max_width: self.width(),
+                    clip: None,
+                })
+            );
+            return;
+        }
+
         let section = (Section {
             screen_position: position,
             bounds: (self.config.width as f32, self.config.height as f32),
             layout: wgpu_glyph::Layout::default().h_align(wgpu_glyph::HorizontalAlign::Left),
             ..Section::default()
-        }).add_text(Text::new(text).with_color(color).with_scale(size));
+        }).add_text(
+            wgpu_glyph::Text::new(text)
+                .with_color(style.color)
+                .with_scale(style.size)
+                .with_font_id(wgpu_glyph::FontId(style.font.0))
+        );
 
+        self.queue_text_effects(&section, &[(style.shadow, style.outline)]);
+        self.queued_glyphs += section_glyph_count(&section);
         self.glyph_brush.queue(section);
     }
 
-    pub fn render_text(&mut self) -> Result<(), wgpu::SurfaceError> {
-        match self.surface.get_current_texture() {
-            Ok(frame) => {
-                let view = frame.texture.create_view(&Default::default());
-                let mut encoder = self.device.create_command_encoder(
-                    &(wgpu::CommandEncoderDescriptor { label: None })
-                );
+    /// Queues single-line text with every glyph clipped to `clip`, for scrolled content like
+    /// text fields. Outline and shadow effects are not drawn for clipped text.
+    pub fn queue_clipped_text(
+        &mut self,
+        text: &str,
+        position: (f32, f32),
+        style: &TextStyle,
+        clip: Rect
+    ) {
+        if self.bitmap_fonts.font(style.font).is_some() {
+            self.bitmap_fonts.queue(
+                &(BitmapSection {
+                    text,
+                    font: style.font,
+                    position,
+                    size: style.size,
+                    color: style.color,
+                    h_align: HorizontalAlign::Left,
+                    v_align: VerticalAlign::Top,
+                    max_width: UNBOUNDED_F32,
+                    clip: Some(clip),
+                })
+            );
+            return;
+        }
 
-                self.glyph_brush
-                    .draw_queued(
-                        &self.device,
-                        &mut self.staging_belt,
-                        &mut encoder,
-                        &view,
-                        self.config.width,
-                        self.config.height
-                    )
-                    .unwrap();
+        let section = (Section {
+            screen_position: position,
+            bounds: (UNBOUNDED_F32, UNBOUNDED_F32),
+            layout: wgpu_glyph::Layout::default_single_line(),
+            ..Section::default()
+        }).add_text(
+            wgpu_glyph::Text
+                ::new(text)
+                .with_color(style.color)
+                .with_scale(style.size)
+                .with_font_id(wgpu_glyph::FontId(style.font.0))
+        );
+
+        let glyphs = self.glyph_brush.glyphs(&section).cloned().collect();
+        let extra = section.text
+            .iter()
+            .map(|text| text.extra)
+            .collect();
+        let bounds = ab_glyph::Rect {
+            min: ab_glyph::point(clip.x, clip.y),
+            max: ab_glyph::point(clip.right(), clip.bottom()),
+        };
+
+        self.queued_glyphs += glyph_count(text);
+        self.glyph_brush.queue_pre_positioned(glyphs, extra, bounds);
+    }
+
+    /// Returns the height of one line of text, from the font's ascent to its descent.
+    pub fn line_height(&self, size: f32, font: FontId) -> f32 {
+        if let Some(bitmap_font) = self.bitmap_fonts.font(font) {
+            return bitmap_font.line_height(size);
+        }
+
+        let scaled = self.glyph_brush.fonts()[font.0].as_scaled(size);
+        scaled.ascent() - scaled.descent()
+    }
+
+    /// Queues a retained `Text` object, wrapping it inside its bounds and applying its alignment.
+    /// Invisible text is skipped.
+    pub fn queue_text_object(&mut self, text: &Text) {
+        if !text.visible || text.text.is_empty() {
+            return;
+        }
+
+        let (h_align, v_align) = text.alignment();
+        let screen_position = aligned_position(text.position, text.bounds, h_align, v_align);
+
+        if self.bitmap_fonts.font(text.font).is_some() {
+            self.bitmap_fonts.queue(
+                &(BitmapSection {
+                    text: &text.text,
+                    font: text.font,
+                    position: screen_position,
+                    size: text.size,
+                    color: text.color.to_array(),
+                    h_align,
+                    v_align,
+                    max_width: text.bounds.x,
+                    clip: None,
+                })
+            );
+            return;
+        }
+
+        let section = (Section {
+            screen_position,
+            bounds: (text.bounds.x, text.bounds.y),
+            layout: glyph_layout(h_align, v_align),
+            ..Section::default()
+        }).add_text(
+            wgpu_glyph::Text
+                ::new(&text.text)
+                .with_color(text.color.to_array())
+                .with_scale(text.size)
+                .with_font_id(wgpu_glyph::FontId(text.font.0))
+        );
+
+        self.queue_text_effects(&section, &[(text.shadow, text.outline)]);
+        self.queued_glyphs += section_glyph_count(&section);
+        self.glyph_brush.queue(section);
+    }
+
+    /// Queues a paragraph of styled spans, laid out and wrapped as a single section. Bitmap fonts
+    /// cannot be laid out together with other fonts, so spans using one are left out.
+    pub fn queue_rich_text(&mut self, text: &RichText) {
+        let spans = glyph_spans(text, |font| self.bitmap_fonts.font(font).is_some());
+        let section = rich_text_section(text, &spans);
+        let effects: Vec<_> = spans
+            .iter()
+            .map(|span| (span.style.shadow, span.style.outline))
+            .collect();
+
+        self.queue_text_effects(&section, &effects);
+        self.queued_glyphs += section_glyph_count(&section);
+        self.glyph_brush.queue(section);
+    }
+
+    /// Queues distance-field text, which can be scaled, rotated and moved with the camera
+    /// without losing sharpness. Bitmap fonts have no outlines to generate distance fields from.
+    pub fn queue_msdf_text(&mut self, text: &MsdfText) {
+        if self.bitmap_fonts.font(text.font).is_some() {
+            log::warn!("MSDF text cannot use bitmap font {:?}", text.font);
+            return;
+        }
+        self.msdf.queue(self.glyph_brush.fonts(), text);
+    }
+
+    /// Generates the distance fields of every character in `characters` now, for example at
+    /// load time. Distance-field glyphs are otherwise generated when first drawn, a few per
+    /// frame, and left out of the frames before theirs is ready.
+    pub fn preload_msdf_glyphs(&mut self, font: FontId, characters: &str) {
+        if self.bitmap_fonts.font(font).is_some() {
+            log::warn!("MSDF text cannot use bitmap font {:?}", font);
+            return;
+        }
+        let counters = self.profiler.counters();
+        self.msdf.preload(&self.queue, self.glyph_brush.fonts(), font.0, characters, counters);
+    }
+
+    /// Queues the shadow and outline passes of a section. `effects` holds the shadow and
+    /// outline of each text run in the section.
+    fn queue_text_effects(
+        &mut self,
+        section: &Section,
+        effects: &[(Option<TextShadow>, Option<TextOutline>)]
+    ) {
+        // Each distinct effect gets its own copy of the section, where runs without that effect
+        // are transparent so the layout stays identical
+        let mut shadows: Vec<TextShadow> = Vec::new();
+        let mut outlines: Vec<TextOutline> = Vec::new();
+        for (shadow, outline) in effects {
+            if let Some(shadow) = shadow.filter(|s| !shadows.contains(s)) {
+                shadows.push(shadow);
+            }
+            if let Some(outline) = outline.filter(|o| !outlines.contains(o)) {
+                outlines.push(outline);
+            }
+        }
+
+        for shadow in shadows {
+            let mut effect_section = section.clone();
+            effect_section.screen_position.0 += shadow.offset.0;
+            effect_section.screen_position.1 += shadow.offset.1;
+            for (text, (run_shadow, _)) in effect_section.text.iter_mut().zip(effects) {
+                text.extra.color = match run_shadow {
+                    Some(s) if *s == shadow => shadow.color,
+                    _ => [0.0; 4],
+                };
+            }
+            self.text_effects.queue(EffectParams::shadow(shadow.blur), effect_section);
+        }
+
+        for outline in outlines {
+            let mut effect_section = section.clone();
+            for (text, (_, run_outline)) in effect_section.text.iter_mut().zip(effects) {
+                text.extra.color = match run_outline {
+                    Some(o) if *o == outline => outline.color,
+                    _ => [0.0; 4],
+                };
+            }
+            self.text_effects.queue(
+                EffectParams::outline(outline.thickness, outline.blur),
+                effect_section
+            );
+        }
+    }
+
+    /// Returns the screen-space layout bounds of a rich text paragraph, without the spans
+    /// `queue_rich_text` leaves out.
+    pub fn measure_rich_text(&mut self, text: &RichText) -> Rect {
+        let fonts = self.glyph_brush.fonts().to_vec();
+        let spans = glyph_spans(text, |font| self.bitmap_fonts.font(font).is_some());
+        let section = rich_text_section(text, &spans);
+        layout_bounds(&layout_glyph_positions(&fonts, self.glyph_brush.glyphs(section)))
+    }
+
+    /// Returns the layout bounds of `text` when laid out at the origin and wrapped at `max_width`.
+    /// Pass `UNBOUNDED_F32` as `max_width` to disable wrapping.
+    ///
+    /// The bounds cover the advance of every glyph, whitespace included, and the full line height
+    /// of every line, so they match the boxes of `glyph_positions` rather than the inked pixels.
+    pub fn measure_text(&mut self, text: &str, size: f32, font: FontId, max_width: f32) -> Rect {
+        if let Some(bitmap_font) = self.bitmap_fonts.font(font) {
+            let lines = bitmap_font.layout(text, size, max_width);
+            if lines.iter().all(|line| line.glyphs.is_empty()) {
+                return Rect::default();
+            }
+
+            let width = lines
+                .iter()
+                .map(|line| line.width)
+                .fold(0.0, f32::max);
+            return Rect::new(0.0, 0.0, width, bitmap_font.line_height(size) * (lines.len() as f32));
+        }
+
+        layout_bounds(&self.glyph_positions(text, size, font, max_width))
+    }
+
+    /// Returns the layout box of every glyph in `text`, laid out the same way as `measure_text`.
+    pub fn glyph_positions(
+        &mut self,
+        text: &str,
+        size: f32,
+        font: FontId,
+        max_width: f32
+    ) -> Vec<GlyphPosition> {
+        if let Some(bitmap_font) = self.bitmap_fonts.font(font) {
+            let line_height = bitmap_font.line_height(size);
+
+            return bitmap_font
+                .layout(text, size, max_width)
+                .iter()
+                .enumerate()
+                .flat_map(|(line_index, line)| {
+                    line.glyphs.iter().map(move |glyph| GlyphPosition {
+                        byte_index: glyph.byte_index,
+                        rect: Rect::new(
+                            glyph.x,
+                            line_height * (line_index as f32),
+                            glyph.advance,
+                            line_height
+                        ),
+                    })
+                })
+                .collect();
+        }
+
+        let section = layout_section(text, size, font, max_width);
+        let fonts = self.glyph_brush.fonts().to_vec();
+
+        layout_glyph_positions(&fonts, self.glyph_brush.glyphs(section))
+    }
 
-                self.staging_belt.finish();
-                self.queue.submit(iter::once(encoder.finish()));
-                frame.present();
-                Ok(())
+    /// Returns the byte index of the caret position closest to `point`, where `point` is
+    /// relative to the text origin. Useful for placing a caret from a mouse click.
+    pub fn hit_test_text(
+        &mut self,
+        text: &str,
+        size: f32,
+        font: FontId,
+        max_width: f32,
+        point: (f32, f32)
+    ) -> usize {
+        let glyphs = self.glyph_positions(text, size, font, max_width);
+        let (x, y) = point;
+
+        // Pick the line whose vertical center is closest to the point
+        let line_distance = |g: &GlyphPosition| (y - (g.rect.y + g.rect.height * 0.5)).abs();
+        let Some(line_top) = glyphs
+            .iter()
+            .min_by(|a, b| line_distance(a).total_cmp(&line_distance(b)))
+            .map(|g| g.rect.y) else {
+            return 0;
+        };
+
+        let mut caret = None;
+        for glyph in glyphs.iter().filter(|g| g.rect.y == line_top) {
+            let char_len = text[glyph.byte_index..].chars().next().map_or(0, char::len_utf8);
+            if x < glyph.rect.x + glyph.rect.width * 0.5 {
+                return caret.unwrap_or(glyph.byte_index);
             }
-            Err(e) => Err(e),
+            caret = Some(glyph.byte_index + char_len);
+        }
+
+        caret.unwrap_or(0)
+    }
+
+    /// Draws queued distance-field text, text effects, bitmap font text and then the queued text
+    /// itself on top of `view`.
+    fn draw_queued_text(&mut self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let counters = self.profiler.counters();
+        self.msdf.prepare(&self.device, &self.queue, self.glyph_brush.fonts(), counters);
+        self.msdf.draw(encoder, view, &self.bind_group, counters);
+
+        self.text_effects.draw(
+            &self.device,
+            &self.queue,
+            &mut self.staging_belt,
+            encoder,
+            view,
+            &self.bind_group,
+            self.config.width,
+            self.config.height,
+            counters
+        );
+
+        self.bitmap_fonts.prepare(&self.device, &self.queue, counters);
+        self.bitmap_fonts.draw(encoder, view, &self.bind_group, counters);
+
+        self.glyph_brush
+            .draw_queued(
+                &self.device,
+                &mut self.staging_belt,
+                encoder,
+                view,
+                self.config.width,
+                self.config.height
+            )
+            .unwrap();
+        counters.glyph_draw(std::mem::take(&mut self.queued_glyphs));
+    }
+
+    /// Creates a material from a WGSL module with one vertex and one fragment entry point.
+    ///
+    /// The vertex stage receives the shape `Vertex` layout: `@location(0) position: vec2<f32>`
+    /// and `@location(1) color: vec4<f32>`, in screen pixels. Group 0, binding 0 holds the
+    /// globals `{ screen_size: vec2<f32>, view: mat4x4<f32> }`, and materials with uniforms get
+    /// their block at group 1, binding 0. Materials are alpha blended.
+    pub fn create_material(
+        &mut self,
+        wgsl_source: &str,
+        uniform_layout: UniformLayout
+    ) -> Result<MaterialId, RenderError> {
+        let material = Material::new(
+            &self.device,
+            &self.globals_layout,
+            self.config.format,
+            wgsl_source,
+            uniform_layout,
+            self.pipeline_cache.as_ref()
+        )?;
+
+        self.materials.push(material);
+        Ok(MaterialId(self.materials.len() - 1))
+    }
+
+    /// Writes the uniform block of `material`. `uniforms` must match the size of the material's
+    /// `UniformLayout`.
+    pub fn set_material_uniforms<T: bytemuck::Pod>(
+        &mut self,
+        material: MaterialId,
+        uniforms: &T
+    ) -> Result<(), RenderError> {
+        let bytes = bytemuck::bytes_of(uniforms);
+        let material = self.materials
+            .get(material.0)
+            .ok_or_else(|| RenderError::ResourceError(format!("Unknown material {:?}", material)))?;
+
+        MaterialUniforms::write(material.uniforms.as_ref(), &self.queue, material.layout, bytes)?;
+        self.profiler.counters().upload(bytes.len());
+        Ok(())
+    }
+
+    /// Sets the material used by shapes queued after this call. `None` switches back to the
+    /// built-in shape shader. Instanced shapes are always drawn with their own pipeline.
+    pub fn set_material(&mut self, material: Option<MaterialId>) {
+        self.material = material;
+    }
+
+    pub fn material(&self) -> Option<MaterialId> {
+        self.material
+    }
+
+    /// Sets the layer of shapes queued after this call. Higher layers are drawn on top of lower
+    /// ones. Within a layer, shapes are regrouped by material to save draw calls, but a shape is
+    /// never moved across an earlier one it overlaps.
+    pub fn set_layer(&mut self, layer: i32) {
+        self.layer = layer;
+    }
+
+    pub fn layer(&self) -> i32 {
+        self.layer
+    }
+
+    /// Adds a light that brightens shapes around `position`, in screen pixels. Its contribution
+    /// fades out to nothing at `radius`. `shadows` sets how occluders block it, pass
+    /// `Shadows::default()` for hard shadows. Up to 64 point lights are drawn.
+    pub fn add_point_light(
+        &mut self,
+        position: glam::Vec2,
+        color: [f32; 3],
+        radius: f32,
+        intensity: f32,
+        shadows: Shadows
+    ) -> LightId {
+        self.lighting.add_light(PointLight {
+            position,
+            color,
+            radius,
+            intensity,
+            shadows,
+        })
+    }
+
+    /// Returns a light for moving or recoloring it, or `None` if it was removed.
+    pub fn point_light_mut(&mut self, light: LightId) -> Option<&mut PointLight> {
+        self.lighting.light_mut(light)
+    }
+
+    pub fn remove_point_light(&mut self, light: LightId) -> Option<PointLight> {
+        self.lighting.remove_light(light)
+    }
+
+    /// While enabled, queued rectangles and circles also block light from point lights, casting
+    /// shadows as set by `PointLight::shadows`. Applies to the shapes queued after the call.
+    pub fn set_casting_shadows(&mut self, enabled: bool) {
+        self.casting_shadows = enabled;
+    }
+
+    /// Queues an invisible closed polygon, in screen pixels, that blocks light this frame.
+    pub fn queue_occluder(&mut self, polygon: &[glam::Vec2]) {
+        self.lighting.queue_occluder(polygon);
+    }
+
+    /// Loads a tangent-space normal map from image bytes: red, green and blue hold the normal's
+    /// x, y and z mapped from -1..1 to 0..1, with green pointing up, and alpha its coverage.
+    pub fn load_normal_map(&mut self, image_bytes: &[u8]) -> Result<NormalMapId, RenderError> {
+        self.normal_maps.load(&self.device, &self.queue, image_bytes)
+    }
+
+    /// Lights the sprite drawn at `x`, `y` with `width` and `height` this frame with a normal map
+    /// stretched over it, instead of as a flat surface. Normal maps go on the current layer but
+    /// are only ordered among themselves: a shape on a higher layer drawn over the sprite is lit
+    /// with its normals unless it is given a normal map too.
+    pub fn queue_normal_map(
+        &mut self,
+        normal_map: NormalMapId,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32
+    ) {
+        self.normal_maps.queue(normal_map, self.layer, Rect::new(x, y, width, height));
+    }
+
+    /// Sets the light every shape receives regardless of point lights. White, the default, leaves
+    /// shapes unlit unless a point light exists.
+    pub fn set_ambient_light(&mut self, color: [f32; 3]) {
+        self.lighting.set_ambient(color);
+    }
+
+    pub fn ambient_light(&self) -> [f32; 3] {
+        self.lighting.ambient()
+    }
+
+    /// Sets the full-screen effects run, in order, after shapes and text are drawn. An empty
+    /// chain draws straight to the frame.
+    pub fn set_post_effects(&mut self, effects: &[PostEffect]) {
+        self.post.set_effects(effects);
+    }
+
+    pub fn post_effects(&self) -> &[PostEffect] {
+        self.post.effects()
+    }
+
+    /// Loads a color grading lookup table for `PostEffect::ColorGrading`. The image is a strip of
+    /// N slices of N by N texels side by side, N squared pixels wide and N high, where the slice
+    /// is picked by blue, x by red and y by green.
+    pub fn load_color_lut(&mut self, image_bytes: &[u8]) -> Result<ColorLutId, RenderError> {
+        self.post.load_lut(&self.device, &self.queue, image_bytes)
+    }
+
+    /// Creates a post-processing shader for `PostEffect::Custom` from WGSL with a single fragment
+    /// entry point taking a `PostInput`.
+    ///
+    /// The source is appended to a prelude declaring `struct PostInput { position, uv }`, the
+    /// previous pass's output as `input_texture` with `input_sampler`, and `post`, whose
+    /// `texel_size` is one pixel in uv units. Shaders with uniforms get their block at group 1,
+    /// binding 0.
+    pub fn create_post_shader(
+        &mut self,
+        wgsl_source: &str,
+        uniform_layout: UniformLayout
+    ) -> Result<PostShaderId, RenderError> {
+        self.post.create_shader(
+            &self.device,
+            wgsl_source,
+            uniform_layout,
+            self.pipeline_cache.as_ref()
+        )
+    }
+
+    /// Writes the uniform block of a custom post shader. `uniforms` must match the size of its
+    /// `UniformLayout`.
+    pub fn set_post_shader_uniforms<T: bytemuck::Pod>(
+        &mut self,
+        shader: PostShaderId,
+        uniforms: &T
+    ) -> Result<(), RenderError> {
+        let bytes = bytemuck::bytes_of(uniforms);
+        self.post.set_shader_uniforms(&self.queue, shader, bytes)?;
+        self.profiler.counters().upload(bytes.len());
+        Ok(())
+    }
+
+    /// Creates a compute pipeline from a WGSL module with one compute entry point, such as the
+    /// `.comp.wgsl` that build.rs generates from a `.comp` shader. Its resources are bound at
+    /// group 0 through `dispatch_compute`.
+    pub fn create_compute_pipeline(
+        &mut self,
+        wgsl_source: &str
+    ) -> Result<ComputePipelineId, RenderError> {
+        self.compute.create_pipeline(&self.device, wgsl_source, self.pipeline_cache.as_ref())
+    }
+
+    /// Creates a zeroed buffer that compute shaders can bind as storage or uniform buffer. The
+    /// size is rounded up to a multiple of four bytes.
+    pub fn create_storage_buffer(&mut self, size: BufferAddress) -> StorageBufferId {
+        self.compute.create_buffer(&self.device, size)
+    }
+
+    /// Writes `data` into a storage buffer at a byte `offset`, both aligned to four bytes.
+    pub fn write_storage_buffer<T: bytemuck::Pod>(
+        &mut self,
+        buffer: StorageBufferId,
+        offset: BufferAddress,
+        data: &[T]
+    ) -> Result<(), RenderError> {
+        let bytes = bytemuck::cast_slice(data);
+        self.compute.write_buffer(&self.queue, buffer, offset, bytes)?;
+        self.profiler.counters().upload(bytes.len());
+        Ok(())
+    }
+
+    /// Creates a 2D texture that compute shaders can write as `texture_storage_2d` or read as
+    /// `texture_2d`. The format must support storage use, like `Rgba8Unorm` or `Rgba32Float`.
+    pub fn create_storage_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat
+    ) -> Result<StorageTextureId, RenderError> {
+        self.compute.create_texture(&self.device, width, height, format)
+    }
+
+    /// Replaces the contents of a storage texture with tightly packed rows of texels.
+    pub fn write_storage_texture(
+        &mut self,
+        texture: StorageTextureId,
+        data: &[u8]
+    ) -> Result<(), RenderError> {
+        self.compute.write_texture(&self.queue, texture, data)
+    }
+
+    /// Queues a dispatch of `workgroups` with `bindings` bound to group 0 in order. Queued
+    /// compute work runs at the start of the next `Frame::end_frame`, before shapes and text
+    /// are drawn, or when `submit_compute` is called.
+    pub fn dispatch_compute(
+        &mut self,
+        pipeline: ComputePipelineId,
+        bindings: &[ComputeBinding],
+        workgroups: [u32; 3]
+    ) -> Result<(), RenderError> {
+        self.compute.dispatch(&self.device, pipeline, bindings, workgroups)
+    }
+
+    /// Queues a copy of a storage buffer's contents back to the CPU, taken after the compute
+    /// work queued before it.
+    pub fn read_storage_buffer(&mut self, buffer: StorageBufferId) -> Result<Readback, RenderError> {
+        self.compute.read_buffer(&self.device, buffer)
+    }
+
+    /// Queues a copy of a storage texture's texels back to the CPU as tightly packed rows.
+    pub fn read_storage_texture(
+        &mut self,
+        texture: StorageTextureId
+    ) -> Result<Readback, RenderError> {
+        self.compute.read_texture(&self.device, texture)
+    }
+
+    /// Submits queued compute work and readbacks without drawing a frame.
+    pub fn submit_compute(&mut self) {
+        if !self.compute.has_work() {
+            return;
         }
+        let mut encoder = self.device.create_command_encoder(
+            &(wgpu::CommandEncoderDescriptor { label: Some("Compute Encoder") })
+        );
+        self.compute.encode(&mut encoder);
+        self.queue.submit(iter::once(encoder.finish()));
+        self.compute.after_submit();
+    }
+
+    /// Delivers readbacks whose copies finished. Ending a frame does this without waiting. With
+    /// `wait`, blocks until all submitted GPU work is done, so every submitted readback is ready
+    /// afterwards.
+    pub fn poll_readbacks(&mut self, wait: bool) {
+        self.compute.poll(&self.device, wait);
+    }
+
+    /// Records the indices queued since `first_index` as one shape with the current state.
+    fn push_primitive(&mut self, first_index: u32, bounds: Rect) {
+        self.primitives.push(Primitive {
+            layer: self.layer,
+            key: BatchKey { material: self.material, ..BatchKey::new(DrawKind::Shapes) },
+            items: first_index..self.queued_indices.len() as u32,
+            bounds,
+        });
     }
 
     pub fn queue_rectangle(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
         let vertex_offset = self.queued_vertices.len() as u32;
+        let first_index = self.queued_indices.len() as u32;
 
         self.queued_vertices.extend_from_slice(
             &[
@@ -164,6 +1222,12 @@ impl Renderer {
                 vertex_offset,
             ]
         );
+        self.push_primitive(first_index, Rect::new(x, y, width, height));
+
+        if self.casting_shadows {
+            let size = glam::vec2(width, height);
+            self.lighting.queue_rectangle_occluder(glam::vec2(x, y) + size * 0.5, size, 0.0);
+        }
     }
 
     pub fn queue_square(&mut self, x: f32, y: f32, size: f32, color: [f32; 4]) {
@@ -173,6 +1237,7 @@ impl Renderer {
     pub fn queue_circle(&mut self, center_x: f32, center_y: f32, radius: f32, color: [f32; 4]) {
         const SEGMENTS: usize = 32;
         let vertex_offset = self.queued_vertices.len() as u32;
+        let first_index = self.queued_indices.len() as u32;
 
         // Center vertex
         self.queued_vertices.push(Vertex::with_color(center_x, center_y, color));
@@ -190,18 +1255,320 @@ impl Renderer {
             self.queued_indices.push(vertex_offset + ((i + 1) as u32));
             self.queued_indices.push(vertex_offset);
         }
+        let diameter = radius * 2.0;
+        self.push_primitive(
+            first_index,
+            Rect::new(center_x - radius, center_y - radius, diameter, diameter)
+        );
+
+        if self.casting_shadows {
+            self.lighting.queue_circle_occluder(glam::vec2(center_x, center_y), radius);
+        }
     }
 
-    pub fn begin_frame(&mut self) -> Result<(), wgpu::SurfaceError> {
-        self.surface.get_current_texture()?;
-        Ok(())
+    /// Queues rectangles drawn through the instanced path: one shared quad mesh plus a small
+    /// per-instance record, which is far cheaper than `queue_rectangle` for thousands of shapes.
+    /// The instances go on the current layer and are ordered with the other shapes like one
+    /// queued shape. They are drawn with their own pipeline, so the current material does not
+    /// apply to them.
+    pub fn queue_rectangle_instances(&mut self, instances: &[ShapeInstance]) {
+        let Some(bounds) = instance_bounds(instances) else {
+            return;
+        };
+        let items = self.instancer.queue_rectangles(instances);
+        self.primitives.push(Primitive {
+            layer: self.layer,
+            key: BatchKey::new(DrawKind::Rectangles),
+            items,
+            bounds,
+        });
+
+        if self.casting_shadows {
+            for instance in instances {
+                self.lighting.queue_rectangle_occluder(
+                    instance.center.into(),
+                    instance.size.into(),
+                    instance.rotation
+                );
+            }
+        }
+    }
+
+    /// Queues circles drawn through the instanced path. See `queue_rectangle_instances`.
+    pub fn queue_circle_instances(&mut self, instances: &[ShapeInstance]) {
+        let Some(bounds) = instance_bounds(instances) else {
+            return;
+        };
+        let items = self.instancer.queue_circles(instances);
+        self.primitives.push(Primitive {
+            layer: self.layer,
+            key: BatchKey::new(DrawKind::Circles),
+            items,
+            bounds,
+        });
+
+        if self.casting_shadows {
+            for instance in instances {
+                let radius = instance.size[0].max(instance.size[1]) * 0.5;
+                self.lighting.queue_circle_occluder(instance.center.into(), radius);
+            }
+        }
+    }
+
+    /// Uploads a mesh to buffers of its own, once. Unlike queued shapes, it is not rebuilt every
+    /// frame: draw it with `draw_mesh` each frame it should appear. Indices are into `vertices`
+    /// and form triangle lists.
+    pub fn create_mesh(&mut self, vertices: &[Vertex], indices: &[u32]) -> MeshId {
+        self.meshes.create_mesh(&self.device, vertices, indices)
+    }
+
+    /// Draws a mesh this frame, moved by `transform` and with its vertex colors multiplied by
+    /// `tint`. The mesh goes on the current layer and is ordered with the other shapes like one
+    /// queued shape. It is drawn with its own pipeline, so the current material does not apply.
+    pub fn draw_mesh(&mut self, mesh: MeshId, transform: glam::Affine2, tint: [f32; 4]) {
+        let Some((items, bounds)) = self.meshes.queue_draw(mesh, transform, tint) else {
+            return;
+        };
+        self.primitives.push(Primitive {
+            layer: self.layer,
+            key: BatchKey { mesh: Some(mesh), ..BatchKey::new(DrawKind::Meshes) },
+            items,
+            bounds,
+        });
+    }
+
+    /// Frees a mesh's buffers. Returns whether the mesh existed.
+    pub fn remove_mesh(&mut self, mesh: MeshId) -> bool {
+        self.meshes.remove_mesh(mesh)
+    }
+
+    /// Turns GPU timing of the shape, text and post passes on or off, see `frame_stats`. Returns
+    /// `false` if the adapter does not support timestamp queries, in which case it stays off.
+    pub fn set_profiling(&mut self, enabled: bool) -> bool {
+        self.profiler.set_enabled(&self.device, &self.queue, enabled)
+    }
+
+    pub fn profiling(&self) -> bool {
+        self.profiler.is_enabled()
+    }
+
+    /// Returns the counts and CPU time of the last rendered frame. With profiling on, it also
+    /// holds the GPU times of the most recent frame whose timestamps have been read back.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.profiler.stats()
+    }
+
+    /// Submits a list recorded with `DrawList`, possibly on another thread, to be drawn in the
+    /// next frame. Lists are queued when the frame is recorded, sorted by their sort key, after
+    /// everything queued on the renderer directly.
+    pub fn submit(&mut self, draw_list: DrawList) {
+        if !draw_list.is_empty() {
+            self.draw_lists.push(draw_list);
+        }
+    }
+
+    /// Acquires the next surface texture and starts a frame. Shapes and text are queued on the
+    /// returned `Frame`, which draws and presents them in `Frame::end_frame` or when dropped.
+    pub fn begin_frame(&mut self) -> Result<Frame<'_>, wgpu::SurfaceError> {
+        let surface_texture = self.surface.get_current_texture()?;
+        let encoder = self.device.create_command_encoder(
+            &(wgpu::CommandEncoderDescriptor { label: Some("Frame Encoder") })
+        );
+        Ok(Frame::new(self, surface_texture, encoder))
+    }
+
+    /// Queues the submitted draw lists in sort key order, keeping the renderer's own state.
+    fn replay_draw_lists(&mut self) {
+        if self.draw_lists.is_empty() {
+            return;
+        }
+
+        let state = ListState {
+            material: self.material,
+            layer: self.layer,
+            casting_shadows: self.casting_shadows,
+        };
+        let draw_lists = std::mem::take(&mut self.draw_lists);
+        for command in replay_commands(draw_lists, state) {
+            command.apply(self);
+        }
+    }
+
+    /// Records everything queued for the frame, drawing into `frame_view`.
+    pub(crate) fn record_frame(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame_view: &wgpu::TextureView,
+        custom_passes: Vec<CustomPass<'_>>
+    ) {
+        self.profiler.begin_frame(&self.device);
+        self.replay_draw_lists();
+
+        // Handle buffer uploads
+        let (order, batches) = build_batches(&self.primitives);
+        let indices = sorted_items(
+            &self.primitives,
+            &order,
+            DrawKind::Shapes,
+            &self.queued_indices
+        );
+        if !self.queued_vertices.is_empty() {
+            grow_buffer(
+                &self.device,
+                &mut self.vertex_buffer,
+                None,
+                Vertex::SIZE * (self.queued_vertices.len() as BufferAddress)
+            );
+            grow_buffer(
+                &self.device,
+                &mut self.index_buffer,
+                None,
+                U32_SIZE * (indices.len() as BufferAddress)
+            );
+            let vertex_bytes: &[u8] = bytemuck::cast_slice(&self.queued_vertices);
+            let index_bytes: &[u8] = bytemuck::cast_slice(&indices);
+            self.queue.write_buffer(&self.vertex_buffer, 0, vertex_bytes);
+            self.queue.write_buffer(&self.index_buffer, 0, index_bytes);
+            self.profiler.counters().upload(vertex_bytes.len() + index_bytes.len());
+        }
+        self.instancer.prepare(
+            &self.device,
+            &self.queue,
+            &self.primitives,
+            &order,
+            self.profiler.counters()
+        );
+        self.meshes.prepare(
+            &self.device,
+            &self.queue,
+            &self.primitives,
+            &order,
+            self.profiler.counters()
+        );
+
+        let lighting = self.lighting.is_active();
+        if lighting {
+            self.normal_maps.prepare(&self.device, &self.queue, self.profiler.counters());
+        }
+        let post = self.post.is_active();
+        let target = |label| TransientTexture {
+            label,
+            size: (self.config.width, self.config.height),
+            format: self.config.format,
+        };
+
+        let mut graph: RenderGraph<Renderer> = RenderGraph::new();
+        let frame = graph.import_texture(frame_view.clone());
+        // With post effects the scene is drawn offscreen first
+        let scene = if post { graph.create_texture(target("Scene Target")) } else { frame };
+        // Lit shapes are drawn offscreen and lit into the scene before text
+        let shape_target = if lighting {
+            graph.create_texture(target("Lighting Scene Target"))
+        } else {
+            scene
+        };
+        let normals = lighting.then(|| {
+            graph.create_texture(TransientTexture {
+                format: NORMAL_FORMAT,
+                ..target("Normal Target")
+            })
+        });
+
+        // Compute work runs before anything is drawn
+        graph.add_pass("Compute", &[], &[], |renderer, encoder, _| {
+            renderer.compute.encode(encoder);
+        });
+        // Timestamps go on the passes that start and end each timed section. Shape time includes
+        // lighting, so its end is written by the lighting pass when there is one
+        let shapes_end = if lighting { None } else { Some(Mark::ShapesEnd) };
+        graph.add_pass("Shapes", &[], &[shape_target], move |renderer, encoder, textures| {
+            let timestamps = renderer.profiler.pass_timestamps(Some(Mark::FrameStart), shapes_end);
+            renderer.draw_shapes(encoder, textures.view(shape_target), &batches, timestamps);
+        });
+        if let Some(normals) = normals {
+            graph.add_pass("Normal Maps", &[], &[normals], move |renderer, encoder, textures| {
+                renderer.normal_maps.draw(
+                    encoder,
+                    textures.view(normals),
+                    &renderer.bind_group,
+                    renderer.profiler.counters()
+                );
+            });
+            graph.add_pass(
+                "Lighting",
+                &[shape_target, normals],
+                &[scene],
+                move |renderer, encoder, textures| {
+                    let timestamps = renderer.profiler.pass_timestamps(None, Some(Mark::ShapesEnd));
+                    renderer.lighting.apply(
+                        &renderer.device,
+                        &renderer.queue,
+                        encoder,
+                        textures.view(shape_target),
+                        textures.view(normals),
+                        textures.view(scene),
+                        timestamps,
+                        renderer.profiler.counters()
+                    );
+                }
+            );
+        }
+        graph.add_pass("Text", &[], &[scene], move |renderer, encoder, textures| {
+            renderer.draw_queued_text(encoder, textures.view(scene));
+        });
+        for pass in custom_passes {
+            graph.add_pass("Custom", &[], &[scene], move |_, encoder, textures| {
+                pass(encoder, textures.view(scene));
+            });
+        }
+        if post {
+            let ping = graph.create_texture(target("Post Target"));
+            let pong = graph.create_texture(target("Post Target"));
+            graph.add_pass(
+                "Post",
+                &[scene],
+                &[ping, pong, frame],
+                move |renderer, encoder, textures| {
+                    // Text ends where the first post pass starts
+                    let timestamps = renderer.profiler.pass_timestamps(
+                        Some(Mark::TextEnd),
+                        Some(Mark::PostEnd)
+                    );
+                    renderer.post.apply(
+                        &renderer.device,
+                        &renderer.queue,
+                        encoder,
+                        [textures.view(scene), textures.view(ping), textures.view(pong)],
+                        textures.view(frame),
+                        timestamps,
+                        renderer.profiler.counters()
+                    );
+                }
+            );
+        }
+        graph.set_final_pass("Profiler", move |renderer, encoder, _| {
+            // wgpu_glyph records the last text pass itself, so without post effects the end of
+            // text is written after it
+            if !post {
+                renderer.profiler.mark(encoder, Mark::TextEnd, Mark::PostEnd);
+            }
+            renderer.profiler.end_frame(encoder);
+        });
+
+        let device = self.device.clone();
+        let mut texture_pool = std::mem::take(&mut self.texture_pool);
+        graph.execute(&device, &mut texture_pool, encoder, self);
+        self.texture_pool = texture_pool;
     }
 
-    pub fn draw_shape(
+    /// Clears `view` and draws the batches of queued shapes, instanced shapes and meshes into it
+    /// in layer order.
+    fn draw_shapes(
         &mut self,
-        num_indices: u32,
         encoder: &mut wgpu::CommandEncoder,
-        view: &wgpu::TextureView
+        view: &wgpu::TextureView,
+        batches: &[Batch],
+        timestamps: Option<PassTimestamps>
     ) {
         let mut render_pass = encoder.begin_render_pass(
             &(wgpu::RenderPassDescriptor {
@@ -210,112 +1577,309 @@ impl Renderer {
                     Some(wgpu::RenderPassColorAttachment {
                         view,
                         resolve_target: None,
-                        ops: wgpu::Operations::default(),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color {
+                                r: 0.0,
+                                g: 0.0,
+                                b: 0.0,
+                                a: 1.0,
+                            }),
+                            store: wgpu::StoreOp::Store,
+                        },
                         depth_slice: None,
                     }),
                 ],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: timestamps
+                    .as_ref()
+                    .map(|timestamps| timestamps.writes(true, true)),
                 occlusion_query_set: None,
             })
         );
 
-        render_pass.set_pipeline(&self.pipeline);
-        render_pass.set_bind_group(0, &self.bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        render_pass.draw_indexed(0..num_indices, 0, 0..1);
+        let counters = self.profiler.counters();
+        for batch in batches {
+            let (kind, instances) = (batch.key.kind, batch.items.clone());
+            match kind {
+                DrawKind::Shapes => {}
+                DrawKind::Rectangles | DrawKind::Circles => {
+                    let globals = &self.bind_group;
+                    self.instancer.draw(&mut render_pass, globals, kind, instances, counters);
+                    continue;
+                }
+                DrawKind::Meshes => {
+                    if let Some(mesh) = batch.key.mesh {
+                        let globals = &self.bind_group;
+                        self.meshes.draw(&mut render_pass, globals, mesh, instances, counters);
+                    }
+                    continue;
+                }
+            }
+
+            // Instanced draws rebind the buffers, so every shape batch binds its own
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            match batch.key.material.and_then(|id| self.materials.get(id.0)) {
+                Some(material) => {
+                    render_pass.set_pipeline(&material.pipeline);
+                    if let Some(uniforms) = &material.uniforms {
+                        render_pass.set_bind_group(1, &uniforms.bind_group, &[]);
+                    }
+                }
+                None => render_pass.set_pipeline(&self.pipeline),
+            }
+            render_pass.draw_indexed(batch.items.clone(), 0, 0..1);
+            counters.draw(batch.items.len() as u32, 1);
+        }
     }
 
-    pub fn render_frame(&mut self) -> Result<(), wgpu::SurfaceError> {
-        match self.surface.get_current_texture() {
-            Ok(frame) => {
-                let view = frame.texture.create_view(&Default::default());
+    /// Submits a recorded frame, presents it and clears the queued data for the next one.
+    pub(crate) fn submit_frame(
+        &mut self,
+        encoder: wgpu::CommandEncoder,
+        surface_texture: wgpu::SurfaceTexture
+    ) {
+        self.staging_belt.finish();
+        self.queue.submit(iter::once(encoder.finish()));
+        surface_texture.present();
+        self.profiler.after_submit();
+        self.compute.after_submit();
+        self.compute.poll(&self.device, false);
 
-                // Handle buffer uploads
-                if !self.queued_vertices.is_empty() {
-                    self.queue.write_buffer(
-                        &self.vertex_buffer,
-                        0,
-                        bytemuck::cast_slice(&self.queued_vertices)
-                    );
-                    self.queue.write_buffer(
-                        &self.index_buffer,
-                        0,
-                        bytemuck::cast_slice(&self.queued_indices)
-                    );
-                }
+        // Clear queued data for next frame
+        self.queued_vertices.clear();
+        self.queued_indices.clear();
+        self.primitives.clear();
+        self.instancer.clear();
+        self.meshes.clear();
+        self.normal_maps.clear();
+        self.lighting.clear_occluders();
 
-                let mut encoder = self.device.create_command_encoder(
-                    &(wgpu::CommandEncoderDescriptor { label: None })
-                );
+        // Reclaim staging belt memory
+        // If we don't do this, we get a memory leak.
+        self.staging_belt.recall();
+    }
+}
 
-                // Create render pass with clear and render shapes
-                {
-                    let mut render_pass = encoder.begin_render_pass(
-                        &(wgpu::RenderPassDescriptor {
-                            label: Some("Shape Render Pass"),
-                            color_attachments: &[
-                                Some(wgpu::RenderPassColorAttachment {
-                                    view: &view,
-                                    resolve_target: None,
-                                    ops: wgpu::Operations {
-                                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                                            r: 0.0,
-                                            g: 0.0,
-                                            b: 0.0,
-                                            a: 1.0,
-                                        }),
-                                        store: wgpu::StoreOp::Store,
-                                    },
-                                    depth_slice: None,
-                                }),
-                            ],
-                            depth_stencil_attachment: None,
-                            timestamp_writes: None,
-                            occlusion_query_set: None,
-                        })
-                    );
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        if let Err(e) = self.save_pipeline_cache() {
+            log::warn!("{}", e);
+        }
+    }
+}
 
-                    if !self.queued_vertices.is_empty() {
-                        render_pass.set_pipeline(&self.pipeline);
-                        render_pass.set_bind_group(0, &self.bind_group, &[]);
-                        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-                        render_pass.set_index_buffer(
-                            self.index_buffer.slice(..),
-                            wgpu::IndexFormat::Uint32
-                        );
-                        render_pass.draw_indexed(0..self.queued_indices.len() as u32, 0, 0..1);
-                    }
-                }
+fn layout_section(text: &str, size: f32, font: FontId, max_width: f32) -> Section<'_> {
+    (Section {
+        screen_position: (0.0, 0.0),
+        bounds: (max_width, UNBOUNDED_F32),
+        layout: wgpu_glyph::Layout::default().h_align(wgpu_glyph::HorizontalAlign::Left),
+        ..Section::default()
+    }).add_text(
+        wgpu_glyph::Text::new(text).with_scale(size).with_font_id(wgpu_glyph::FontId(font.0))
+    )
+}
 
-                // Render text on top
-                self.glyph_brush
-                    .draw_queued(
-                        &self.device,
-                        &mut self.staging_belt,
-                        &mut encoder,
-                        &view,
-                        self.config.width,
-                        self.config.height
-                    )
-                    .unwrap();
+/// Returns the layout box of each laid out glyph: its advance wide and its line tall.
+fn layout_glyph_positions<'a>(
+    fonts: &[ab_glyph::FontArc],
+    glyphs: impl Iterator<Item = &'a wgpu_glyph::SectionGlyph>
+) -> Vec<GlyphPosition> {
+    glyphs
+        .map(|section_glyph| {
+            let glyph = &section_glyph.glyph;
+            let scaled = fonts[section_glyph.font_id.0].as_scaled(glyph.scale);
 
-                self.staging_belt.finish();
-                self.queue.submit(iter::once(encoder.finish()));
-                frame.present();
+            GlyphPosition {
+                byte_index: section_glyph.byte_index,
+                rect: Rect::new(
+                    glyph.position.x,
+                    glyph.position.y - scaled.ascent(),
+                    scaled.h_advance(glyph.id),
+                    scaled.ascent() - scaled.descent()
+                ),
+            }
+        })
+        .collect()
+}
 
-                // Clear queued data for next frame
-                self.queued_vertices.clear();
-                self.queued_indices.clear();
+/// Returns the union of the glyph boxes, or an empty rect at the origin without glyphs.
+fn layout_bounds(glyphs: &[GlyphPosition]) -> Rect {
+    let mut glyphs = glyphs.iter().map(|glyph| glyph.rect);
+    let Some(first) = glyphs.next() else {
+        return Rect::default();
+    };
+    glyphs.fold(first, |bounds, rect| bounds.union(&rect))
+}
 
-                // Reclaim staging belt memory
-                // If we don't do this, we get a memory leak.
-                self.staging_belt.recall();
+fn section_glyph_count(section: &Section) -> u32 {
+    section.text
+        .iter()
+        .map(|text| glyph_count(text.text))
+        .sum()
+}
 
-                Ok(())
+/// Returns the spans of `text` the glyph brush can lay out, warning about those in a bitmap font.
+fn glyph_spans(text: &RichText, is_bitmap: impl Fn(FontId) -> bool) -> Vec<&TextSpan> {
+    text.spans
+        .iter()
+        .filter(|span| {
+            let bitmap = is_bitmap(span.style.font);
+            if bitmap {
+                log::warn!("Rich text cannot use bitmap font {:?}, skipping span", span.style.font);
             }
-            Err(e) => Err(e),
-        }
+            !bitmap
+        })
+        .collect()
+}
+
+fn rich_text_section<'a>(text: &RichText, spans: &[&'a TextSpan]) -> Section<'a> {
+    let screen_position = aligned_position(text.position, text.bounds, text.h_align, text.v_align);
+
+    Section {
+        screen_position,
+        bounds: (text.bounds.x, text.bounds.y),
+        layout: glyph_layout(text.h_align, text.v_align),
+        text: spans
+            .iter()
+            .map(|span| {
+                wgpu_glyph::Text
+                    ::new(&span.text)
+                    .with_color(span.style.color)
+                    .with_scale(span.style.size)
+                    .with_font_id(wgpu_glyph::FontId(span.style.font.0))
+            })
+            .collect(),
+    }
+}
+
+/// Along each axis where the box is finite, the layout anchor moves with the alignment. Along an
+/// unbounded axis `position` is the anchor itself.
+fn aligned_position(
+    position: glam::Vec2,
+    bounds: glam::Vec2,
+    h_align: HorizontalAlign,
+    v_align: VerticalAlign
+) -> (f32, f32) {
+    let x = match h_align {
+        _ if !bounds.x.is_finite() => position.x,
+        HorizontalAlign::Left => position.x,
+        HorizontalAlign::Center => position.x + bounds.x * 0.5,
+        HorizontalAlign::Right => position.x + bounds.x,
+    };
+    let y = match v_align {
+        _ if !bounds.y.is_finite() => position.y,
+        VerticalAlign::Top => position.y,
+        VerticalAlign::Middle => position.y + bounds.y * 0.5,
+        VerticalAlign::Bottom => position.y + bounds.y,
+    };
+
+    (x, y)
+}
+
+fn glyph_layout(
+    h_align: HorizontalAlign,
+    v_align: VerticalAlign
+) -> wgpu_glyph::Layout<wgpu_glyph::BuiltInLineBreaker> {
+    let h_align = match h_align {
+        HorizontalAlign::Left => wgpu_glyph::HorizontalAlign::Left,
+        HorizontalAlign::Center => wgpu_glyph::HorizontalAlign::Center,
+        HorizontalAlign::Right => wgpu_glyph::HorizontalAlign::Right,
+    };
+    let v_align = match v_align {
+        VerticalAlign::Top => wgpu_glyph::VerticalAlign::Top,
+        VerticalAlign::Middle => wgpu_glyph::VerticalAlign::Center,
+        VerticalAlign::Bottom => wgpu_glyph::VerticalAlign::Bottom,
+    };
+
+    wgpu_glyph::Layout::default_wrap().h_align(h_align).v_align(v_align)
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu_glyph::{ ab_glyph::FontArc, GlyphPositioner, SectionGeometry };
+
+    use super::*;
+
+    const FONT: &[u8] = include_bytes!("../res/fonts/PressStart2P-Regular.ttf");
+
+    fn measure(text: &str) -> Rect {
+        let fonts = [FontArc::try_from_slice(FONT).unwrap()];
+        let section = layout_section(text, 16.0, FontId::default(), UNBOUNDED_F32);
+        let glyphs = section.layout.calculate_glyphs(
+            &fonts,
+            &SectionGeometry::from(&section),
+            &section.text
+        );
+        layout_bounds(&layout_glyph_positions(&fonts, glyphs.iter()))
+    }
+
+    #[test]
+    fn measure_counts_whitespace_advances() {
+        let word = measure("ab");
+        let padded = measure("ab  ");
+        let indented = measure("  ab");
+
+        // Press Start 2P is monospaced, so each space adds one glyph advance
+        let advance = word.width / 2.0;
+        assert!((padded.width - (word.width + advance * 2.0)).abs() < 0.01);
+        assert!((indented.width - padded.width).abs() < 0.01);
+        assert_eq!(indented.x, 0.0);
+    }
+
+    #[test]
+    fn alignment_applies_per_finite_axis() {
+        let position = glam::vec2(10.0, 20.0);
+        let wrapped = glam::vec2(200.0, UNBOUNDED_F32);
+        let (center, right) = (HorizontalAlign::Center, HorizontalAlign::Right);
+
+        let centered = aligned_position(position, wrapped, center, VerticalAlign::Bottom);
+        assert_eq!(centered, (110.0, 20.0));
+        let right_aligned = aligned_position(position, wrapped, right, VerticalAlign::Top);
+        assert_eq!(right_aligned, (210.0, 20.0));
+
+        let unbounded = glam::vec2(UNBOUNDED_F32, 100.0);
+        let aligned = aligned_position(position, unbounded, right, VerticalAlign::Middle);
+        assert_eq!(aligned, (10.0, 70.0));
+    }
+
+    #[test]
+    fn rich_text_skips_bitmap_spans() {
+        // Bitmap fonts fill their glyph brush slot with the first font as a placeholder
+        let font = FontArc::try_from_slice(FONT).unwrap();
+        let fonts = [font.clone(), font];
+        let bitmap = FontId(1);
+        let measure_rich = |text: &RichText| {
+            let spans = glyph_spans(text, |font| font == bitmap);
+            let section = rich_text_section(text, &spans);
+            let glyphs = section.layout.calculate_glyphs(
+                &fonts,
+                &SectionGeometry::from(&section),
+                &section.text
+            );
+            layout_bounds(&layout_glyph_positions(&fonts, glyphs.iter()))
+        };
+
+        let style = TextStyle::new(16.0, [1.0; 4]);
+        let mixed = RichText::new(0.0, 0.0)
+            .span("ab", style)
+            .span("cdef", style.with_font(bitmap));
+        let vector = RichText::new(0.0, 0.0).span("ab", style);
+        assert_eq!(measure_rich(&mixed), measure_rich(&vector));
+
+        let only_bitmap = RichText::new(0.0, 0.0).span("cdef", style.with_font(bitmap));
+        assert_eq!(measure_rich(&only_bitmap), Rect::default());
+    }
+
+    #[test]
+    fn measure_uses_line_height() {
+        let fonts = [FontArc::try_from_slice(FONT).unwrap()];
+        let scaled = fonts[0].as_scaled(16.0);
+        let line_height = scaled.ascent() - scaled.descent();
+
+        assert!((measure("a").height - line_height).abs() < 0.01);
+        assert!((measure(".").height - line_height).abs() < 0.01);
+        assert_eq!(measure(""), Rect::default());
     }
 }
@@ -0,0 +1,4 @@
+//! Rust mirrors of the shaders' uniform blocks and vertex inputs, generated by `build.rs` from
+//! the GLSL sources in `res/shaders`.
+
+include!(concat!(env!("OUT_DIR"), "/shader_types.rs"));
@@ -0,0 +1,674 @@
+//! Outline and drop-shadow effects for text.
+//!
+//! Effect glyphs are drawn by a second glyph brush into an offscreen texture, then a filter
+//! shader dilates (outline) or blurs (shadow) them and composites the result underneath the
+//! regular text. Each effect uses its own variants of the filter shader. Texts sharing the same
+//! effect parameters are filtered together.
+//!
+//! Filtering is separable: a horizontal pass writes into an intermediate target and a vertical
+//! pass reads it back, so each pixel reads at most `2 * (2 * MAX_RADIUS + 1)` texels instead of
+//! a whole disk. Outlines are dilated by the distance to the closest glyph texel, found along x
+//! and then combined along y as in a separable distance transform.
+
+use wgpu::{
+    BindGroup,
+    BindGroupDescriptor,
+    BindGroupEntry,
+    BindGroupLayout,
+    BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry,
+    BindingResource,
+    BindingType,
+    BlendState,
+    Buffer,
+    BufferAddress,
+    BufferBinding,
+    BufferBindingType,
+    BufferDescriptor,
+    BufferUsages,
+    ColorTargetState,
+    ColorWrites,
+    Device,
+    SamplerBindingType,
+    ShaderModule,
+    ShaderStages,
+    TextureSampleType,
+    TextureViewDimension,
+};
+use wgpu_glyph::{ ab_glyph, GlyphCruncher, OwnedSection, Section };
+
+use crate::init::grow_buffer;
+pub(crate) use crate::shader_types::EffectParams;
+use crate::profiler::{ glyph_count, FrameCounters };
+use crate::shader_types::TextEffectVertex;
+
+// Picks the text_effect.frag variant a group is filtered with
+const MODE_OUTLINE: u32 = 0;
+const MODE_SHADOW: u32 = 1;
+// Must match `MAX_RADIUS` in text_effect.frag. Thickness and blur are each clamped to it, and
+// the filter reaches at most this far from a glyph
+const MAX_RADIUS: f32 = 16.0;
+// Holds the outline distance or the partial blur between the two filter passes
+const PASS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+impl EffectParams {
+    pub(crate) fn outline(thickness: f32, blur: f32) -> Self {
+        Self {
+            thickness: thickness.clamp(0.0, MAX_RADIUS),
+            blur: blur.clamp(0.0, MAX_RADIUS),
+            mode: MODE_OUTLINE,
+            ..bytemuck::Zeroable::zeroed()
+        }
+    }
+
+    pub(crate) fn shadow(blur: f32) -> Self {
+        Self {
+            thickness: 0.0,
+            blur: blur.clamp(0.0, MAX_RADIUS),
+            mode: MODE_SHADOW,
+            ..bytemuck::Zeroable::zeroed()
+        }
+    }
+
+    /// How far the effect can reach past the glyph layout bounds, in pixels.
+    fn reach(&self) -> f32 {
+        (self.thickness + self.blur).ceil()
+    }
+}
+
+struct EffectGroup {
+    params: EffectParams,
+    sections: Vec<OwnedSection>,
+    // Screen-space quads covering every section, expanded by the effect reach
+    quads: Vec<[f32; 2]>,
+}
+
+/// Shader modules of every text_effect.frag variant.
+pub(crate) struct EffectShaders {
+    pub(crate) vert: ShaderModule,
+    pub(crate) shadow: ShaderModule,
+    pub(crate) shadow_horizontal: ShaderModule,
+    pub(crate) outline: ShaderModule,
+    pub(crate) outline_horizontal: ShaderModule,
+}
+
+struct EffectPipelines {
+    shadow: wgpu::RenderPipeline,
+    shadow_horizontal: wgpu::RenderPipeline,
+    outline: wgpu::RenderPipeline,
+    outline_horizontal: wgpu::RenderPipeline,
+}
+
+pub(crate) struct TextEffects {
+    glyph_brush: wgpu_glyph::GlyphBrush<()>,
+    pipelines: EffectPipelines,
+    bind_group_layout: BindGroupLayout,
+    // Reads the glyph target, for the horizontal pass
+    bind_group: BindGroup,
+    // Reads the intermediate target, for the vertical pass
+    pass_bind_group: BindGroup,
+    sampler: wgpu::Sampler,
+    target_view: wgpu::TextureView,
+    pass_view: wgpu::TextureView,
+    params_buffer: Buffer,
+    params_stride: BufferAddress,
+    vertex_buffer: Buffer,
+    groups: Vec<EffectGroup>,
+}
+
+impl TextEffects {
+    pub(crate) fn new(
+        device: &Device,
+        config: &wgpu::SurfaceConfiguration,
+        screen_size_layout: &BindGroupLayout,
+        glyph_brush: wgpu_glyph::GlyphBrush<()>,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Self {
+        let bind_group_layout = create_effect_bind_group_layout(device);
+        let pipelines = create_effect_pipelines(
+            device,
+            screen_size_layout,
+            &bind_group_layout,
+            config.format,
+            &create_effect_shaders(device),
+            cache
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+        let (target_view, pass_view) = create_target_views(
+            device,
+            config.format,
+            config.width,
+            config.height
+        );
+
+        let params_stride = device
+            .limits()
+            .min_uniform_buffer_offset_alignment.max(
+                std::mem::size_of::<EffectParams>() as u32
+            ) as BufferAddress;
+        let params_buffer = device.create_buffer(
+            &(BufferDescriptor {
+                label: Some("Text Effect Params Buffer"),
+                size: params_stride * 4,
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+        let vertex_buffer = device.create_buffer(
+            &(BufferDescriptor {
+                label: Some("Text Effect Vertex Buffer"),
+                size: TextEffectVertex::SIZE * 256,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        );
+
+        let bind_group = create_effect_bind_group(
+            device,
+            &bind_group_layout,
+            &target_view,
+            &sampler,
+            &params_buffer
+        );
+        let pass_bind_group = create_effect_bind_group(
+            device,
+            &bind_group_layout,
+            &pass_view,
+            &sampler,
+            &params_buffer
+        );
+
+        Self {
+            glyph_brush,
+            pipelines,
+            bind_group_layout,
+            bind_group,
+            pass_bind_group,
+            sampler,
+            target_view,
+            pass_view,
+            params_buffer,
+            params_stride,
+            vertex_buffer,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Rebuilds the pipelines from recompiled shaders, keeping the old ones if they are invalid.
+    #[cfg(feature = "hot-reload")]
+    pub(crate) fn reload_shaders(
+        &mut self,
+        device: &Device,
+        screen_size_layout: &BindGroupLayout,
+        format: wgpu::TextureFormat,
+        shaders: &EffectShaders,
+        cache: Option<&wgpu::PipelineCache>
+    ) -> Result<(), crate::RenderError> {
+        self.pipelines = crate::init::validated(device, || {
+            create_effect_pipelines(
+                device,
+                screen_size_layout,
+                &self.bind_group_layout,
+                format,
+                shaders,
+                cache
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Keeps font ids in sync with the main glyph brush.
+    pub(crate) fn add_font(&mut self, font: ab_glyph::FontArc) {
+        self.glyph_brush.add_font(font);
+    }
+
+    pub(crate) fn resize(
+        &mut self,
+        device: &Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32
+    ) {
+        (self.target_view, self.pass_view) = create_target_views(device, format, width, height);
+        self.rebuild_bind_groups(device);
+    }
+
+    /// Queues a section whose glyph colors are the effect color.
+    pub(crate) fn queue(&mut self, params: EffectParams, section: Section) {
+        let Some(bounds) = self.glyph_brush.glyph_bounds(&section) else {
+            return;
+        };
+
+        // Glyphs may overhang their advance box, so pad by a fraction of the largest scale
+        let overhang = section.text
+            .iter()
+            .map(|text| text.scale.y)
+            .fold(0.0f32, f32::max) * 0.25;
+        let reach = params.reach() + overhang;
+        let (left, top) = (bounds.min.x - reach, bounds.min.y - reach);
+        let (right, bottom) = (bounds.max.x + reach, bounds.max.y + reach);
+
+        let group = match self.groups.iter().position(|group| group.params == params) {
+            Some(index) => &mut self.groups[index],
+            None => {
+                self.groups.push(EffectGroup {
+                    params,
+                    sections: Vec::new(),
+                    quads: Vec::new(),
+                });
+                self.groups.last_mut().unwrap()
+            }
+        };
+
+        group.quads.extend_from_slice(
+            &[
+                [left, top],
+                [right, top],
+                [right, bottom],
+                [left, top],
+                [right, bottom],
+                [left, bottom],
+            ]
+        );
+        group.sections.push(section.to_owned());
+    }
+
+    /// Draws all queued effects onto `view`. Must run before the regular text is drawn.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn draw(
+        &mut self,
+        device: &Device,
+        queue: &wgpu::Queue,
+        staging_belt: &mut wgpu::util::StagingBelt,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        screen_size_bind_group: &BindGroup,
+        width: u32,
+        height: u32,
+        counters: &mut FrameCounters
+    ) {
+        if self.groups.is_empty() {
+            return;
+        }
+
+        // Shadows sit underneath outlines
+        self.groups.sort_by_key(|group| group.params.mode != MODE_SHADOW);
+
+        // Upload the params of every group and all quads up front
+        let params_size = self.params_stride * (self.groups.len() as BufferAddress);
+        if
+            grow_buffer(
+                device,
+                &mut self.params_buffer,
+                Some("Text Effect Params Buffer"),
+                params_size
+            )
+        {
+            self.rebuild_bind_groups(device);
+        }
+        for (i, group) in self.groups.iter().enumerate() {
+            queue.write_buffer(
+                &self.params_buffer,
+                self.params_stride * (i as BufferAddress),
+                bytemuck::bytes_of(&group.params)
+            );
+            counters.upload(std::mem::size_of_val(&group.params));
+        }
+
+        let quads: Vec<TextEffectVertex> = self.groups
+            .iter()
+            .flat_map(|group| group.quads.iter().map(|&position| TextEffectVertex { position }))
+            .collect();
+        grow_buffer(
+            device,
+            &mut self.vertex_buffer,
+            Some("Text Effect Vertex Buffer"),
+            TextEffectVertex::SIZE * (quads.len() as BufferAddress)
+        );
+        let quads: &[u8] = bytemuck::cast_slice(&quads);
+        queue.write_buffer(&self.vertex_buffer, 0, quads);
+        counters.upload(quads.len());
+
+        let mut first_vertex = 0;
+        for (i, group) in self.groups.drain(..).enumerate() {
+            // Clear the offscreen target and draw this group's effect glyphs into it
+            encoder.begin_render_pass(
+                &(wgpu::RenderPassDescriptor {
+                    label: Some("Text Effect Clear Pass"),
+                    color_attachments: &[
+                        Some(wgpu::RenderPassColorAttachment {
+                            view: &self.target_view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: wgpu::StoreOp::Store,
+                            },
+                            depth_slice: None,
+                        }),
+                    ],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                })
+            );
+
+            let mut glyphs = 0;
+            for section in &group.sections {
+                glyphs += section.text
+                    .iter()
+                    .map(|text| glyph_count(&text.text))
+                    .sum::<u32>();
+                self.glyph_brush.queue(section.to_borrowed());
+            }
+            self.glyph_brush
+                .draw_queued(device, staging_belt, encoder, &self.target_view, width, height)
+                .unwrap();
+            counters.glyph_draw(glyphs);
+
+            // Filter the glyphs along x into the intermediate target, then along y onto the frame
+            let vertex_count = group.quads.len() as u32;
+            let vertices = first_vertex..first_vertex + vertex_count;
+            let params_offset = (self.params_stride * (i as BufferAddress)) as u32;
+            let (horizontal_pipeline, pipeline) = if group.params.mode == MODE_OUTLINE {
+                (&self.pipelines.outline_horizontal, &self.pipelines.outline)
+            } else {
+                (&self.pipelines.shadow_horizontal, &self.pipelines.shadow)
+            };
+
+            let mut render_pass = encoder.begin_render_pass(
+                &(wgpu::RenderPassDescriptor {
+                    label: Some("Text Effect Horizontal Pass"),
+                    color_attachments: &[
+                        Some(wgpu::RenderPassColorAttachment {
+                            view: &self.pass_view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: wgpu::StoreOp::Store,
+                            },
+                            depth_slice: None,
+                        }),
+                    ],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                })
+            );
+            render_pass.set_pipeline(horizontal_pipeline);
+            render_pass.set_bind_group(0, screen_size_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.bind_group, &[params_offset]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(vertices.clone(), 0..1);
+            counters.draw(vertex_count, 1);
+            drop(render_pass);
+
+            let mut render_pass = encoder.begin_render_pass(
+                &(wgpu::RenderPassDescriptor {
+                    label: Some("Text Effect Composite Pass"),
+                    color_attachments: &[
+                        Some(wgpu::RenderPassColorAttachment {
+                            view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            },
+                            depth_slice: None,
+                        }),
+                    ],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                })
+            );
+
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, screen_size_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.pass_bind_group, &[params_offset]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(vertices, 0..1);
+            counters.draw(vertex_count, 1);
+
+            first_vertex += vertex_count;
+        }
+    }
+
+    fn rebuild_bind_groups(&mut self, device: &Device) {
+        self.bind_group = create_effect_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.target_view,
+            &self.sampler,
+            &self.params_buffer
+        );
+        self.pass_bind_group = create_effect_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.pass_view,
+            &self.sampler,
+            &self.params_buffer
+        );
+    }
+}
+
+/// Creates the glyph target and the intermediate target of the filter passes.
+fn create_target_views(
+    device: &Device,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32
+) -> (wgpu::TextureView, wgpu::TextureView) {
+    (
+        create_target_view(device, "Text Effect Target", format, width, height),
+        create_target_view(device, "Text Effect Pass Target", PASS_FORMAT, width, height),
+    )
+}
+
+fn create_target_view(
+    device: &Device,
+    label: &str,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32
+) -> wgpu::TextureView {
+    let texture = device.create_texture(
+        &(wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    );
+
+    texture.create_view(&Default::default())
+}
+
+fn create_effect_bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(
+        &(BindGroupLayoutDescriptor {
+            label: Some("Text Effect BGL"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<EffectParams>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        })
+    )
+}
+
+fn create_effect_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    target_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    params_buffer: &Buffer
+) -> BindGroup {
+    device.create_bind_group(
+        &(BindGroupDescriptor {
+            label: Some("Text Effect BG"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(target_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: params_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<EffectParams>() as u64),
+                    }),
+                },
+            ],
+        })
+    )
+}
+
+fn create_effect_shaders(device: &Device) -> EffectShaders {
+    let create = |label: &str, source: &'static str| {
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(source)),
+        })
+    };
+
+    EffectShaders {
+        vert: create(
+            "text effect vertex shader",
+            include_str!("../res/shaders/text_effect.vert.wgsl")
+        ),
+        shadow: create(
+            "text shadow fragment shader",
+            include_str!("../res/shaders/text_effect.frag.wgsl")
+        ),
+        shadow_horizontal: create(
+            "text shadow horizontal fragment shader",
+            include_str!("../res/shaders/text_effect.horizontal.frag.wgsl")
+        ),
+        outline: create(
+            "text outline fragment shader",
+            include_str!("../res/shaders/text_effect.outline.frag.wgsl")
+        ),
+        outline_horizontal: create(
+            "text outline horizontal fragment shader",
+            include_str!("../res/shaders/text_effect.outline_horizontal.frag.wgsl")
+        ),
+    }
+}
+
+fn create_effect_pipelines(
+    device: &Device,
+    screen_size_layout: &BindGroupLayout,
+    effect_layout: &BindGroupLayout,
+    format: wgpu::TextureFormat,
+    shaders: &EffectShaders,
+    cache: Option<&wgpu::PipelineCache>
+) -> EffectPipelines {
+    // The horizontal passes overwrite the intermediate target, the vertical ones composite onto
+    // the frame. The offscreen glyphs are premultiplied
+    let create = |frag_shader, format, blend| {
+        create_effect_pipeline(
+            device,
+            screen_size_layout,
+            effect_layout,
+            ColorTargetState {
+                format,
+                blend,
+                write_mask: ColorWrites::ALL,
+            },
+            &shaders.vert,
+            frag_shader,
+            cache
+        )
+    };
+    let composite = Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING);
+
+    EffectPipelines {
+        shadow: create(&shaders.shadow, format, composite),
+        shadow_horizontal: create(&shaders.shadow_horizontal, PASS_FORMAT, None),
+        outline: create(&shaders.outline, format, composite),
+        outline_horizontal: create(&shaders.outline_horizontal, PASS_FORMAT, None),
+    }
+}
+
+fn create_effect_pipeline(
+    device: &Device,
+    screen_size_layout: &BindGroupLayout,
+    effect_layout: &BindGroupLayout,
+    target: ColorTargetState,
+    vert_shader: &ShaderModule,
+    frag_shader: &ShaderModule,
+    cache: Option<&wgpu::PipelineCache>
+) -> wgpu::RenderPipeline {
+
+    let pipeline_layout = device.create_pipeline_layout(
+        &(wgpu::PipelineLayoutDescriptor {
+            label: Some("Text Effect Pipeline Layout"),
+            bind_group_layouts: &[screen_size_layout, effect_layout],
+            push_constant_ranges: &[],
+        })
+    );
+
+    device.create_render_pipeline(
+        &(wgpu::RenderPipelineDescriptor {
+            label: Some("Text Effect Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: vert_shader,
+                entry_point: Some("main"),
+                buffers: &[TextEffectVertex::DESC],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: frag_shader,
+                entry_point: Some("main"),
+                targets: &[Some(target)],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache,
+        })
+    )
+}
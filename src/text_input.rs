@@ -0,0 +1,534 @@
+//! Single-line editable text field.
+//!
+//! `TextInput` consumes winit window events to edit its text, keeping a caret, a selection and a
+//! horizontal scroll offset. The selection highlight and caret are drawn with the shape pipeline
+//! and the text itself through the glyph brush, clipped to the field.
+
+use std::time::{ Duration, Instant };
+
+use winit::event::{ ElementState, Ime, MouseButton, WindowEvent };
+use winit::keyboard::{ Key, ModifiersState, NamedKey };
+
+use crate::renderer::Renderer;
+use crate::types::{ Rect, Text, TextStyle, UNBOUNDED_F32 };
+
+const CARET_WIDTH: f32 = 2.0;
+const CARET_BLINK: Duration = Duration::from_millis(530);
+
+pub struct TextInput {
+    /// Content, box (`position` and `bounds`), style and focus of the field
+    pub text: Text,
+    pub selection_color: [f32; 4],
+    pub caret_color: [f32; 4],
+    /// Maximum length in characters, if any
+    pub max_length: Option<usize>,
+    // Byte indices into `text.text`. The selection spans between `anchor` and `caret`.
+    caret: usize,
+    anchor: usize,
+    scroll: f32,
+    // IME composition shown at the caret until it is committed
+    preedit: String,
+    modifiers: ModifiersState,
+    cursor_position: (f32, f32),
+    dragging: bool,
+    last_edit: Instant,
+}
+
+impl TextInput {
+    /// Creates an empty field covering `bounds`.
+    pub fn new(bounds: Rect, size: f32) -> Self {
+        Self {
+            text: Text {
+                position: (bounds.x, bounds.y).into(),
+                bounds: (bounds.width, bounds.height).into(),
+                size,
+                visible: true,
+                ..Default::default()
+            },
+            selection_color: [0.2, 0.4, 0.8, 1.0],
+            caret_color: [1.0, 1.0, 1.0, 1.0],
+            max_length: None,
+            caret: 0,
+            anchor: 0,
+            scroll: 0.0,
+            preedit: String::new(),
+            modifiers: ModifiersState::empty(),
+            cursor_position: (0.0, 0.0),
+            dragging: false,
+            last_edit: Instant::now(),
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.text.text
+    }
+
+    pub fn set_value(&mut self, value: impl Into<String>) {
+        self.text.text = value.into();
+        self.caret = self.text.text.len();
+        self.anchor = self.caret;
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.text.focused
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.text.focused = focused;
+        self.preedit.clear();
+        self.last_edit = Instant::now();
+    }
+
+    /// Returns the byte index of the caret.
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// Returns the selected byte range, which is empty when nothing is selected.
+    pub fn selection(&self) -> std::ops::Range<usize> {
+        self.caret.min(self.anchor)..self.caret.max(self.anchor)
+    }
+
+    pub fn selected_text(&self) -> &str {
+        &self.text.text[self.selection()]
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.caret = self.text.text.len();
+    }
+
+    /// Screen area of the caret. Pass it to `Window::set_ime_cursor_area` so the IME candidate
+    /// window appears next to the field.
+    pub fn ime_cursor_area(&self, renderer: &mut Renderer) -> Rect {
+        let x = self.caret_x(renderer, self.caret) - self.scroll;
+        let line_height = renderer.line_height(self.text.size, self.text.font);
+        Rect::new(self.box_rect().x + x, self.text_y(renderer), CARET_WIDTH, line_height)
+    }
+
+    /// Applies a window event to the field. Returns true if the event was consumed.
+    pub fn handle_event(&mut self, renderer: &mut Renderer, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = (position.x as f32, position.y as f32);
+                if self.dragging {
+                    self.caret = self.hit_test(renderer, self.cursor_position.0);
+                    self.scroll_to_caret(renderer);
+                }
+                self.dragging
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                match state {
+                    ElementState::Pressed => {
+                        let (x, y) = self.cursor_position;
+                        let inside = self.box_rect().contains(x, y);
+                        if inside != self.text.focused {
+                            self.set_focused(inside);
+                        }
+                        if inside {
+                            self.caret = self.hit_test(renderer, x);
+                            if !self.modifiers.shift_key() {
+                                self.anchor = self.caret;
+                            }
+                            self.dragging = true;
+                        }
+                        inside
+                    }
+                    ElementState::Released => {
+                        let was_dragging = self.dragging;
+                        self.dragging = false;
+                        was_dragging
+                    }
+                }
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                if !self.text.focused || event.state != ElementState::Pressed {
+                    return false;
+                }
+
+                let handled = self.handle_key(&event.logical_key, event.text.as_deref());
+                if handled {
+                    self.last_edit = Instant::now();
+                    self.scroll_to_caret(renderer);
+                }
+                handled
+            }
+            WindowEvent::Ime(ime) => {
+                if !self.text.focused {
+                    return false;
+                }
+
+                match ime {
+                    Ime::Preedit(text, _) => {
+                        self.preedit = text.clone();
+                    }
+                    Ime::Commit(text) => {
+                        self.preedit.clear();
+                        self.insert(text);
+                    }
+                    Ime::Enabled | Ime::Disabled => self.preedit.clear(),
+                }
+                self.last_edit = Instant::now();
+                self.scroll_to_caret(renderer);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Queues the selection highlight, the text and the caret.
+    pub fn draw(&self, renderer: &mut Renderer) {
+        if !self.text.visible {
+            return;
+        }
+
+        let bounds = self.box_rect();
+        let origin_x = bounds.x - self.scroll;
+        let text_y = self.text_y(renderer);
+        let line_height = renderer.line_height(self.text.size, self.text.font);
+
+        let selection = self.selection();
+        if self.text.focused && !selection.is_empty() {
+            let start = origin_x + self.caret_x(renderer, selection.start);
+            let end = origin_x + self.caret_x(renderer, selection.end);
+            let (left, right) = (start.max(bounds.x), end.min(bounds.right()));
+            if right > left {
+                renderer.queue_rectangle(
+                    left,
+                    text_y,
+                    right - left,
+                    line_height,
+                    self.selection_color
+                );
+            }
+        }
+
+        let style = TextStyle::new(self.text.size, self.text.color.to_array()).with_font(
+            self.text.font
+        );
+        let display = self.display_text();
+        renderer.queue_clipped_text(&display, (origin_x, text_y), &style, bounds);
+
+        if !self.text.focused {
+            return;
+        }
+
+        // Underline the IME composition
+        let caret_x = origin_x + self.caret_x(renderer, self.caret);
+        if !self.preedit.is_empty() {
+            let preedit_end =
+                origin_x + self.display_caret_x(renderer, &display, self.caret + self.preedit.len());
+            let (left, right) = (caret_x.max(bounds.x), preedit_end.min(bounds.right()));
+            if right > left {
+                renderer.queue_rectangle(left, text_y + line_height, right - left, 1.0, style.color);
+            }
+        }
+
+        let blink_phase = self.last_edit.elapsed().as_millis() / CARET_BLINK.as_millis();
+        let blink_on = blink_phase.is_multiple_of(2);
+        if blink_on && self.preedit.is_empty() && caret_x >= bounds.x && caret_x <= bounds.right() {
+            renderer.queue_rectangle(caret_x, text_y, CARET_WIDTH, line_height, self.caret_color);
+        }
+    }
+
+    fn handle_key(&mut self, key: &Key, text: Option<&str>) -> bool {
+        let shift = self.modifiers.shift_key();
+        let word = self.modifiers.control_key() || self.modifiers.alt_key();
+
+        match key {
+            Key::Named(NamedKey::Backspace) => {
+                if self.selection().is_empty() {
+                    self.anchor = if word {
+                        self.previous_word(self.caret)
+                    } else {
+                        self.previous_char(self.caret)
+                    };
+                }
+                self.insert("");
+            }
+            Key::Named(NamedKey::Delete) => {
+                if self.selection().is_empty() {
+                    self.anchor = if word {
+                        self.next_word(self.caret)
+                    } else {
+                        self.next_char(self.caret)
+                    };
+                }
+                self.insert("");
+            }
+            Key::Named(NamedKey::ArrowLeft) => {
+                let selection = self.selection();
+                self.caret = if !shift && !selection.is_empty() {
+                    selection.start
+                } else if word {
+                    self.previous_word(self.caret)
+                } else {
+                    self.previous_char(self.caret)
+                };
+                if !shift {
+                    self.anchor = self.caret;
+                }
+            }
+            Key::Named(NamedKey::ArrowRight) => {
+                let selection = self.selection();
+                self.caret = if !shift && !selection.is_empty() {
+                    selection.end
+                } else if word {
+                    self.next_word(self.caret)
+                } else {
+                    self.next_char(self.caret)
+                };
+                if !shift {
+                    self.anchor = self.caret;
+                }
+            }
+            Key::Named(NamedKey::Home) => {
+                self.caret = 0;
+                if !shift {
+                    self.anchor = self.caret;
+                }
+            }
+            Key::Named(NamedKey::End) => {
+                self.caret = self.text.text.len();
+                if !shift {
+                    self.anchor = self.caret;
+                }
+            }
+            Key::Character(c) if self.modifiers.control_key() || self.modifiers.super_key() => {
+                if !c.eq_ignore_ascii_case("a") {
+                    return false;
+                }
+                self.select_all();
+            }
+            _ => {
+                let Some(text) = text.filter(|t| !t.chars().any(char::is_control)) else {
+                    return false;
+                };
+                self.insert(text);
+            }
+        }
+
+        true
+    }
+
+    /// Replaces the selection with `text` and moves the caret after it.
+    fn insert(&mut self, text: &str) {
+        let selection = self.selection();
+        let mut text = text;
+        if let Some(max_length) = self.max_length {
+            let remaining = max_length.saturating_sub(
+                self.text.text.chars().count() - self.text.text[selection.clone()].chars().count()
+            );
+            let end = text.char_indices().nth(remaining).map_or(text.len(), |(i, _)| i);
+            text = &text[..end];
+        }
+
+        self.text.text.replace_range(selection.clone(), text);
+        self.caret = selection.start + text.len();
+        self.anchor = self.caret;
+    }
+
+    fn previous_char(&self, index: usize) -> usize {
+        self.text.text[..index].char_indices().next_back().map_or(0, |(i, _)| i)
+    }
+
+    fn next_char(&self, index: usize) -> usize {
+        self.text.text[index..].chars().next().map_or(index, |c| index + c.len_utf8())
+    }
+
+    fn previous_word(&self, index: usize) -> usize {
+        let before = self.text.text[..index].trim_end();
+        before.rfind(char::is_whitespace).map_or(0, |i| {
+            i + before[i..].chars().next().map_or(0, char::len_utf8)
+        })
+    }
+
+    fn next_word(&self, index: usize) -> usize {
+        let after = &self.text.text[index..];
+        let word_start = after.len() - after.trim_start().len();
+        let trimmed = &after[word_start..];
+        index + word_start + trimmed.find(char::is_whitespace).unwrap_or(trimmed.len())
+    }
+
+    fn box_rect(&self) -> Rect {
+        Rect::new(
+            self.text.position.x,
+            self.text.position.y,
+            self.text.bounds.x,
+            self.text.bounds.y
+        )
+    }
+
+    /// Top of the text line, vertically centered in the box.
+    fn text_y(&self, renderer: &Renderer) -> f32 {
+        let line_height = renderer.line_height(self.text.size, self.text.font);
+        self.text.position.y + (self.text.bounds.y - line_height) * 0.5
+    }
+
+    /// Text with the IME composition spliced in at the caret.
+    fn display_text(&self) -> String {
+        let mut display = self.text.text.clone();
+        if self.text.focused && !self.preedit.is_empty() {
+            display.insert_str(self.caret, &self.preedit);
+        }
+        display
+    }
+
+    /// X offset of the caret at `index`, relative to the start of the text.
+    fn caret_x(&self, renderer: &mut Renderer, index: usize) -> f32 {
+        self.display_caret_x(renderer, &self.text.text, index)
+    }
+
+    fn display_caret_x(&self, renderer: &mut Renderer, text: &str, index: usize) -> f32 {
+        let glyphs = renderer.glyph_positions(text, self.text.size, self.text.font, UNBOUNDED_F32);
+        glyphs
+            .iter()
+            .find(|g| g.byte_index >= index)
+            .map(|g| g.rect.x)
+            .or_else(|| glyphs.last().map(|g| g.rect.right()))
+            .unwrap_or(0.0)
+    }
+
+    fn hit_test(&self, renderer: &mut Renderer, x: f32) -> usize {
+        let local_x = x - self.text.position.x + self.scroll;
+        renderer.hit_test_text(
+            &self.text.text,
+            self.text.size,
+            self.text.font,
+            UNBOUNDED_F32,
+            (local_x, 0.0)
+        )
+    }
+
+    /// Adjusts the scroll offset so the caret stays inside the box.
+    fn scroll_to_caret(&mut self, renderer: &mut Renderer) {
+        let width = self.text.bounds.x;
+        let display = self.display_text();
+        let caret_x = self.display_caret_x(renderer, &display, self.caret + self.preedit.len());
+        let text_width = self.display_caret_x(renderer, &display, display.len());
+
+        if caret_x - self.scroll > width - CARET_WIDTH {
+            self.scroll = caret_x - width + CARET_WIDTH;
+        }
+        if caret_x < self.scroll {
+            self.scroll = caret_x;
+        }
+        // Don't leave empty space on the right after deleting text
+        self.scroll = self.scroll.min((text_width + CARET_WIDTH - width).max(0.0)).max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(value: &str) -> TextInput {
+        let mut input = TextInput::new(Rect::new(0.0, 0.0, 200.0, 30.0), 16.0);
+        input.set_value(value);
+        input
+    }
+
+    fn press(input: &mut TextInput, key: NamedKey) -> bool {
+        input.handle_key(&Key::Named(key), None)
+    }
+
+    #[test]
+    fn arrows_move_over_whole_chars() {
+        // 'é' is two bytes and '🦀' four
+        let mut input = input("aé🦀");
+        assert_eq!(input.caret(), 7);
+
+        press(&mut input, NamedKey::ArrowLeft);
+        assert_eq!(input.caret(), 3);
+        press(&mut input, NamedKey::ArrowLeft);
+        assert_eq!(input.caret(), 1);
+        press(&mut input, NamedKey::ArrowLeft);
+        press(&mut input, NamedKey::ArrowLeft);
+        assert_eq!(input.caret(), 0);
+
+        press(&mut input, NamedKey::ArrowRight);
+        press(&mut input, NamedKey::ArrowRight);
+        assert_eq!(input.caret(), 3);
+        assert!(input.value().is_char_boundary(input.caret()));
+    }
+
+    #[test]
+    fn word_movement_skips_whitespace() {
+        let mut input = input("hello  big world");
+        input.modifiers = ModifiersState::CONTROL;
+
+        press(&mut input, NamedKey::ArrowLeft);
+        assert_eq!(input.caret(), 11);
+        press(&mut input, NamedKey::ArrowLeft);
+        assert_eq!(input.caret(), 7);
+        press(&mut input, NamedKey::ArrowRight);
+        assert_eq!(input.caret(), 10);
+    }
+
+    #[test]
+    fn typing_replaces_the_selection() {
+        let mut input = input("hello world");
+        input.modifiers = ModifiersState::SHIFT | ModifiersState::CONTROL;
+        press(&mut input, NamedKey::ArrowLeft);
+        assert_eq!(input.selected_text(), "world");
+
+        input.modifiers = ModifiersState::empty();
+        assert!(input.handle_key(&Key::Character("t".into()), Some("there")));
+        assert_eq!(input.value(), "hello there");
+        assert_eq!(input.caret(), 11);
+        assert!(input.selection().is_empty());
+    }
+
+    #[test]
+    fn backspace_and_delete_stop_at_the_ends() {
+        let mut input = input("añ");
+
+        press(&mut input, NamedKey::Delete);
+        assert_eq!(input.value(), "añ");
+        press(&mut input, NamedKey::Backspace);
+        assert_eq!(input.value(), "a");
+
+        press(&mut input, NamedKey::Home);
+        press(&mut input, NamedKey::Backspace);
+        assert_eq!(input.value(), "a");
+        assert_eq!(input.caret(), 0);
+        press(&mut input, NamedKey::Delete);
+        assert_eq!(input.value(), "");
+    }
+
+    #[test]
+    fn backspace_removes_the_selection() {
+        let mut input = input("abcdef");
+        input.select_all();
+        press(&mut input, NamedKey::Backspace);
+        assert_eq!(input.value(), "");
+        assert_eq!(input.caret(), 0);
+    }
+
+    #[test]
+    fn insertion_respects_max_length() {
+        let mut input = input("ab");
+        input.max_length = Some(4);
+
+        input.insert("cdef");
+        assert_eq!(input.value(), "abcd");
+        input.insert("g");
+        assert_eq!(input.value(), "abcd");
+
+        // Replacing a selection frees its characters first
+        input.select_all();
+        input.insert("ü");
+        assert_eq!(input.value(), "ü");
+    }
+
+    #[test]
+    fn control_characters_are_not_inserted() {
+        let mut input = input("");
+        assert!(!input.handle_key(&Key::Named(NamedKey::Tab), Some("\t")));
+        assert_eq!(input.value(), "");
+    }
+}
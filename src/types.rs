@@ -1,17 +1,12 @@
-use wgpu::{ BufferAddress, VertexBufferLayout, VertexStepMode };
+use std::time::Duration;
 
-pub const U32_SIZE: BufferAddress = std::mem::size_of::<u32>() as BufferAddress;
+use wgpu::BufferAddress;
 
-#[derive(Copy, Clone)]
-#[repr(C)]
-pub struct Vertex {
-    pub position: [f32; 2],
-    pub color: [f32; 4], // RGBA
-}
+pub const U32_SIZE: BufferAddress = std::mem::size_of::<u32>() as BufferAddress;
 
-// Lets me convert vertices to raw bytes
-unsafe impl bytemuck::Pod for Vertex {}
-unsafe impl bytemuck::Zeroable for Vertex {}
+/// Vertex of the shape pipeline, generated from the inputs of textured.vert.
+pub use crate::shader_types::TexturedVertex as Vertex;
+pub(crate) use crate::shader_types::Globals;
 
 impl Vertex {
     pub fn new(x: f32, y: f32) -> Self {
@@ -27,32 +22,256 @@ impl Vertex {
             color,
         }
     }
+}
+
+/// One shape of the instanced shape pipeline, generated from the per-instance inputs of
+/// shape.vert. Queued through `Renderer::queue_rectangle_instances` and
+/// `Renderer::queue_circle_instances`.
+pub use crate::shader_types::ShapeInstance;
+
+impl ShapeInstance {
+    /// A rectangle with its top-left corner at `(x, y)`, like `Renderer::queue_rectangle`.
+    pub fn rectangle(x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) -> Self {
+        Self {
+            center: [x + width * 0.5, y + height * 0.5],
+            size: [width, height],
+            rotation: 0.0,
+            color,
+        }
+    }
+
+    pub fn circle(center_x: f32, center_y: f32, radius: f32, color: [f32; 4]) -> Self {
+        Self {
+            center: [center_x, center_y],
+            size: [radius * 2.0, radius * 2.0],
+            rotation: 0.0,
+            color,
+        }
+    }
+
+    /// Rotates the shape around its center, in radians.
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+}
+
+/// 2D camera used by world-space rendering.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera2D {
+    /// World position shown at the center of the screen
+    pub position: glam::Vec2,
+    pub zoom: f32,
+    /// Rotation in radians
+    pub rotation: f32,
+}
+
+impl Camera2D {
+    pub fn new(position: glam::Vec2) -> Self {
+        Self {
+            position,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the matrix mapping world space to screen pixels.
+    pub fn view_matrix(&self, screen_width: f32, screen_height: f32) -> glam::Mat4 {
+        glam::Mat4::from_translation(glam::vec3(screen_width * 0.5, screen_height * 0.5, 0.0)) *
+            glam::Mat4::from_scale(glam::vec3(self.zoom, self.zoom, 1.0)) *
+            glam::Mat4::from_rotation_z(-self.rotation) *
+            glam::Mat4::from_translation(-self.position.extend(0.0))
+    }
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self {
+            position: glam::Vec2::ZERO,
+            zoom: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+pub const UNBOUNDED_F32: f32 = f32::INFINITY;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// The same area with a non-negative width and height, moving `x` and `y` to the top-left
+    /// corner of rectangles that extend left or up.
+    pub fn normalized(&self) -> Rect {
+        Rect::new(
+            self.x.min(self.right()),
+            self.y.min(self.bottom()),
+            self.width.abs(),
+            self.height.abs()
+        )
+    }
+
+    /// Whether the two rectangles overlap. Rectangles that only share an edge do not. Negative
+    /// sizes are treated as in `normalized`.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        let (a, b) = (self.normalized(), other.normalized());
+        a.x < b.right() && b.x < a.right() && a.y < b.bottom() && b.y < a.bottom()
+    }
+
+    /// Smallest rectangle containing both, with a non-negative size.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let (a, b) = (self.normalized(), other.normalized());
+        let x = a.x.min(b.x);
+        let y = a.y.min(b.y);
+        Rect::new(x, y, a.right().max(b.right()) - x, a.bottom().max(b.bottom()) - y)
+    }
+}
+
+/// Layout box of a single glyph, relative to the origin the text was laid out at.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GlyphPosition {
+    /// Byte index of the glyph's character in the source string.
+    pub byte_index: usize,
+    pub rect: Rect,
+}
+
+/// Handle to a font loaded with `Renderer::load_font`.
+/// The default id refers to the built-in PressStart2P pixel font.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FontId(pub(crate) usize);
+
+/// Outline drawn around the glyphs. `thickness` and `blur` are in pixels, up to 16 combined.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextOutline {
+    pub color: [f32; 4],
+    pub thickness: f32,
+    pub blur: f32,
+}
+
+/// Drop shadow drawn underneath the glyphs. `offset` and `blur` are in pixels.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextShadow {
+    pub color: [f32; 4],
+    pub offset: (f32, f32),
+    pub blur: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TextStyle {
+    pub size: f32,
+    pub color: [f32; 4], // RGBA
+    pub font: FontId,
+    pub outline: Option<TextOutline>,
+    pub shadow: Option<TextShadow>,
+}
+
+impl TextStyle {
+    pub fn new(size: f32, color: [f32; 4]) -> Self {
+        Self {
+            size,
+            color,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_font(mut self, font: FontId) -> Self {
+        self.font = font;
+        self
+    }
+
+    pub fn with_outline(mut self, color: [f32; 4], thickness: f32, blur: f32) -> Self {
+        self.outline = Some(TextOutline { color, thickness, blur });
+        self
+    }
+
+    pub fn with_shadow(mut self, color: [f32; 4], offset: (f32, f32), blur: f32) -> Self {
+        self.shadow = Some(TextShadow { color, offset, blur });
+        self
+    }
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 16.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            font: FontId::default(),
+            outline: None,
+            shadow: None,
+        }
+    }
+}
 
-    pub const SIZE: BufferAddress = std::mem::size_of::<Self>() as BufferAddress;
-    pub const DESC: VertexBufferLayout<'static> = VertexBufferLayout {
-        array_stride: Vertex::SIZE,
-        step_mode: VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![
-            0 => Float32x2,
-            1 => Float32x4
-        ],
-    };
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HorizontalAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
 }
 
-pub const UNBOUNDED_F32: f32 = std::f32::INFINITY;
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
 
-#[derive(Debug)]
+/// Retained text object, queued with `Renderer::queue_text_object`.
+///
+/// `position` is the top-left corner of the text box: text wraps at `bounds.x` and is aligned
+/// inside the box. Along an unbounded axis, `position` is the alignment anchor instead, so text
+/// with a wrap width and no height limit is still aligned horizontally inside its width.
+#[derive(Clone, Debug)]
 pub struct Text {
     pub position: glam::Vec2<>,
     pub bounds: glam::Vec2<>,
     pub color: glam::Vec4<>,
     pub text: String,
     pub size: f32,
+    pub font: FontId,
+    pub h_align: HorizontalAlign,
+    pub v_align: VerticalAlign,
+    pub outline: Option<TextOutline>,
+    pub shadow: Option<TextShadow>,
     pub visible: bool,
     pub focused: bool,
+    /// Shorthand for `HorizontalAlign::Center` and `VerticalAlign::Middle`.
     pub centered: bool,
 }
 
+impl Text {
+    /// Returns the alignment to lay out with, taking `centered` into account.
+    pub fn alignment(&self) -> (HorizontalAlign, VerticalAlign) {
+        if self.centered {
+            (HorizontalAlign::Center, VerticalAlign::Middle)
+        } else {
+            (self.h_align, self.v_align)
+        }
+    }
+}
+
 impl Default for Text {
     fn default() -> Self {
         Self {
@@ -61,9 +280,266 @@ impl Default for Text {
             color: (1.0, 1.0, 1.0, 1.0).into(),
             text: String::new(),
             size: 16.0,
+            font: FontId::default(),
+            h_align: HorizontalAlign::default(),
+            v_align: VerticalAlign::default(),
+            outline: None,
+            shadow: None,
             visible: false,
             focused: false,
             centered: false,
         }
     }
 }
+
+/// A run of text sharing one style inside a `RichText` paragraph.
+#[derive(Clone, Debug)]
+pub struct TextSpan {
+    pub text: String,
+    pub style: TextStyle,
+}
+
+/// A paragraph made of differently styled spans, laid out and wrapped as one unit.
+/// `position`, `bounds` and alignment behave like they do for `Text`.
+#[derive(Clone, Debug)]
+pub struct RichText {
+    pub spans: Vec<TextSpan>,
+    pub position: glam::Vec2<>,
+    pub bounds: glam::Vec2<>,
+    pub h_align: HorizontalAlign,
+    pub v_align: VerticalAlign,
+}
+
+impl RichText {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self {
+            position: (x, y).into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_bounds(mut self, width: f32, height: f32) -> Self {
+        self.bounds = (width, height).into();
+        self
+    }
+
+    pub fn with_alignment(mut self, h_align: HorizontalAlign, v_align: VerticalAlign) -> Self {
+        self.h_align = h_align;
+        self.v_align = v_align;
+        self
+    }
+
+    pub fn span(mut self, text: impl Into<String>, style: TextStyle) -> Self {
+        self.spans.push(TextSpan {
+            text: text.into(),
+            style,
+        });
+        self
+    }
+}
+
+impl Default for RichText {
+    fn default() -> Self {
+        Self {
+            spans: Vec::new(),
+            position: (0.0, 0.0).into(),
+            bounds: (UNBOUNDED_F32, UNBOUNDED_F32).into(),
+            h_align: HorizontalAlign::default(),
+            v_align: VerticalAlign::default(),
+        }
+    }
+}
+
+/// Text rendered from distance fields. It stays sharp at any size, rotation or camera zoom,
+/// which makes it suited to animated and world-space labels.
+#[derive(Clone, Debug)]
+pub struct MsdfText {
+    pub text: String,
+    /// Anchor point that alignment and rotation are relative to
+    pub position: glam::Vec2<>,
+    pub size: f32,
+    /// Rotation around `position`, in radians
+    pub rotation: f32,
+    pub color: [f32; 4],
+    pub font: FontId,
+    pub h_align: HorizontalAlign,
+    pub v_align: VerticalAlign,
+    /// Follows the camera set with `Renderer::set_camera` when true
+    pub world_space: bool,
+}
+
+impl Default for MsdfText {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            position: (0.0, 0.0).into(),
+            size: 16.0,
+            rotation: 0.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            font: FontId::default(),
+            h_align: HorizontalAlign::default(),
+            v_align: VerticalAlign::default(),
+            world_space: false,
+        }
+    }
+}
+
+/// Handle to a shader material created with `Renderer::create_material`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub(crate) usize);
+
+/// Describes the uniform block a material binds at group 1, binding 0.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct UniformLayout {
+    /// Size of the block in bytes. Zero means the material has no uniforms and no group 1.
+    pub size: BufferAddress,
+}
+
+impl UniformLayout {
+    pub const NONE: Self = Self { size: 0 };
+
+    pub fn new(size: BufferAddress) -> Self {
+        Self { size }
+    }
+
+    /// Layout sized for a `#[repr(C)]` uniform struct.
+    pub fn of<T: bytemuck::Pod>() -> Self {
+        Self::new(std::mem::size_of::<T>() as BufferAddress)
+    }
+}
+
+/// Handle to a compute pipeline created with `Renderer::create_compute_pipeline`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ComputePipelineId(pub(crate) usize);
+
+/// Handle to a GPU buffer created with `Renderer::create_storage_buffer`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StorageBufferId(pub(crate) usize);
+
+/// Handle to a GPU texture created with `Renderer::create_storage_texture`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StorageTextureId(pub(crate) usize);
+
+/// Resource bound to a compute dispatch. The bindings of a dispatch go to group 0 in order, so
+/// the first one is `@binding(0)`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ComputeBinding {
+    /// A `var<storage>` or `var<uniform>` buffer
+    Buffer(StorageBufferId),
+    /// A `texture_storage_2d` or `texture_2d`
+    Texture(StorageTextureId),
+}
+
+/// Handle to a color grading lookup table loaded with `Renderer::load_color_lut`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ColorLutId(pub(crate) usize);
+
+/// Handle to a custom post-processing shader created with `Renderer::create_post_shader`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PostShaderId(pub(crate) usize);
+
+/// Full-screen pass run after shapes and text are drawn, see `Renderer::set_post_effects`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PostEffect {
+    /// Blurs the parts brighter than `threshold` and adds them back, scaled by `intensity`
+    Bloom {
+        threshold: f32,
+        intensity: f32,
+        /// Spacing of the blur taps in pixels
+        radius: f32,
+    },
+    /// Gaussian blur with taps `radius` pixels apart
+    Blur {
+        radius: f32,
+    },
+    /// Scanlines darkening every other row by `scanline_intensity` and a screen curved by
+    /// `curvature`
+    Crt {
+        scanline_intensity: f32,
+        curvature: f32,
+    },
+    /// Darkens the corners by `intensity`. The darkening starts at `radius`, where 1 is the
+    /// corner distance, and fades in over `softness`.
+    Vignette {
+        intensity: f32,
+        radius: f32,
+        softness: f32,
+    },
+    /// Remaps colors through a lookup table, blended in by `intensity`
+    ColorGrading {
+        lut: ColorLutId,
+        intensity: f32,
+    },
+    Custom(PostShaderId),
+}
+
+/// Handle to a light added with `Renderer::add_point_light`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LightId(pub(crate) usize);
+
+/// Light that brightens shapes within `radius` pixels of `position`, fading out towards the edge.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PointLight {
+    /// Screen pixels
+    pub position: glam::Vec2,
+    pub color: [f32; 3],
+    pub radius: f32,
+    pub intensity: f32,
+    /// Shadows cast by occluders, see `Renderer::set_casting_shadows`
+    pub shadows: Shadows,
+}
+
+/// How a point light is blocked by occluders.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Shadows {
+    /// The light passes through occluders
+    None,
+    /// Sharp shadow edges
+    #[default]
+    Hard,
+    /// Shadow edges blurred over `softness` pixels
+    Soft {
+        softness: f32,
+    },
+}
+
+/// Handle to a mesh uploaded with `Renderer::create_mesh`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MeshId(pub(crate) usize);
+
+/// Handle to a normal map loaded with `Renderer::load_normal_map`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NormalMapId(pub(crate) usize);
+
+/// Timings and counts of the last rendered frame, see `Renderer::frame_stats`.
+///
+/// Text drawn through wgpu_glyph counts as one draw call per glyph brush, with a quad per glyph.
+/// Its staging belt uploads are counted as if the glyph instances were rewritten every frame,
+/// which wgpu_glyph skips when the queued text did not change.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// GPU time of the shape pass and lighting. `None` until GPU timing is enabled with
+    /// `Renderer::set_profiling` and the first timed frame has been read back, which takes a
+    /// frame or two.
+    pub shape_gpu_time: Option<Duration>,
+    /// GPU time of all text passes and the frame's custom passes
+    pub text_gpu_time: Option<Duration>,
+    /// GPU time of the post effect chain, zero without post effects
+    pub post_gpu_time: Option<Duration>,
+    /// CPU time spent uploading and recording the frame, up to its submission
+    pub cpu_encode_time: Duration,
+    pub draw_calls: u32,
+    /// Vertices processed by the draw calls, counting every instance
+    pub vertices: u64,
+    pub triangles: u64,
+    /// Bytes written to GPU buffers, including writes made between frames such as uniform
+    /// updates
+    pub bytes_uploaded: u64,
+}
+
+impl FrameStats {
+    /// Total GPU time of the frame, if it was timed.
+    pub fn gpu_time(&self) -> Option<Duration> {
+        Some(self.shape_gpu_time? + self.text_gpu_time? + self.post_gpu_time?)
+    }
+}
@@ -0,0 +1,137 @@
+//! Layouts of the Rust structs build.rs generates from shader uniform blocks and vertex inputs.
+
+extern crate naga_build as naga;
+
+#[path = "../build/reflect.rs"]
+#[allow(dead_code)]
+mod reflect;
+
+use std::path::Path;
+
+use naga::ShaderStage;
+use naga::front::glsl::{ Frontend, Options };
+
+fn parse(stage: ShaderStage, source: &str) -> naga::Module {
+    Frontend::default().parse(&Options::from(stage), source).unwrap()
+}
+
+/// Reflects a fragment shader declaring `declarations`.
+fn reflect_fragment(declarations: &str) -> anyhow::Result<String> {
+    let source = format!(
+        "#version 450
+        {}
+        layout(location = 0) out vec4 fColor;
+        void main() {{ fColor = vec4(0.0); }}",
+        declarations
+    );
+    let mut reflection = reflect::Reflection::default();
+    let module = parse(ShaderStage::Fragment, &source);
+    reflection.add(Path::new("test.frag"), ShaderStage::Fragment, &module)?;
+    Ok(reflection.finish())
+}
+
+#[test]
+fn blocks_are_padded_to_16_bytes() {
+    let code = reflect_fragment(
+        "layout(set = 0, binding = 0) uniform Params { float thickness; float blur; uint mode; };"
+    ).unwrap();
+
+    assert!(code.contains("    pub thickness: f32,\n    pub blur: f32,\n    pub mode: u32,\n"));
+    assert!(code.contains("    pub _pad12: [u8; 4],\n"));
+    assert!(code.contains("pub const MODE_OFFSET: wgpu::BufferAddress = 8;"));
+    assert!(code.contains("std::mem::size_of::<Params>() == 16"));
+}
+
+#[test]
+fn members_follow_std140_alignment() {
+    let code = reflect_fragment(
+        "layout(set = 0, binding = 0) uniform Params {
+            vec2 offset;
+            vec3 color;
+            float alpha;
+            mat3 transform;
+            vec4 tint;
+        };"
+    ).unwrap();
+
+    // The vec3 is aligned to 16 bytes and the float packs into its last component
+    assert!(code.contains("    pub offset: [f32; 2],\n    pub _pad8: [u8; 8],\n"));
+    assert!(code.contains("    pub color: [f32; 3],\n    pub alpha: f32,\n"));
+    assert!(code.contains("pub const COLOR_OFFSET: wgpu::BufferAddress = 16;"));
+    assert!(code.contains("pub const ALPHA_OFFSET: wgpu::BufferAddress = 28;"));
+    // Matrix columns are padded to a vec4 each
+    assert!(code.contains("    pub transform: [[f32; 4]; 3],\n    pub tint: [f32; 4],\n"));
+    assert!(code.contains("pub const TRANSFORM_OFFSET: wgpu::BufferAddress = 32;"));
+    assert!(code.contains("pub const TINT_OFFSET: wgpu::BufferAddress = 80;"));
+    assert!(code.contains("std::mem::size_of::<Params>() == 96"));
+    assert!(!code.contains("_pad96"));
+}
+
+#[test]
+fn nested_structs_keep_their_std140_span() {
+    let code = reflect_fragment(
+        "struct Light { vec2 position; float radius; };
+        layout(set = 0, binding = 0) uniform Lights { Light lights[2]; float count; };"
+    ).unwrap();
+
+    // Structs are rounded up to 16 bytes, so the array elements need no padding
+    assert!(code.contains("    pub radius: f32,\n    pub _pad12: [u8; 4],\n}"));
+    assert!(code.contains("std::mem::size_of::<Light>() == 16"));
+    assert!(code.contains("    pub count: f32,\n    pub _pad36: [u8; 12],\n"));
+    assert!(code.contains("pub const COUNT_OFFSET: wgpu::BufferAddress = 32;"));
+    assert!(code.contains("std::mem::size_of::<Lights>() == 48"));
+}
+
+#[test]
+fn padded_array_elements_are_rejected() {
+    // std140 strides scalar arrays by 16 bytes
+    let error = reflect_fragment(
+        "layout(set = 0, binding = 0) uniform Params { float weights[4]; };"
+    ).unwrap_err();
+
+    assert!(error.to_string().contains("padded elements"));
+}
+
+#[test]
+fn mismatched_declarations_are_rejected() {
+    let module = |members: &str| {
+        let source = format!(
+            "#version 450
+            layout(set = 0, binding = 0) uniform Params {{ {} }};
+            layout(location = 0) out vec4 fColor;
+            void main() {{ fColor = vec4(0.0); }}",
+            members
+        );
+        parse(ShaderStage::Fragment, &source)
+    };
+
+    let mut reflection = reflect::Reflection::default();
+    reflection.add(Path::new("a.frag"), ShaderStage::Fragment, &module("float a;")).unwrap();
+    reflection.add(Path::new("b.frag"), ShaderStage::Fragment, &module("float a;")).unwrap();
+    let error = reflection
+        .add(Path::new("c.frag"), ShaderStage::Fragment, &module("vec2 a;"))
+        .unwrap_err();
+
+    assert!(error.to_string().contains("does not match its declaration in a.frag, b.frag"));
+}
+
+#[test]
+fn vertex_inputs_are_split_by_step() {
+    let module = parse(
+        ShaderStage::Vertex,
+        "#version 450
+        layout(location = 0) in vec2 aPosition;
+        layout(location = 1) in vec4 iColor;
+        layout(location = 2) in float aDepth;
+        void main() { gl_Position = vec4(aPosition, aDepth, 1.0) * iColor; }"
+    );
+    let mut reflection = reflect::Reflection::default();
+    reflection.add(Path::new("test.vert"), ShaderStage::Vertex, &module).unwrap();
+    let code = reflection.finish();
+
+    assert!(code.contains("pub struct TestVertex {\n    pub position: [f32; 2],\n"));
+    assert!(code.contains("    pub depth: f32,\n}"));
+    assert!(code.contains("format: wgpu::VertexFormat::Float32, offset: 8, shader_location: 2"));
+    assert!(code.contains("pub struct TestInstance {\n    pub color: [f32; 4],\n}"));
+    assert!(code.contains("step_mode: wgpu::VertexStepMode::Instance"));
+}